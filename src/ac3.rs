@@ -0,0 +1,188 @@
+use crate::bitcursor::{BitCursor, BitError};
+use crate::crc::crc16;
+
+/// Syncword of AC-3 and E-AC-3 syncframes
+pub const AC3_SYNCWORD: u16 = 0x0B77;
+
+/// Sampling frequencies in Hz by fscod
+const SAMPLE_RATES: [u32; 3] = [48000, 44100, 32000];
+/// Sampling frequencies of reduced rate E-AC-3 streams by fscod2
+const REDUCED_SAMPLE_RATES: [u32; 3] = [24000, 22050, 16000];
+
+/// Nominal bitrates in kbit/s by frmsizecod / 2
+const BITRATES: [u32; 19] = [
+    32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 576, 640,
+];
+
+/// Samples per channel in one audio block
+const BLOCK_SAMPLES: u32 = 256;
+
+/// Highest bsid of AC-3, E-AC-3 uses 11 to 16
+const MAX_AC3_BSID: u8 = 10;
+
+/// The fields of an AC-3 or E-AC-3 syncframe header, ATSC A/52
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct Ac3Header {
+    pub bsid: u8,
+    pub sample_rate: u32,
+    /// Length of the syncframe in bytes
+    pub frame_length: usize,
+    /// Number of audio blocks of 256 samples
+    pub blocks: u8,
+    /// Audio coding mode, the arrangement of the full bandwidth channels
+    pub acmod: u8,
+    pub lfeon: bool,
+    /// E-AC-3 stream type: 0 independent, 1 dependent, 2 converted AC-3
+    pub stream_type: Option<u8>,
+    pub substream_id: u8,
+}
+
+impl Ac3Header {
+    pub fn is_eac3(&self) -> bool {
+        self.bsid > MAX_AC3_BSID
+    }
+
+    /// Number of samples per channel in the syncframe
+    pub fn samples(&self) -> u32 {
+        u32::from(self.blocks) * BLOCK_SAMPLES
+    }
+
+    /// Duration of the syncframe in seconds
+    pub fn duration(&self) -> f64 {
+        f64::from(self.samples()) / f64::from(self.sample_rate)
+    }
+
+    /// Whether the syncframe carries the program's own audio, rather than
+    /// extra channels or programs in E-AC-3 substreams
+    pub fn is_main_substream(&self) -> bool {
+        self.stream_type != Some(1) && self.substream_id == 0
+    }
+
+    /// Human readable channel layout, including the LFE channel
+    pub fn channel_layout(&self) -> String {
+        let layout = match self.acmod {
+            0 => "1+1 (Ch1 Ch2)",
+            1 => "1/0 (C)",
+            2 => "2/0 (L R)",
+            3 => "3/0 (L C R)",
+            4 => "2/1 (L R S)",
+            5 => "3/1 (L C R S)",
+            6 => "2/2 (L R Ls Rs)",
+            _ => "3/2 (L C R Ls Rs)",
+        };
+        if self.lfeon {
+            format!("{} + LFE", layout)
+        } else {
+            layout.to_string()
+        }
+    }
+}
+
+/// Parse the AC-3 or E-AC-3 syncframe header at the start of `data`
+pub fn parse_header(data: &[u8]) -> Option<Ac3Header> {
+    if data.len() < 8 || u16::from_be_bytes([data[0], data[1]]) != AC3_SYNCWORD {
+        return None;
+    }
+    // bsid is at the same position in both
+    let bsid = data[5] >> 3;
+    let mut reader = BitCursor::new(data, 0);
+    let header = if bsid <= MAX_AC3_BSID {
+        parse_ac3(&mut reader, bsid)
+    } else if bsid <= 16 {
+        parse_eac3(&mut reader, bsid)
+    } else {
+        return None;
+    };
+    header.ok().flatten()
+}
+
+fn parse_ac3(reader: &mut BitCursor, bsid: u8) -> Result<Option<Ac3Header>, BitError> {
+    // syncword and crc1
+    reader.skip(16)?;
+    reader.skip(16)?;
+    let fscod = usize::from(reader.read_u8(2)?);
+    let frmsizecod = usize::from(reader.read_u8(6)?);
+    if fscod == 3 || frmsizecod >= 2 * BITRATES.len() {
+        return Ok(None);
+    }
+    let bitrate = BITRATES[frmsizecod / 2];
+    let words = match fscod {
+        0 => bitrate * 2,
+        1 => bitrate * 1000 * 1536 / 44100 / 16 + (frmsizecod as u32 & 1),
+        _ => bitrate * 3,
+    };
+    // Lower bsids are half and quarter sampling rate variants
+    let shift = bsid.saturating_sub(8);
+
+    // bsid, bsmod
+    reader.skip(5)?;
+    reader.skip(3)?;
+    let acmod = reader.read_u8(3)?;
+    if acmod & 0x01 != 0 && acmod != 1 {
+        // cmixlev
+        reader.skip(2)?;
+    }
+    if acmod & 0x04 != 0 {
+        // surmixlev
+        reader.skip(2)?;
+    }
+    if acmod == 2 {
+        // dsurmod
+        reader.skip(2)?;
+    }
+    let lfeon = reader.read_bool()?;
+
+    Ok(Some(Ac3Header {
+        bsid,
+        sample_rate: SAMPLE_RATES[fscod] >> shift,
+        frame_length: words as usize * 2,
+        blocks: 6,
+        acmod,
+        lfeon,
+        stream_type: None,
+        substream_id: 0,
+    }))
+}
+
+fn parse_eac3(reader: &mut BitCursor, bsid: u8) -> Result<Option<Ac3Header>, BitError> {
+    reader.skip(16)?;
+    let stream_type = reader.read_u8(2)?;
+    let substream_id = reader.read_u8(3)?;
+    let frmsiz = reader.read_u16(11)?;
+    let fscod = usize::from(reader.read_u8(2)?);
+    let (sample_rate, blocks) = if fscod == 3 {
+        let fscod2 = usize::from(reader.read_u8(2)?);
+        if fscod2 == 3 {
+            return Ok(None);
+        }
+        (REDUCED_SAMPLE_RATES[fscod2], 6)
+    } else {
+        let blocks = [1, 2, 3, 6][usize::from(reader.read_u8(2)?)];
+        (SAMPLE_RATES[fscod], blocks)
+    };
+    let acmod = reader.read_u8(3)?;
+    let lfeon = reader.read_bool()?;
+    if stream_type == 3 {
+        return Ok(None);
+    }
+
+    Ok(Some(Ac3Header {
+        bsid,
+        sample_rate,
+        frame_length: (usize::from(frmsiz) + 1) * 2,
+        blocks,
+        acmod,
+        lfeon,
+        stream_type: Some(stream_type),
+        substream_id,
+    }))
+}
+
+/// Check the CRC at the end of a whole syncframe
+///
+/// The CRC covers everything after the syncword, including itself, so the
+/// remainder is zero for an intact frame.
+pub fn check_crc(frame: &[u8]) -> bool {
+    frame.len() > 2 && crc16(0, &frame[2..]) == 0
+}
@@ -0,0 +1,81 @@
+use crate::bitcursor::{BitCursor, BitError};
+use crate::elements::{self, ProgramConfig};
+
+/// Start of an ADIF file
+pub const ADIF_ID: [u8; 4] = *b"ADIF";
+
+/// The fields of an adif_header, ISO 14496-3 1.A.2.1
+///
+/// ADIF has this single header at the start of the file. The raw data
+/// blocks after it have no framing, so its frames cannot be found without
+/// decoding.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct AdifHeader {
+    pub copyright_id: Option<[u8; 9]>,
+    pub original_copy: bool,
+    pub home: bool,
+    pub variable_bitrate: bool,
+    /// Bitrate in bit/s, the peak bitrate for variable bitrate streams
+    pub bitrate: u32,
+    pub programs: Vec<AdifProgram>,
+    /// Length of the header in bytes, where the raw data blocks start
+    pub header_length: usize,
+}
+
+/// A program defined by the ADIF header
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct AdifProgram {
+    /// Decoder buffer fullness at the start, only for constant bitrate
+    pub buffer_fullness: Option<u32>,
+    pub config: ProgramConfig,
+}
+
+pub fn is_adif(data: &[u8]) -> bool {
+    data.starts_with(&ADIF_ID)
+}
+
+/// Parse the ADIF header at the start of `data`, which must begin with
+/// `ADIF_ID`
+pub fn parse_header(data: &[u8]) -> Result<AdifHeader, BitError> {
+    let mut reader = BitCursor::new(data, 0);
+    reader.skip(32)?;
+    let copyright_id = if reader.read_bool()? {
+        let mut id = [0; 9];
+        for byte in id.iter_mut() {
+            *byte = reader.read_u8(8)?;
+        }
+        Some(id)
+    } else {
+        None
+    };
+    let original_copy = reader.read_bool()?;
+    let home = reader.read_bool()?;
+    let variable_bitrate = reader.read_bool()?;
+    let bitrate = reader.read_u32(23)?;
+    let count = reader.read_u8(4)? + 1;
+
+    let mut programs = Vec::new();
+    for _ in 0..count {
+        let buffer_fullness = if variable_bitrate {
+            None
+        } else {
+            Some(reader.read_u32(20)?)
+        };
+        programs.push(AdifProgram {
+            buffer_fullness,
+            config: elements::read_program_config(&mut reader)?,
+        });
+    }
+
+    Ok(AdifHeader {
+        copyright_id,
+        original_copy,
+        home,
+        variable_bitrate,
+        bitrate,
+        programs,
+        header_length: reader.position().div_ceil(8) as usize,
+    })
+}
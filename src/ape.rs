@@ -0,0 +1,104 @@
+use std::convert::TryInto;
+
+/// Start of the header and footer of an APE tag
+pub const APE_PREAMBLE: [u8; 8] = *b"APETAGEX";
+/// Length of the header, and of the footer
+pub const APE_FOOTER_LEN: usize = 32;
+
+/// The footer of an APEv1 or APEv2 tag, which sits at its end so it can be
+/// found from the end of a file
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct ApeFooter {
+    /// 1000 for APEv1, 2000 for APEv2
+    pub version: u32,
+    /// Length of the items and the footer, without the header
+    pub size: usize,
+    pub items: u32,
+    /// Whether a header comes before the items, only APEv2 tags have one
+    pub has_header: bool,
+}
+
+impl ApeFooter {
+    /// Length of the whole tag including header and footer
+    pub fn total_length(&self) -> usize {
+        let header = if self.has_header { APE_FOOTER_LEN } else { 0 };
+        header + self.size
+    }
+}
+
+/// Parse the APE tag footer at the start of `data`
+pub fn parse_footer(data: &[u8]) -> Option<ApeFooter> {
+    let footer = data.get(..APE_FOOTER_LEN)?;
+    let u32_at = |pos: usize| u32::from_le_bytes(footer[pos..pos + 4].try_into().unwrap());
+    let (version, size, flags) = (u32_at(8), u32_at(12) as usize, u32_at(20));
+    // The header has the same layout, with bit 29 set
+    if footer[..8] != APE_PREAMBLE || flags & 1 << 29 != 0 || size < APE_FOOTER_LEN {
+        return None;
+    }
+    Some(ApeFooter {
+        version,
+        size,
+        items: u32_at(16),
+        has_header: version >= 2000 && flags & 1 << 31 != 0,
+    })
+}
+
+/// An item of an APE tag
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ApeItem {
+    /// Key like Title or Artist, case is not significant
+    pub key: String,
+    /// Length of the value in bytes
+    pub length: usize,
+    /// Value of text items, several values separated by " / "
+    pub text: Option<String>,
+}
+
+/// Parse the items of the APE tag whose footer is at the end of `data`
+///
+/// `data` holds the tag without the header. Items that do not fit in it are
+/// left out.
+pub fn parse_items(data: &[u8], footer: &ApeFooter) -> Vec<ApeItem> {
+    let end = data.len().saturating_sub(APE_FOOTER_LEN);
+    let start = data.len().saturating_sub(footer.size);
+    let body = &data[start.min(end)..end];
+
+    let mut items = Vec::new();
+    let mut pos = 0;
+    while items.len() < footer.items as usize {
+        let header = match body.get(pos..pos + 8) {
+            Some(header) => header,
+            None => break,
+        };
+        let length = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let flags = u32::from_le_bytes(header[4..].try_into().unwrap());
+        let key_end = match body[pos + 8..].iter().position(|byte| *byte == 0) {
+            Some(len) => pos + 8 + len,
+            None => break,
+        };
+        let value = match body.get(key_end + 1..(key_end + 1).saturating_add(length)) {
+            Some(value) => value,
+            None => break,
+        };
+        // Bits 1 and 2 tell text from binary data and external locators,
+        // APEv1 only has text
+        let text = if footer.version < 2000 || flags >> 1 & 0x03 == 0 {
+            let values: Vec<String> = value
+                .split(|byte| *byte == 0)
+                .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+                .collect();
+            Some(values.join(" / "))
+        } else {
+            None
+        };
+        items.push(ApeItem {
+            key: String::from_utf8_lossy(&body[pos + 8..key_end]).into_owned(),
+            length,
+            text,
+        });
+        pos = key_end + 1 + length;
+    }
+    items
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+
+/// A read that ran past the end of the data available to a `BitCursor`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct BitError {
+    /// Absolute bit position the failed read started at
+    pub position: u64,
+    /// Number of bits that were requested
+    pub requested: u8,
+}
+
+impl fmt::Display for BitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "unexpected end of data reading {} bits at byte 0x{:X}, bit {}",
+            self.requested,
+            self.position / 8,
+            self.position % 8
+        )
+    }
+}
+
+impl std::error::Error for BitError {}
+
+/// MSB-first bit reader over a borrowed byte slice
+///
+/// Positions are tracked relative to the start of the source the slice was
+/// taken from, so errors can point at the exact bit in the input file.
+pub struct BitCursor<'a> {
+    data: &'a [u8],
+    /// Absolute bit position of `data[0]`
+    base: u64,
+    /// Bit position within `data`
+    pos: u64,
+}
+
+impl<'a> BitCursor<'a> {
+    /// Create a cursor over `data`, which starts at `byte_offset` in the source
+    pub fn new(data: &'a [u8], byte_offset: u64) -> BitCursor<'a> {
+        BitCursor {
+            data,
+            base: byte_offset * 8,
+            pos: 0,
+        }
+    }
+
+    /// Absolute bit position of the next read
+    pub fn position(&self) -> u64 {
+        self.base + self.pos
+    }
+
+    /// Read the next `bits` bits (at most 64) without advancing
+    pub fn peek(&self, bits: u8) -> Result<u64, BitError> {
+        assert!(bits <= 64, "cannot read more than 64 bits at once");
+
+        let error = BitError {
+            position: self.position(),
+            requested: bits,
+        };
+        if self.pos + u64::from(bits) > self.data.len() as u64 * 8 {
+            return Err(error);
+        }
+
+        let mut value: u64 = 0;
+        let mut pos = self.pos;
+        let mut left = bits;
+        while left > 0 {
+            let byte = self.data[(pos / 8) as usize];
+            let bit_in_byte = (pos % 8) as u8;
+            let take = left.min(8 - bit_in_byte);
+            let chunk = (byte >> (8 - bit_in_byte - take)) & (0xFF >> (8 - take));
+
+            value = (value << take) | u64::from(chunk);
+            pos += u64::from(take);
+            left -= take;
+        }
+        Ok(value)
+    }
+
+    pub fn read_u8(&mut self, bits: u8) -> Result<u8, BitError> {
+        assert!(bits <= 8);
+        self.read(bits).map(|v| v as u8)
+    }
+
+    pub fn read_u16(&mut self, bits: u8) -> Result<u16, BitError> {
+        assert!(bits <= 16);
+        self.read(bits).map(|v| v as u16)
+    }
+
+    pub fn read_u32(&mut self, bits: u8) -> Result<u32, BitError> {
+        assert!(bits <= 32);
+        self.read(bits).map(|v| v as u32)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, BitError> {
+        self.read(1).map(|v| v == 1)
+    }
+
+    pub fn skip(&mut self, bits: u8) -> Result<(), BitError> {
+        self.read(bits).map(|_| ())
+    }
+
+    fn read(&mut self, bits: u8) -> Result<u64, BitError> {
+        let value = self.peek(bits)?;
+        self.pos += u64::from(bits);
+        Ok(value)
+    }
+}
@@ -0,0 +1,95 @@
+/// Length of the windows whose bitrates decide between CBR and VBR, in
+/// seconds, long enough to even out the bit reservoir
+const WINDOW: f64 = 1.0;
+
+/// Largest deviation of a window bitrate from the average for CBR
+const CBR_TOLERANCE: f64 = 0.05;
+
+/// Bitrate of a frame of `length` bytes lasting `duration` seconds, in bit/s
+pub fn frame_bitrate(length: u16, duration: f64) -> f64 {
+    f64::from(length) * 8.0 / duration
+}
+
+/// Collects per-frame bitrates over a whole run
+pub struct BitrateStats {
+    bytes: u64,
+    duration: f64,
+    min: f64,
+    max: f64,
+    window_bytes: u64,
+    window_duration: f64,
+    window_min: f64,
+    window_max: f64,
+    windows: usize,
+}
+
+impl BitrateStats {
+    pub fn new() -> BitrateStats {
+        BitrateStats {
+            bytes: 0,
+            duration: 0.0,
+            min: f64::INFINITY,
+            max: 0.0,
+            window_bytes: 0,
+            window_duration: 0.0,
+            window_min: f64::INFINITY,
+            window_max: 0.0,
+            windows: 0,
+        }
+    }
+
+    pub fn push(&mut self, length: u16, duration: f64) {
+        let bitrate = frame_bitrate(length, duration);
+        self.bytes += u64::from(length);
+        self.duration += duration;
+        self.min = self.min.min(bitrate);
+        self.max = self.max.max(bitrate);
+
+        self.window_bytes += u64::from(length);
+        self.window_duration += duration;
+        if self.window_duration >= WINDOW {
+            let bitrate = self.window_bytes as f64 * 8.0 / self.window_duration;
+            self.window_min = self.window_min.min(bitrate);
+            self.window_max = self.window_max.max(bitrate);
+            self.windows += 1;
+            self.window_bytes = 0;
+            self.window_duration = 0.0;
+        }
+    }
+
+    /// Average bitrate in bit/s, if any frame was seen
+    pub fn average(&self) -> Option<f64> {
+        if self.duration > 0.0 {
+            Some(self.bytes as f64 * 8.0 / self.duration)
+        } else {
+            None
+        }
+    }
+
+    /// Bytes of all frames seen
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Largest deviation of a complete window from the average bitrate, as
+    /// a share of the average, or `None` if the run is too short to compare
+    /// windows
+    pub fn deviation(&self) -> Option<f64> {
+        let average = self.average()?;
+        if self.windows < 2 {
+            return None;
+        }
+        Some(f64::max(average - self.window_min, self.window_max - average) / average)
+    }
+
+    /// Lowest and highest bitrate of a single frame
+    pub fn min_max(&self) -> Option<(f64, f64)> {
+        self.average().map(|_| (self.min, self.max))
+    }
+
+    /// Whether every complete window stays within the CBR tolerance of the
+    /// average, or `None` if the run is too short to compare windows
+    pub fn is_cbr(&self) -> Option<bool> {
+        self.deviation().map(|deviation| deviation <= CBR_TOLERANCE)
+    }
+}
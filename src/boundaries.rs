@@ -0,0 +1,64 @@
+use std::io::{self, Write};
+
+use crate::json;
+use crate::timefmt::TimeFormat;
+
+/// Writes frame boundaries as JSON, for editors that snap cuts onto them
+///
+/// The output is a single object with the input name and a `frames` array
+/// of one object per frame, giving its byte range, start time and first
+/// sample.
+pub struct BoundaryWriter<W: Write> {
+    out: W,
+    frames: u64,
+    time_format: TimeFormat,
+}
+
+impl<W: Write> BoundaryWriter<W> {
+    pub fn new(
+        mut out: W,
+        file_name: &str,
+        time_format: TimeFormat,
+    ) -> io::Result<BoundaryWriter<W>> {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"file\": {},", json::string(file_name))?;
+        write!(out, "  \"frames\": [")?;
+        Ok(BoundaryWriter {
+            out,
+            frames: 0,
+            time_format,
+        })
+    }
+
+    pub fn frame(
+        &mut self,
+        offset: u64,
+        length: u16,
+        time: f64,
+        sample: u64,
+        samples: u32,
+        id: Option<&str>,
+    ) -> io::Result<()> {
+        let separator = if self.frames == 0 { "" } else { "," };
+        let id = id.map_or_else(String::new, |id| format!("\"id\": {}, ", json::string(id)));
+        write!(
+            self.out,
+            "{}\n    {{{}\"offset\": {}, \"length\": {}, \"time\": {}, \"sample\": {}, \"samples\": {}}}",
+            separator,
+            id,
+            offset,
+            length,
+            self.time_format.format_json(time),
+            sample,
+            samples
+        )?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        writeln!(self.out, "\n  ]")?;
+        writeln!(self.out, "}}")?;
+        self.out.flush()
+    }
+}
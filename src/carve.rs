@@ -0,0 +1,75 @@
+/// A run of consecutive valid frames with the same stream parameters
+#[derive(Debug)]
+pub struct Run {
+    pub start: u64,
+    /// Offset just after the last frame
+    pub end: u64,
+    pub frames: usize,
+    /// Duration in seconds
+    pub duration: f64,
+    /// Description of the stream parameters, runs with different ones never
+    /// belong to the same stream
+    pub format: String,
+}
+
+/// Runs that are assumed to be fragments of one recovered stream
+#[derive(Debug)]
+pub struct Candidate {
+    pub runs: Vec<Run>,
+}
+
+impl Candidate {
+    pub fn start(&self) -> u64 {
+        self.runs[0].start
+    }
+
+    pub fn end(&self) -> u64 {
+        self.runs[self.runs.len() - 1].end
+    }
+
+    pub fn frames(&self) -> usize {
+        self.runs.iter().map(|run| run.frames).sum()
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.runs.iter().map(|run| run.duration).sum()
+    }
+
+    pub fn format(&self) -> &str {
+        &self.runs[0].format
+    }
+
+    /// Confidence from 0 to 100 that this is a real stream
+    ///
+    /// Streams that fill most of the range they span and last longer than
+    /// `CONFIDENT_DURATION` score high, short runs scattered between other
+    /// data score low.
+    pub fn confidence(&self) -> u8 {
+        let bytes: u64 = self.runs.iter().map(|run| run.end - run.start).sum();
+        let coverage = bytes as f64 / (self.end() - self.start()) as f64;
+        let length = (self.duration() / CONFIDENT_DURATION).min(1.0);
+        (100.0 * coverage * (0.5 + 0.5 * length)).round() as u8
+    }
+}
+
+/// Duration in seconds from which length no longer adds to the confidence
+const CONFIDENT_DURATION: f64 = 30.0;
+
+/// Group runs, sorted by offset, into stream candidates
+///
+/// A run continues the latest candidate with the same format that ended at
+/// most `max_gap` bytes before it, so streams stay together across small
+/// fragmentation gaps and interleaved data of other streams.
+pub fn group(runs: Vec<Run>, max_gap: u64) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = Vec::new();
+    for run in runs {
+        let continued = candidates.iter_mut().rev().find(|candidate| {
+            candidate.format() == run.format && run.start - candidate.end() <= max_gap
+        });
+        match continued {
+            Some(candidate) => candidate.runs.push(run),
+            None => candidates.push(Candidate { runs: vec![run] }),
+        }
+    }
+    candidates
+}
@@ -0,0 +1,156 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Error, ErrorKind, Write};
+use std::path::Path;
+
+use crate::verdict::{ParameterChange, Resync};
+
+const MAGIC: &str = "streamreader-checkpoint 1";
+
+/// Position and accumulated statistics of a scan, enough to resume it
+#[derive(Debug)]
+pub struct Progress {
+    /// Offset where the next frame header is expected
+    pub next_offset: u64,
+    pub frames: usize,
+    /// Start time of the next frame in seconds
+    pub time: f64,
+    pub header_warnings: u64,
+    pub mpeg_audio_frames: usize,
+    pub parameter_changes: Vec<ParameterChange>,
+    pub resyncs: Vec<Resync>,
+    pub crc: CrcCounts,
+    /// Number of frames whose first channel element contradicts the channel
+    /// configuration
+    pub element_mismatches: u64,
+}
+
+/// Results of checking the CRC of protected frames
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CrcCounts {
+    pub valid: u64,
+    pub mismatches: u64,
+    pub unverifiable: u64,
+}
+
+impl Progress {
+    pub fn new(start: u64) -> Progress {
+        Progress {
+            next_offset: start,
+            frames: 0,
+            time: 0.0,
+            header_warnings: 0,
+            mpeg_audio_frames: 0,
+            parameter_changes: Vec::new(),
+            resyncs: Vec::new(),
+            crc: CrcCounts::default(),
+            element_mismatches: 0,
+        }
+    }
+}
+
+/// Write a checkpoint for a scan over an input of `file_length` bytes
+///
+/// The checkpoint is written to a temporary file first and renamed, so an
+/// interrupted write never leaves a broken checkpoint behind.
+pub fn save(path: &Path, file_length: u64, progress: &Progress) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    let mut out = io::BufWriter::new(fs::File::create(&tmp_path)?);
+    writeln!(out, "{}", MAGIC)?;
+    writeln!(out, "file_length={}", file_length)?;
+    writeln!(out, "next_offset={}", progress.next_offset)?;
+    writeln!(out, "frames={}", progress.frames)?;
+    writeln!(out, "time={}", progress.time)?;
+    writeln!(out, "header_warnings={}", progress.header_warnings)?;
+    writeln!(out, "mpeg_audio_frames={}", progress.mpeg_audio_frames)?;
+    writeln!(out, "crc_valid={}", progress.crc.valid)?;
+    writeln!(out, "crc_mismatches={}", progress.crc.mismatches)?;
+    writeln!(out, "crc_unverifiable={}", progress.crc.unverifiable)?;
+    writeln!(out, "element_mismatches={}", progress.element_mismatches)?;
+    for change in progress.parameter_changes.iter() {
+        writeln!(
+            out,
+            "parameter_change={} {} {}",
+            change.offset, change.time, change.description
+        )?;
+    }
+    for resync in progress.resyncs.iter() {
+        writeln!(
+            out,
+            "resync={} {} {}",
+            resync.offset, resync.time, resync.skipped
+        )?;
+    }
+    out.flush()?;
+    drop(out);
+
+    fs::rename(&tmp_path, path)
+}
+
+/// Read a checkpoint, returning the input length it was written for and
+/// the progress to resume from
+pub fn load(path: &Path) -> io::Result<(u64, Progress)> {
+    let mut lines = BufReader::new(fs::File::open(path)?).lines();
+    if lines.next().transpose()?.as_deref() != Some(MAGIC) {
+        return Err(invalid("not a streamreader checkpoint"));
+    }
+
+    let mut file_length = None;
+    let mut progress = Progress::new(0);
+    for line in lines {
+        let line = line?;
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| invalid("malformed line"))?;
+        match key {
+            "file_length" => file_length = Some(parse(value)?),
+            "next_offset" => progress.next_offset = parse(value)?,
+            "frames" => progress.frames = parse(value)?,
+            "time" => progress.time = parse(value)?,
+            "header_warnings" => progress.header_warnings = parse(value)?,
+            "mpeg_audio_frames" => progress.mpeg_audio_frames = parse(value)?,
+            "crc_valid" => progress.crc.valid = parse(value)?,
+            "crc_mismatches" => progress.crc.mismatches = parse(value)?,
+            "crc_unverifiable" => progress.crc.unverifiable = parse(value)?,
+            "element_mismatches" => progress.element_mismatches = parse(value)?,
+            "parameter_change" => {
+                let mut parts = value.splitn(3, ' ');
+                let offset = parse(parts.next().unwrap_or(""))?;
+                let time = parse(parts.next().unwrap_or(""))?;
+                let description = parts.next().unwrap_or("").to_string();
+                progress.parameter_changes.push(ParameterChange {
+                    offset,
+                    time,
+                    description,
+                });
+            }
+            "resync" => {
+                let mut parts = value.splitn(3, ' ');
+                let offset = parse(parts.next().unwrap_or(""))?;
+                let time = parse(parts.next().unwrap_or(""))?;
+                let skipped = parse(parts.next().unwrap_or(""))?;
+                progress.resyncs.push(Resync {
+                    offset,
+                    time,
+                    skipped,
+                });
+            }
+            _ => return Err(invalid("unknown key")),
+        }
+    }
+
+    let file_length = file_length.ok_or_else(|| invalid("missing file_length"))?;
+    Ok((file_length, progress))
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> io::Result<T> {
+    value.parse().map_err(|_| invalid("malformed value"))
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid checkpoint: {}", message),
+    )
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::sha256::sha256;
+
+const MAGIC: &str = "streamreader-chunks 1";
+
+/// Chunks are never cut before this many bytes
+const MIN_CHUNK_LEN: usize = 16 * 1024;
+/// Chunks are always cut after this many bytes
+const MAX_CHUNK_LEN: usize = 256 * 1024;
+/// A chunk ends after a frame whose payload hash has these bits clear,
+/// for chunks of 64 frames on average
+const CUT_MASK: u64 = 0x3F;
+
+/// Splits the frames of a run into content-addressed chunks for archival
+///
+/// Cuts depend only on the payloads of the frames right before them, so
+/// recordings sharing a long run of frames, like the same jingle or ad,
+/// also share the chunks inside it. Every chunk is stored once in the
+/// output directory, named by the SHA-256 of its contents, and a
+/// `manifest` lists the chunks of this run in order. Concatenating them
+/// gives the frames of the input without the data between them.
+pub struct ChunkWriter {
+    dir: PathBuf,
+    manifest: BufWriter<fs::File>,
+    chunk: Vec<u8>,
+    /// Input offset of the first frame in the chunk
+    offset: u64,
+    frames: usize,
+    chunks: u64,
+    stored: u64,
+}
+
+impl ChunkWriter {
+    pub fn new(dir: &Path) -> io::Result<ChunkWriter> {
+        fs::create_dir_all(dir)?;
+        let mut manifest = BufWriter::new(fs::File::create(dir.join("manifest"))?);
+        writeln!(manifest, "{}", MAGIC)?;
+        Ok(ChunkWriter {
+            dir: dir.to_path_buf(),
+            manifest,
+            chunk: Vec::new(),
+            offset: 0,
+            frames: 0,
+            chunks: 0,
+            stored: 0,
+        })
+    }
+
+    /// Add the frame at `offset`, whose payload has `payload_hash`
+    pub fn frame(&mut self, offset: u64, frame: &[u8], payload_hash: u64) -> io::Result<()> {
+        if self.chunk.is_empty() {
+            self.offset = offset;
+        }
+        self.chunk.extend_from_slice(frame);
+        self.frames += 1;
+
+        let cut = payload_hash & CUT_MASK == 0 && self.chunk.len() >= MIN_CHUNK_LEN;
+        if cut || self.chunk.len() >= MAX_CHUNK_LEN {
+            self.cut()?;
+        }
+        Ok(())
+    }
+
+    fn cut(&mut self) -> io::Result<()> {
+        let name: String = sha256(&self.chunk)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        let path = self.dir.join(&name);
+        if !path.exists() {
+            // Written under a temporary name first, so an interrupted run
+            // never leaves a chunk with the wrong contents behind
+            let tmp_path = self.dir.join(format!("{}.tmp", name));
+            fs::write(&tmp_path, &self.chunk)?;
+            fs::rename(&tmp_path, &path)?;
+            self.stored += 1;
+        }
+        writeln!(
+            self.manifest,
+            "{} {} {} {}",
+            name,
+            self.offset,
+            self.chunk.len(),
+            self.frames
+        )?;
+        self.chunks += 1;
+        self.chunk.clear();
+        self.frames = 0;
+        Ok(())
+    }
+
+    /// Write the last chunk, returning the number of chunks in the manifest
+    /// and how many of them were not stored before
+    pub fn finish(mut self) -> io::Result<(u64, u64)> {
+        if !self.chunk.is_empty() {
+            self.cut()?;
+        }
+        self.manifest.flush()?;
+        Ok((self.chunks, self.stored))
+    }
+}
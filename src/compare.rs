@@ -0,0 +1,181 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+
+use crate::json::{self, Value};
+
+/// The objects of a JSON report written with --format json
+pub struct Report {
+    pub frames: Vec<Value>,
+    /// The summary and all other objects, in the order they were written
+    pub others: Vec<Value>,
+}
+
+impl Report {
+    /// Read the report at `path`, one object per line
+    pub fn load(path: &Path) -> io::Result<Report> {
+        let mut report = Report {
+            frames: Vec::new(),
+            others: Vec::new(),
+        };
+        for (index, line) in fs::read_to_string(path)?.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let value = json::parse(line).map_err(|err| {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("line {}: {}", index + 1, err),
+                )
+            })?;
+            match value.get("type").and_then(Value::as_str) {
+                Some("frame") => report.frames.push(value),
+                Some(_) => report.others.push(value),
+                None => {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("line {}: object without a type", index + 1),
+                    ))
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Whether the frames carry the IDs of --frame-ids, with their payload
+    /// hashes
+    pub fn has_frame_ids(&self) -> bool {
+        self.frames.iter().all(|frame| frame.get("id").is_some()) && !self.frames.is_empty()
+    }
+}
+
+/// A value that changed between two reports
+pub struct Difference {
+    /// Where the value is, like "summary.crc.mismatches"
+    pub path: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Everything that changed from `old` to `new`
+///
+/// Frames are compared by position, with their payload hashes instead of
+/// their whole frame IDs, which also hold the input name. The other
+/// objects are paired up by type.
+pub fn compare(old: &Report, new: &Report) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    if old.frames.len() != new.frames.len() {
+        differences.push(Difference {
+            path: "frames".to_string(),
+            old: Some(old.frames.len().to_string()),
+            new: Some(new.frames.len().to_string()),
+        });
+    }
+    for (index, (old, new)) in old.frames.iter().zip(new.frames.iter()).enumerate() {
+        let path = format!("frame {}", index);
+        let hashes = (payload_hash(old), payload_hash(new));
+        if let (Some(old), Some(new)) = hashes {
+            if old != new {
+                differences.push(Difference {
+                    path: format!("{}.hash", path),
+                    old: Some(old.to_string()),
+                    new: Some(new.to_string()),
+                });
+            }
+        }
+        diff(&path, &without_id(old), &without_id(new), &mut differences);
+    }
+
+    let mut unpaired: Vec<&Value> = new.others.iter().collect();
+    for old in old.others.iter() {
+        let kind = old.get("type").and_then(Value::as_str).unwrap_or_default();
+        let paired = unpaired
+            .iter()
+            .position(|new| new.get("type").and_then(Value::as_str) == Some(kind));
+        match paired {
+            Some(index) => diff(kind, old, unpaired.remove(index), &mut differences),
+            None => differences.push(Difference {
+                path: kind.to_string(),
+                old: Some(old.to_string()),
+                new: None,
+            }),
+        }
+    }
+    for new in unpaired {
+        differences.push(Difference {
+            path: new
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            old: None,
+            new: Some(new.to_string()),
+        });
+    }
+    differences
+}
+
+/// The payload hash at the end of a frame ID
+fn payload_hash(frame: &Value) -> Option<&str> {
+    let id = frame.get("id")?.as_str()?;
+    id.rsplit('#').next()
+}
+
+fn without_id(frame: &Value) -> Value {
+    match frame {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .filter(|(name, _)| name != "id")
+                .cloned()
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Add the differences between `old` and `new` at `path`
+fn diff(path: &str, old: &Value, new: &Value, differences: &mut Vec<Difference>) {
+    match (old, new) {
+        (Value::Object(old_fields), Value::Object(new_fields)) => {
+            for (name, old) in old_fields.iter() {
+                let path = format!("{}.{}", path, name);
+                match new.get(name) {
+                    Some(new) => diff(&path, old, new, differences),
+                    None => differences.push(Difference {
+                        path,
+                        old: Some(old.to_string()),
+                        new: None,
+                    }),
+                }
+            }
+            for (name, new) in new_fields.iter() {
+                if old.get(name).is_none() {
+                    differences.push(Difference {
+                        path: format!("{}.{}", path, name),
+                        old: None,
+                        new: Some(new.to_string()),
+                    });
+                }
+            }
+        }
+        (Value::Array(old_values), Value::Array(new_values)) => {
+            if old_values.len() != new_values.len() {
+                differences.push(Difference {
+                    path: format!("{} entries", path),
+                    old: Some(old_values.len().to_string()),
+                    new: Some(new_values.len().to_string()),
+                });
+            }
+            for (index, (old, new)) in old_values.iter().zip(new_values.iter()).enumerate() {
+                diff(&format!("{}[{}]", path, index), old, new, differences);
+            }
+        }
+        _ if old != new => differences.push(Difference {
+            path: path.to_string(),
+            old: Some(old.to_string()),
+            new: Some(new.to_string()),
+        }),
+        _ => {}
+    }
+}
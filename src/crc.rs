@@ -0,0 +1,186 @@
+/// Number of raw data block bits protected for a single_channel_element or
+/// lfe_channel_element, ISO 13818-7
+const PROTECTED_ELEMENT_BITS: usize = 192;
+
+/// CRC-16 as used by MPEG audio, ISO 11172-3 2.4.3.1
+///
+/// Polynomial x^16 + x^15 + x^2 + 1, initial value 0xFFFF, MSB first.
+pub fn crc16(crc: u16, data: &[u8]) -> u16 {
+    data.iter().fold(crc, |mut crc, byte| {
+        crc ^= u16::from(*byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// CRC-8 of FLAC frame headers
+///
+/// Polynomial x^8 + x^2 + x + 1, initial value 0, MSB first.
+pub fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |mut crc, byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// CRC-32 of MPEG-2 PSI sections, ISO 13818-1 annex A, and Ogg pages
+///
+/// Polynomial 0x04C11DB7, MSB first. PSI sections start from 0xFFFFFFFF
+/// and the CRC is 0 over a whole section including its CRC field, Ogg
+/// pages start from 0.
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |mut crc, byte| {
+        crc ^= u32::from(*byte) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// CRC of an ADTS frame whose raw data block starts with a single
+/// channel or LFE element
+///
+/// `header` holds the 56 header bits without the CRC field. For those
+/// elements only their first 192 bits are protected, shorter elements
+/// are padded with zero bits.
+pub fn adts_crc(header: &[u8], raw_data: &[u8]) -> u16 {
+    let protected = PROTECTED_ELEMENT_BITS / 8;
+    let mut element = [0; PROTECTED_ELEMENT_BITS / 8];
+    let len = raw_data.len().min(protected);
+    element[..len].copy_from_slice(&raw_data[..len]);
+
+    crc16(crc16(0xFFFF, header), &element)
+}
+
+/// Outcome of checking the CRC of a protected ADTS frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum CrcCheck {
+    Valid,
+    Mismatch {
+        stored: u16,
+        computed: u16,
+    },
+    /// The CRC protects parts of the raw data that are not located here,
+    /// like the channels of a channel pair element or several raw data
+    /// blocks
+    Unverifiable,
+}
+
+/// Check the CRC of a whole ADTS frame with `protection_absent` unset
+pub fn check_adts_frame(frame: &[u8]) -> CrcCheck {
+    const SCE: u8 = 0;
+    const LFE: u8 = 3;
+
+    let raw_data = match frame.get(9..) {
+        Some(raw_data) if !raw_data.is_empty() => raw_data,
+        _ => return CrcCheck::Unverifiable,
+    };
+    let raw_data_blocks = frame[6] & 0x03;
+    let element = raw_data[0] >> 5;
+    if raw_data_blocks != 0 || (element != SCE && element != LFE) {
+        return CrcCheck::Unverifiable;
+    }
+
+    let stored = u16::from_be_bytes([frame[7], frame[8]]);
+    let computed = adts_crc(&frame[..7], raw_data);
+    if stored == computed {
+        CrcCheck::Valid
+    } else {
+        CrcCheck::Mismatch { stored, computed }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::tests::adts_frame;
+
+    #[test]
+    fn check_values() {
+        let check = b"123456789";
+        assert_eq!(crc16(0xFFFF, check), 0xAEE7);
+        assert_eq!(crc8(check), 0xF4);
+        assert_eq!(crc32(0xFFFF_FFFF, check), 0x0376_E6E7);
+    }
+
+    #[test]
+    fn psi_section_sums_to_zero() {
+        let mut section = vec![0x00, 0xB0, 0x0D, 0x00, 0x01, 0xC1, 0x00, 0x00];
+        let crc = crc32(0xFFFF_FFFF, &section);
+        section.extend_from_slice(&crc.to_be_bytes());
+        assert_eq!(crc32(0xFFFF_FFFF, &section), 0);
+    }
+
+    /// A protected frame with a single channel element and a valid CRC
+    fn protected_frame() -> Vec<u8> {
+        let mut frame = adts_frame(64, true);
+        frame[6] &= !0x03;
+        for (i, byte) in frame[9..].iter_mut().enumerate() {
+            *byte = (i as u8).wrapping_mul(7) & 0x1F;
+        }
+        let crc = adts_crc(&frame[..7], &frame[9..]);
+        frame[7..9].copy_from_slice(&crc.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn checks_single_channel_frames() {
+        let mut frame = protected_frame();
+        assert_eq!(check_adts_frame(&frame), CrcCheck::Valid);
+
+        let stored = u16::from_be_bytes([frame[7], frame[8]]);
+        frame[10] ^= 0x01;
+        match check_adts_frame(&frame) {
+            CrcCheck::Mismatch {
+                stored: found,
+                computed,
+            } => {
+                assert_eq!(found, stored);
+                assert_ne!(computed, stored);
+            }
+            other => panic!("expected a mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn only_the_protected_bits_count() {
+        let mut frame = protected_frame();
+        let last = frame.len() - 1;
+        frame[last] ^= 0xFF;
+        assert_eq!(check_adts_frame(&frame), CrcCheck::Valid);
+    }
+
+    #[test]
+    fn other_frames_are_unverifiable() {
+        // A channel pair element
+        let mut frame = protected_frame();
+        frame[9] = 0x20;
+        assert_eq!(check_adts_frame(&frame), CrcCheck::Unverifiable);
+
+        // Several raw data blocks
+        let mut frame = protected_frame();
+        frame[6] |= 0x01;
+        assert_eq!(check_adts_frame(&frame), CrcCheck::Unverifiable);
+
+        assert_eq!(check_adts_frame(&frame[..9]), CrcCheck::Unverifiable);
+    }
+}
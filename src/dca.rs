@@ -0,0 +1,63 @@
+use streamreader::dts;
+
+use crate::walk::Frame;
+
+/// Parse the DTS core frame or DTS-HD extension substream at the start of
+/// `data`
+pub fn parse_frame(data: &[u8]) -> Option<Frame> {
+    if let Some(substream) = dts::parse_substream_header(data) {
+        // The substream belongs to the core frame before it, or to the
+        // previous substream in a stream without a core
+        return Some(Frame {
+            length: substream.frame_length,
+            duration: 0.0,
+            lines: vec![
+                format!("Len is {}", substream.frame_length),
+                format!("Extension substream is {}", substream.index),
+                format!("Substream header length is {}", substream.header_length),
+            ],
+            warnings: Vec::new(),
+            advertised: None,
+        });
+    }
+
+    let header = dts::parse_header(data)?;
+    let mut lines = vec![
+        format!("Len is {}", header.frame_length),
+        format!("Packing is {}", header.packing.name()),
+        format!(
+            "Frame type is {}",
+            if header.termination {
+                "termination"
+            } else {
+                "normal"
+            }
+        ),
+        format!("Samples is {}", header.samples()),
+        format!("Sampling frequency is {} Hz", header.sample_rate),
+    ];
+    match header.bitrate {
+        Some(bitrate) => lines.push(format!("Bitrate is {} kbit/s", f64::from(bitrate) / 1000.0)),
+        None => lines.push("Bitrate is open, variable or lossless".to_string()),
+    }
+    lines.push(format!(
+        "Channel arrangement is amode {}, {}",
+        header.amode,
+        header.channel_arrangement()
+    ));
+    lines.push(format!(
+        "CRC present: {}",
+        if header.crc_present { "yes" } else { "no" }
+    ));
+    if let Some(extension) = header.extension_name() {
+        lines.push(format!("Extension audio is {}", extension));
+    }
+
+    Some(Frame {
+        length: header.frame_length,
+        duration: header.duration(),
+        lines,
+        warnings: Vec::new(),
+        advertised: None,
+    })
+}
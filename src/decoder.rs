@@ -0,0 +1,74 @@
+use std::io::{self, ErrorKind, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+
+/// Feeds ADTS frames into an external decoder reading from its stdin
+pub struct Decoder {
+    child: Child,
+    /// `None` once the decoder stopped reading
+    stdin: Option<ChildStdin>,
+    /// Only frames starting in this time range in seconds are passed on
+    range: Option<(f64, f64)>,
+}
+
+impl Decoder {
+    /// Start `command` through the shell
+    pub fn spawn(command: &str, range: Option<(f64, f64)>) -> io::Result<Decoder> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take();
+        Ok(Decoder {
+            child,
+            stdin,
+            range,
+        })
+    }
+
+    /// Pass on a complete frame starting at `time`
+    ///
+    /// A decoder that exits early, for example because its window was
+    /// closed, is not an error; it just gets no further frames.
+    pub fn frame(&mut self, time: f64, frame: &[u8]) -> io::Result<()> {
+        if let Some((start, end)) = self.range {
+            if time < start || time >= end {
+                return Ok(());
+            }
+        }
+        let stdin = match self.stdin.as_mut() {
+            Some(stdin) => stdin,
+            None => return Ok(()),
+        };
+        match stdin.write_all(frame) {
+            Err(ref err) if err.kind() == ErrorKind::BrokenPipe => {
+                self.stdin = None;
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Close the decoder's input and wait for it to exit
+    pub fn finish(mut self) -> io::Result<ExitStatus> {
+        drop(self.stdin.take());
+        self.child.wait()
+    }
+}
+
+/// Parse a time range given as `START-END` in seconds
+pub fn parse_range(value: &str) -> Result<(f64, f64), String> {
+    let invalid = || {
+        format!(
+            "invalid time range '{}', expected START-END in seconds",
+            value
+        )
+    };
+    let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+    let start: f64 = start.parse().map_err(|_| invalid())?;
+    let end: f64 = end.parse().map_err(|_| invalid())?;
+    if start < 0.0 || end <= start {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
@@ -0,0 +1,119 @@
+use std::io::{self, Write};
+
+use structopt::clap::{App, ArgSettings};
+
+use crate::json;
+use crate::output;
+
+/// Input formats the build can read, the values of --input-format
+const INPUT_FORMATS: [&str; 8] = ["adts", "mp3", "ac3", "dts", "flac", "loas", "ts", "ogg"];
+/// Output formats the build can write besides the text report
+const OUTPUT_FORMATS: [&str; 7] = [
+    "json",
+    "csv",
+    "cue",
+    "matroska-chapters",
+    "boundaries-json",
+    "loas",
+    "mp4",
+];
+
+/// Write a JSON description of the command line interface of `app`
+///
+/// clap 2 has no public API for listing arguments, so this reads the
+/// parser fields it exposes for its own macros.
+pub fn write<W: Write>(mut out: W, app: &App) -> io::Result<()> {
+    let parser = &app.p;
+
+    writeln!(out, "{{")?;
+    writeln!(out, "  \"name\": {},", json::string(app.get_name()))?;
+    writeln!(
+        out,
+        "  \"version\": {},",
+        json::string(env!("CARGO_PKG_VERSION"))
+    )?;
+    writeln!(out, "  \"subcommands\": [],")?;
+
+    let positionals: Vec<String> = parser
+        .positionals
+        .values()
+        .map(|arg| {
+            format!(
+                "{{\"name\": {}, \"help\": {}, \"default\": {}}}",
+                json::string(arg.b.name),
+                optional(arg.b.help),
+                optional(
+                    arg.v
+                        .default_val
+                        .map(|value| value.to_string_lossy())
+                        .as_deref()
+                )
+            )
+        })
+        .collect();
+    write_list(&mut out, "positionals", &positionals)?;
+
+    let flags: Vec<String> = parser
+        .flags
+        .iter()
+        .map(|arg| {
+            format!(
+                "{{\"long\": {}, \"help\": {}}}",
+                optional(arg.s.long),
+                optional(arg.b.help)
+            )
+        })
+        .collect();
+    write_list(&mut out, "flags", &flags)?;
+
+    let options: Vec<String> = parser
+        .opts
+        .iter()
+        .map(|arg| {
+            format!(
+                "{{\"long\": {}, \"help\": {}, \"default\": {}, \"repeatable\": {}}}",
+                optional(arg.s.long),
+                optional(arg.b.help),
+                optional(
+                    arg.v
+                        .default_val
+                        .map(|value| value.to_string_lossy())
+                        .as_deref()
+                ),
+                arg.b.is_set(ArgSettings::Multiple)
+            )
+        })
+        .collect();
+    write_list(&mut out, "options", &options)?;
+
+    let quoted = |formats: &[&str]| -> Vec<String> {
+        formats.iter().map(|format| json::string(format)).collect()
+    };
+    writeln!(
+        out,
+        "  \"input_formats\": [{}],",
+        quoted(&INPUT_FORMATS).join(", ")
+    )?;
+    writeln!(
+        out,
+        "  \"output_formats\": [{}],",
+        quoted(&OUTPUT_FORMATS).join(", ")
+    )?;
+    let versions: Vec<String> = output::OUTPUT_VERSIONS.iter().map(u32::to_string).collect();
+    writeln!(out, "  \"output_versions\": [{}]", versions.join(", "))?;
+    writeln!(out, "}}")?;
+    out.flush()
+}
+
+fn write_list<W: Write>(out: &mut W, name: &str, entries: &[String]) -> io::Result<()> {
+    writeln!(out, "  \"{}\": [", name)?;
+    for (index, entry) in entries.iter().enumerate() {
+        let separator = if index + 1 < entries.len() { "," } else { "" };
+        writeln!(out, "    {}{}", entry, separator)?;
+    }
+    writeln!(out, "  ],")
+}
+
+fn optional(value: Option<&str>) -> String {
+    value.map_or_else(|| "null".to_string(), json::string)
+}
@@ -0,0 +1,148 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use streamreader::crc::{self, CrcCheck};
+
+use crate::hash::fnv1a;
+
+/// Frames that may wait for a worker, per worker
+const QUEUE_DEPTH: usize = 16;
+
+/// Payload hash and CRC check of a frame
+pub struct Digest {
+    pub hash: u64,
+    /// Result of the CRC check, if it was asked for
+    pub crc: Option<CrcCheck>,
+}
+
+struct Job {
+    index: u64,
+    frame: Vec<u8>,
+    header_length: usize,
+    check_crc: bool,
+}
+
+/// Hashes frame payloads and checks their CRCs on worker threads, so the
+/// parsing thread only has to read
+///
+/// Digests come back in the order their frames were submitted, each with
+/// the context given along with its frame, for the loop detector and
+/// reference matcher that need the frames in stream order.
+pub struct DigestPool<T> {
+    jobs: Option<SyncSender<Job>>,
+    results: Receiver<(u64, Digest, Vec<u8>)>,
+    workers: Vec<JoinHandle<()>>,
+    /// Contexts of the frames whose digests were not returned yet
+    contexts: VecDeque<T>,
+    /// Digests done before the one of an earlier frame
+    early: BTreeMap<u64, Digest>,
+    submitted: u64,
+    returned: u64,
+    /// Frame copies the workers are done with, to be reused
+    buffers: Vec<Vec<u8>>,
+}
+
+impl<T> DigestPool<T> {
+    pub fn new(threads: usize) -> DigestPool<T> {
+        let (jobs, queue) = mpsc::sync_channel::<Job>(threads * QUEUE_DEPTH);
+        let queue = Arc::new(Mutex::new(queue));
+        let (done, results) = mpsc::channel();
+        let workers = (0..threads)
+            .map(|_| {
+                let queue = Arc::clone(&queue);
+                let done = done.clone();
+                thread::spawn(move || loop {
+                    // The lock is only held while waiting for a job
+                    let job = match queue.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    let digest = Digest {
+                        hash: fnv1a(&job.frame[job.header_length..]),
+                        crc: if job.check_crc {
+                            Some(crc::check_adts_frame(&job.frame))
+                        } else {
+                            None
+                        },
+                    };
+                    if done.send((job.index, digest, job.frame)).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        DigestPool {
+            jobs: Some(jobs),
+            results,
+            workers,
+            contexts: VecDeque::new(),
+            early: BTreeMap::new(),
+            submitted: 0,
+            returned: 0,
+            buffers: Vec::new(),
+        }
+    }
+
+    /// Queue a copy of `frame`, waiting while the queue is full
+    pub fn submit(&mut self, frame: &[u8], header_length: usize, check_crc: bool, context: T) {
+        let mut copy = self.buffers.pop().unwrap_or_default();
+        copy.clear();
+        copy.extend_from_slice(frame);
+        let job = Job {
+            index: self.submitted,
+            frame: copy,
+            header_length,
+            check_crc,
+        };
+        self.contexts.push_back(context);
+        self.submitted += 1;
+        let jobs = self.jobs.as_ref().expect("digest pool is shut down");
+        jobs.send(job).expect("digest workers stopped");
+    }
+
+    /// The digests done so far whose earlier frames are all done, with
+    /// their contexts
+    pub fn ready(&mut self) -> Vec<(T, Digest)> {
+        while let Ok(result) = self.results.try_recv() {
+            self.store(result);
+        }
+        self.take()
+    }
+
+    /// Wait for the digests of all frames submitted
+    pub fn flush(&mut self) -> Vec<(T, Digest)> {
+        while self.returned + (self.early.len() as u64) < self.submitted {
+            let result = self.results.recv().expect("digest workers stopped");
+            self.store(result);
+        }
+        self.take()
+    }
+
+    fn store(&mut self, (index, digest, frame): (u64, Digest, Vec<u8>)) {
+        self.early.insert(index, digest);
+        self.buffers.push(frame);
+    }
+
+    fn take(&mut self) -> Vec<(T, Digest)> {
+        let mut ready = Vec::new();
+        while let Some(digest) = self.early.remove(&self.returned) {
+            self.returned += 1;
+            let context = self.contexts.pop_front().unwrap();
+            ready.push((context, digest));
+        }
+        ready
+    }
+}
+
+impl<T> Drop for DigestPool<T> {
+    fn drop(&mut self) {
+        // Closing the queue stops the workers once it is empty
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
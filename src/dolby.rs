@@ -0,0 +1,54 @@
+use streamreader::ac3;
+
+use crate::walk::Frame;
+
+/// Parse the AC-3 or E-AC-3 syncframe at the start of `data`
+pub fn parse_frame(data: &[u8]) -> Option<Frame> {
+    let header = ac3::parse_header(data)?;
+    let length = header.frame_length;
+
+    let mut lines = vec![
+        format!("Len is {}", length),
+        format!(
+            "Format is {}, bsid {}",
+            if header.is_eac3() { "E-AC-3" } else { "AC-3" },
+            header.bsid
+        ),
+    ];
+    if let Some(stream_type) = header.stream_type {
+        let name = match stream_type {
+            0 => "independent",
+            1 => "dependent",
+            _ => "converted AC-3",
+        };
+        lines.push(format!("Substream is {} {}", name, header.substream_id));
+    }
+    lines.push(format!("Sampling frequency is {} Hz", header.sample_rate));
+    lines.push(format!(
+        "Channel layout is acmod {}, {}",
+        header.acmod,
+        header.channel_layout()
+    ));
+    if let Some(frame) = data.get(..length) {
+        let crc = if ac3::check_crc(frame) {
+            "valid"
+        } else {
+            "mismatch"
+        };
+        lines.push(format!("CRC is {}", crc));
+    }
+
+    // Dependent and additional substreams play alongside the main one
+    let duration = if header.is_main_substream() {
+        header.duration()
+    } else {
+        0.0
+    };
+    Some(Frame {
+        length,
+        duration,
+        lines,
+        warnings: Vec::new(),
+        advertised: None,
+    })
+}
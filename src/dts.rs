@@ -0,0 +1,270 @@
+use crate::bitcursor::{BitCursor, BitError};
+
+/// Syncword of DTS core frames in 16-bit big endian words
+pub const DTS_SYNCWORD: u32 = 0x7FFE_8001;
+/// Syncword of DTS-HD extension substreams
+pub const DTS_SUBSTREAM_SYNCWORD: u32 = 0x6458_2025;
+
+/// Sampling frequencies in Hz by SFREQ, 0 where it is invalid
+const SAMPLE_RATES: [u32; 16] = [
+    0, 8000, 16000, 32000, 0, 0, 11025, 22050, 44100, 0, 0, 12000, 24000, 48000, 0, 0,
+];
+
+/// Transmission bitrates in bit/s by RATE, the last three are open, variable
+/// and lossless
+const BITRATES: [u32; 29] = [
+    32000, 56000, 64000, 96000, 112_000, 128_000, 192_000, 224_000, 256_000, 320_000, 384_000,
+    448_000, 512_000, 576_000, 640_000, 768_000, 960_000, 1_024_000, 1_152_000, 1_280_000,
+    1_344_000, 1_408_000, 1_411_200, 1_472_000, 1_536_000, 1_920_000, 2_048_000, 3_072_000,
+    3_840_000,
+];
+
+/// Samples per channel in one PCM sample block
+const BLOCK_SAMPLES: u32 = 32;
+
+/// Bytes of the packed header needed to parse it, enough for 14-bit words
+const HEADER_LEN: usize = 16;
+
+/// How the words of a DTS stream are stored
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum DtsPacking {
+    Be16,
+    /// Byte-swapped 16-bit words
+    Le16,
+    /// 14 bits of data in each 16-bit word, as on CD and S/PDIF
+    Be14,
+    Le14,
+}
+
+impl DtsPacking {
+    pub fn name(self) -> &'static str {
+        match self {
+            DtsPacking::Be16 => "16-bit big endian",
+            DtsPacking::Le16 => "16-bit little endian",
+            DtsPacking::Be14 => "14-bit big endian",
+            DtsPacking::Le14 => "14-bit little endian",
+        }
+    }
+
+    fn detect(data: &[u8]) -> Option<DtsPacking> {
+        match data.get(..6)? {
+            [0x7F, 0xFE, 0x80, 0x01, _, _] => Some(DtsPacking::Be16),
+            [0xFE, 0x7F, 0x01, 0x80, _, _] => Some(DtsPacking::Le16),
+            [0x1F, 0xFF, 0xE8, 0x00, 0x07, b] if b & 0xF0 == 0xF0 => Some(DtsPacking::Be14),
+            [0xFF, 0x1F, 0x00, 0xE8, b, 0x07] if b & 0xF0 == 0xF0 => Some(DtsPacking::Le14),
+            _ => None,
+        }
+    }
+
+    /// Length in the stream of `len` bytes of 16-bit big endian data
+    fn stream_length(self, len: usize) -> usize {
+        match self {
+            DtsPacking::Be16 | DtsPacking::Le16 => len,
+            DtsPacking::Be14 | DtsPacking::Le14 => (len * 8).div_ceil(14) * 2,
+        }
+    }
+
+    /// Repack the start of `data` into 16-bit big endian words
+    fn unpack(self, data: &[u8]) -> Vec<u8> {
+        let words = data.chunks_exact(2).map(|pair| match self {
+            DtsPacking::Be16 | DtsPacking::Be14 => u16::from_be_bytes([pair[0], pair[1]]),
+            DtsPacking::Le16 | DtsPacking::Le14 => u16::from_le_bytes([pair[0], pair[1]]),
+        });
+        if let DtsPacking::Be16 | DtsPacking::Le16 = self {
+            return words.flat_map(|word| word.to_be_bytes().to_vec()).collect();
+        }
+
+        let mut out = Vec::new();
+        let mut bits: u32 = 0;
+        let mut count = 0;
+        for word in words {
+            bits = (bits << 14) | u32::from(word & 0x3FFF);
+            count += 14;
+            while count >= 8 {
+                count -= 8;
+                out.push((bits >> count) as u8);
+            }
+            bits &= (1 << count) - 1;
+        }
+        out
+    }
+}
+
+/// The fields of a DTS core frame header, ETSI TS 102 114 5.3.1
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct DtsHeader {
+    pub packing: DtsPacking,
+    /// A termination frame has fewer samples than its blocks hold
+    pub termination: bool,
+    /// Number of PCM sample blocks of 32 samples
+    pub blocks: u8,
+    /// Length of the frame in the stream, in bytes
+    pub frame_length: usize,
+    /// Audio channel arrangement
+    pub amode: u8,
+    pub sample_rate: u32,
+    /// Transmission bitrate in bit/s, `None` for open, variable and lossless
+    pub bitrate: Option<u32>,
+    pub crc_present: bool,
+    /// Low frequency effects channel
+    pub lfe: bool,
+    /// Type of the extension audio in the core frame, if there is any
+    pub extension: Option<u8>,
+}
+
+impl DtsHeader {
+    /// Number of samples per channel in the frame
+    pub fn samples(&self) -> u32 {
+        u32::from(self.blocks) * BLOCK_SAMPLES
+    }
+
+    /// Duration of the frame in seconds
+    pub fn duration(&self) -> f64 {
+        f64::from(self.samples()) / f64::from(self.sample_rate)
+    }
+
+    /// Human readable channel arrangement, including the LFE channel
+    pub fn channel_arrangement(&self) -> String {
+        let arrangement = match self.amode {
+            0 => "A (mono)",
+            1 => "A + B (dual mono)",
+            2 => "L + R",
+            3 => "(L+R) + (L-R) (sum-difference)",
+            4 => "LT + RT",
+            5 => "C + L + R",
+            6 => "L + R + S",
+            7 => "C + L + R + S",
+            8 => "L + R + SL + SR",
+            9 => "C + L + R + SL + SR",
+            10 => "CL + CR + L + R + SL + SR",
+            11 => "C + L + R + LR + RR + OV",
+            12 => "CF + CR + LF + RF + LR + RR",
+            13 => "CL + C + CR + L + R + SL + SR",
+            14 => "CL + CR + L + R + SL1 + SL2 + SR1 + SR2",
+            15 => "CL + C + CR + L + R + SL + S + SR",
+            _ => "user defined",
+        };
+        if self.lfe {
+            format!("{} + LFE", arrangement)
+        } else {
+            arrangement.to_string()
+        }
+    }
+
+    /// Name of the extension audio in the core frame
+    pub fn extension_name(&self) -> Option<&'static str> {
+        self.extension.map(|id| match id {
+            0 => "XCh",
+            2 => "X96",
+            6 => "XXCH",
+            _ => "reserved",
+        })
+    }
+}
+
+/// Parse the DTS core frame header at the start of `data`, in any packing
+pub fn parse_header(data: &[u8]) -> Option<DtsHeader> {
+    let packing = DtsPacking::detect(data)?;
+    let header = packing.unpack(data.get(..HEADER_LEN)?);
+    parse_core(&header, packing).ok().flatten()
+}
+
+fn parse_core(data: &[u8], packing: DtsPacking) -> Result<Option<DtsHeader>, BitError> {
+    let mut reader = BitCursor::new(data, 0);
+    if reader.read_u32(32)? != DTS_SYNCWORD {
+        return Ok(None);
+    }
+    let normal = reader.read_bool()?;
+    let deficit = reader.read_u8(5)?;
+    let crc_present = reader.read_bool()?;
+    let blocks = reader.read_u8(7)? + 1;
+    let fsize = usize::from(reader.read_u16(14)?) + 1;
+    let amode = reader.read_u8(6)?;
+    let sample_rate = SAMPLE_RATES[usize::from(reader.read_u8(4)?)];
+    let rate = usize::from(reader.read_u8(5)?);
+    // Blocks come in multiples of 8 except in termination frames, and the
+    // smallest frame is 96 bytes
+    if sample_rate == 0 || blocks < 6 || fsize < 96 {
+        return Ok(None);
+    }
+
+    // Embedded downmix, dynamic range, time stamp and auxiliary data flags,
+    // HDCD
+    reader.skip(5)?;
+    let extension_id = reader.read_u8(3)?;
+    let has_extension = reader.read_bool()?;
+    // Audio sync word insertion flag
+    reader.skip(1)?;
+    let lfe = match reader.read_u8(2)? {
+        0 => false,
+        3 => return Ok(None),
+        _ => true,
+    };
+
+    Ok(Some(DtsHeader {
+        packing,
+        termination: !normal || deficit != 31,
+        blocks,
+        frame_length: packing.stream_length(fsize),
+        amode,
+        sample_rate,
+        bitrate: BITRATES.get(rate).copied(),
+        crc_present,
+        lfe,
+        extension: if has_extension {
+            Some(extension_id)
+        } else {
+            None
+        },
+    }))
+}
+
+/// The fields of a DTS-HD extension substream header
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct SubstreamHeader {
+    /// Index of the extension substream, 0 to 3
+    pub index: u8,
+    pub header_length: usize,
+    /// Length of the whole substream in bytes
+    pub frame_length: usize,
+}
+
+/// Parse the DTS-HD extension substream header at the start of `data`
+///
+/// Extension substreams follow the core frame they extend, or stand alone
+/// for DTS-HD streams without a core.
+pub fn parse_substream_header(data: &[u8]) -> Option<SubstreamHeader> {
+    let mut reader = BitCursor::new(data, 0);
+    parse_substream(&mut reader).ok().flatten()
+}
+
+fn parse_substream(reader: &mut BitCursor) -> Result<Option<SubstreamHeader>, BitError> {
+    if reader.read_u32(32)? != DTS_SUBSTREAM_SYNCWORD {
+        return Ok(None);
+    }
+    // User defined bits
+    reader.skip(8)?;
+    let index = reader.read_u8(2)?;
+    let (header_length, frame_length) = if reader.read_bool()? {
+        (reader.read_u16(12)?, reader.read_u32(20)?)
+    } else {
+        (
+            u16::from(reader.read_u8(8)?),
+            u32::from(reader.read_u16(16)?),
+        )
+    };
+    let header_length = usize::from(header_length) + 1;
+    let frame_length = frame_length as usize + 1;
+    if frame_length < header_length {
+        return Ok(None);
+    }
+
+    Ok(Some(SubstreamHeader {
+        index,
+        header_length,
+        frame_length,
+    }))
+}
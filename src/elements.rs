@@ -0,0 +1,257 @@
+use crate::bitcursor::{BitCursor, BitError};
+use crate::header::MPEGAudioObjectType;
+
+/// Syntactic element of a raw data block by its id_syn_ele, ISO 14496-3
+/// table 4.85
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ElementId {
+    /// single_channel_element
+    Sce,
+    /// channel_pair_element
+    Cpe,
+    /// coupling_channel_element
+    Cce,
+    /// lfe_channel_element
+    Lfe,
+    /// data_stream_element
+    Dse,
+    /// program_config_element
+    Pce,
+    /// fill_element
+    Fil,
+    End,
+}
+
+impl ElementId {
+    fn from_id(id: u8) -> ElementId {
+        match id {
+            0 => ElementId::Sce,
+            1 => ElementId::Cpe,
+            2 => ElementId::Cce,
+            3 => ElementId::Lfe,
+            4 => ElementId::Dse,
+            5 => ElementId::Pce,
+            6 => ElementId::Fil,
+            _ => ElementId::End,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ElementId::Sce => "SCE",
+            ElementId::Cpe => "CPE",
+            ElementId::Cce => "CCE",
+            ElementId::Lfe => "LFE",
+            ElementId::Dse => "DSE",
+            ElementId::Pce => "PCE",
+            ElementId::Fil => "FIL",
+            ElementId::End => "END",
+        }
+    }
+
+    /// Whether the element carries audio, so its length is unknown without
+    /// decoding it
+    pub fn is_channel(self) -> bool {
+        matches!(
+            self,
+            ElementId::Sce | ElementId::Cpe | ElementId::Cce | ElementId::Lfe
+        )
+    }
+}
+
+/// First channel element a raw data block of `channel_configuration` must
+/// start with, or `None` if a program_config_element defines the layout
+pub fn expected_first_element(channel_configuration: u8) -> Option<ElementId> {
+    match channel_configuration {
+        0 => None,
+        2 => Some(ElementId::Cpe),
+        _ => Some(ElementId::Sce),
+    }
+}
+
+/// The payload of a data_stream_element
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct DataStream {
+    pub element_instance_tag: u8,
+    pub data: Vec<u8>,
+}
+
+/// Elements at the start of `raw_data`, up to and including the first
+/// channel element or the end of the block
+///
+/// Only data stream, fill and program config elements state their own
+/// length. Channel elements end where their Huffman coded spectral data
+/// ends, so the walk cannot go past the first of them.
+///
+/// `offset` is the position of `raw_data` in the input and is only used to
+/// locate errors.
+pub fn leading_elements(raw_data: &[u8], offset: u64) -> Result<Vec<ElementId>, BitError> {
+    walk_leading(raw_data, offset).map(|(elements, _)| elements)
+}
+
+/// Payloads of the data stream elements among the leading elements of
+/// `raw_data`, see `leading_elements`
+pub fn leading_data_streams(raw_data: &[u8], offset: u64) -> Result<Vec<DataStream>, BitError> {
+    walk_leading(raw_data, offset).map(|(_, streams)| streams)
+}
+
+fn walk_leading(
+    raw_data: &[u8],
+    offset: u64,
+) -> Result<(Vec<ElementId>, Vec<DataStream>), BitError> {
+    let mut reader = BitCursor::new(raw_data, offset);
+    let mut elements = Vec::new();
+    let mut streams = Vec::new();
+    loop {
+        let element = ElementId::from_id(reader.read_u8(3)?);
+        elements.push(element);
+        match element {
+            ElementId::Dse => streams.push(read_dse(&mut reader)?),
+            ElementId::Pce => {
+                read_program_config(&mut reader)?;
+            }
+            ElementId::Fil => skip_fil(&mut reader)?,
+            _ => return Ok((elements, streams)),
+        }
+    }
+}
+
+fn read_dse(reader: &mut BitCursor) -> Result<DataStream, BitError> {
+    let element_instance_tag = reader.read_u8(4)?;
+    let byte_align = reader.read_bool()?;
+    let mut count = usize::from(reader.read_u8(8)?);
+    if count == 255 {
+        count += usize::from(reader.read_u8(8)?);
+    }
+    if byte_align {
+        align(reader)?;
+    }
+    let mut data = Vec::with_capacity(count);
+    for _ in 0..count {
+        data.push(reader.read_u8(8)?);
+    }
+    Ok(DataStream {
+        element_instance_tag,
+        data,
+    })
+}
+
+fn skip_fil(reader: &mut BitCursor) -> Result<(), BitError> {
+    let mut count = usize::from(reader.read_u8(4)?);
+    if count == 15 {
+        count += usize::from(reader.read_u8(8)?) - 1;
+    }
+    skip_bytes(reader, count)
+}
+
+/// The channel layout of a program_config_element, ISO 14496-3 4.5.1.2
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ProgramConfig {
+    pub element_instance_tag: u8,
+    /// Profile in the two bits ADTS uses, one less than the object type
+    pub object_type: u8,
+    pub sampling_frequency_index: u8,
+    /// Whether each front, side and back element is a channel pair
+    pub front: Vec<bool>,
+    pub side: Vec<bool>,
+    pub back: Vec<bool>,
+    pub lfe: u8,
+    pub assoc_data: u8,
+    pub valid_cc: u8,
+    pub comment: Vec<u8>,
+}
+
+impl ProgramConfig {
+    pub fn profile(&self) -> MPEGAudioObjectType {
+        match self.object_type {
+            0 => MPEGAudioObjectType::AAC_MAIN,
+            1 => MPEGAudioObjectType::AAC_LC,
+            2 => MPEGAudioObjectType::AAC_SSR,
+            _ => MPEGAudioObjectType::AAC_LTP,
+        }
+    }
+
+    /// Number of channels, including LFE channels
+    pub fn channels(&self) -> usize {
+        let elements = self.front.iter().chain(&self.side).chain(&self.back);
+        elements
+            .map(|&is_cpe| if is_cpe { 2 } else { 1 })
+            .sum::<usize>()
+            + usize::from(self.lfe)
+    }
+}
+
+/// Read a program_config_element, after its id_syn_ele
+///
+/// The comment field is byte aligned relative to the start of the source,
+/// as in ADTS frames and the ADIF header.
+pub fn read_program_config(reader: &mut BitCursor) -> Result<ProgramConfig, BitError> {
+    let element_instance_tag = reader.read_u8(4)?;
+    let object_type = reader.read_u8(2)?;
+    let sampling_frequency_index = reader.read_u8(4)?;
+    let front = reader.read_u8(4)?;
+    let side = reader.read_u8(4)?;
+    let back = reader.read_u8(4)?;
+    let lfe = reader.read_u8(2)?;
+    let assoc_data = reader.read_u8(3)?;
+    let valid_cc = reader.read_u8(4)?;
+    // Mono and stereo mixdown element numbers, matrix mixdown index and
+    // pseudo surround flag
+    for bits in [4, 4, 3].iter() {
+        if reader.read_bool()? {
+            reader.skip(*bits)?;
+        }
+    }
+    let mut read_elements = |count: u8| -> Result<Vec<bool>, BitError> {
+        let mut elements = Vec::new();
+        for _ in 0..count {
+            elements.push(reader.read_bool()?);
+            // element_tag_select
+            reader.skip(4)?;
+        }
+        Ok(elements)
+    };
+    let front = read_elements(front)?;
+    let side = read_elements(side)?;
+    let back = read_elements(back)?;
+    for _ in 0..lfe + assoc_data {
+        reader.skip(4)?;
+    }
+    // cc_element_is_ind_sw and valid_cc_element_tag_select
+    for _ in 0..valid_cc {
+        reader.skip(5)?;
+    }
+    align(reader)?;
+    let comment_bytes = usize::from(reader.read_u8(8)?);
+    let mut comment = Vec::with_capacity(comment_bytes);
+    for _ in 0..comment_bytes {
+        comment.push(reader.read_u8(8)?);
+    }
+
+    Ok(ProgramConfig {
+        element_instance_tag,
+        object_type,
+        sampling_frequency_index,
+        front,
+        side,
+        back,
+        lfe,
+        assoc_data,
+        valid_cc,
+        comment,
+    })
+}
+
+fn align(reader: &mut BitCursor) -> Result<(), BitError> {
+    let rest = (8 - reader.position() % 8) % 8;
+    reader.skip(rest as u8)
+}
+
+fn skip_bytes(reader: &mut BitCursor, count: usize) -> Result<(), BitError> {
+    for _ in 0..count {
+        reader.skip(8)?;
+    }
+    Ok(())
+}
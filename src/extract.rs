@@ -0,0 +1,31 @@
+use std::io::{self, Write};
+
+/// Writes the frames of the input without anything between them
+///
+/// The result is the elementary stream with junk removed. Without headers
+/// only the raw data blocks are written, which needs out of band
+/// configuration to decode.
+pub struct Extractor<W: Write> {
+    out: W,
+    strip_headers: bool,
+}
+
+impl<W: Write> Extractor<W> {
+    pub fn new(out: W, strip_headers: bool) -> Extractor<W> {
+        Extractor { out, strip_headers }
+    }
+
+    /// Write a frame with a header of `header_length` bytes, including the
+    /// CRC if present
+    pub fn frame(&mut self, frame: &[u8], header_length: usize) -> io::Result<()> {
+        if self.strip_headers {
+            self.out.write_all(&frame[header_length..])
+        } else {
+            self.out.write_all(frame)
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,243 @@
+use crate::bitcursor::{BitCursor, BitError};
+use crate::crc::crc8;
+
+/// Marker at the start of a FLAC stream, before the metadata blocks
+pub const FLAC_MARKER: [u8; 4] = *b"fLaC";
+
+/// Length of a metadata block header
+pub const METADATA_HEADER_LEN: usize = 4;
+
+/// Sampling frequencies in Hz by sample rate code, 0 where the code does
+/// not give one directly
+const SAMPLE_RATES: [u32; 12] = [
+    0, 88200, 176_400, 192_000, 8000, 16000, 22050, 24000, 32000, 44100, 48000, 96000,
+];
+
+/// Bits per sample by sample size code, 0 where it is taken from
+/// STREAMINFO or reserved
+const SAMPLE_SIZES: [u8; 8] = [0, 8, 12, 0, 16, 20, 24, 32];
+
+/// The header of a metadata block
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct MetadataBlock {
+    /// Whether this is the last metadata block before the frames
+    pub last: bool,
+    pub block_type: u8,
+    /// Length of the block in bytes, without the header
+    pub length: usize,
+}
+
+impl MetadataBlock {
+    pub fn type_name(&self) -> &'static str {
+        match self.block_type {
+            0 => "STREAMINFO",
+            1 => "PADDING",
+            2 => "APPLICATION",
+            3 => "SEEKTABLE",
+            4 => "VORBIS_COMMENT",
+            5 => "CUESHEET",
+            6 => "PICTURE",
+            _ => "reserved",
+        }
+    }
+}
+
+/// Parse the metadata block header at the start of `data`
+pub fn parse_metadata_header(data: &[u8]) -> Option<MetadataBlock> {
+    let header = data.get(..METADATA_HEADER_LEN)?;
+    let block_type = header[0] & 0x7F;
+    if block_type == 127 {
+        return None;
+    }
+    Some(MetadataBlock {
+        last: header[0] & 0x80 != 0,
+        block_type,
+        length: usize::from(header[1]) << 16 | usize::from(header[2]) << 8 | usize::from(header[3]),
+    })
+}
+
+/// The fields of a STREAMINFO metadata block, RFC 9639 8.2
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct StreamInfo {
+    pub min_block_size: u16,
+    pub max_block_size: u16,
+    /// Smallest frame in bytes, 0 if unknown
+    pub min_frame_size: u32,
+    /// Largest frame in bytes, 0 if unknown
+    pub max_frame_size: u32,
+    pub sample_rate: u32,
+    pub channels: u8,
+    pub bits_per_sample: u8,
+    /// Samples per channel in the whole stream, 0 if unknown
+    pub total_samples: u64,
+    /// MD5 of the decoded audio, all zeros if unknown
+    pub md5: [u8; 16],
+}
+
+/// Parse the body of a STREAMINFO metadata block
+pub fn parse_stream_info(block: &[u8]) -> Option<StreamInfo> {
+    let mut reader = BitCursor::new(block, 0);
+    read_stream_info(&mut reader, block).ok()
+}
+
+fn read_stream_info(reader: &mut BitCursor, block: &[u8]) -> Result<StreamInfo, BitError> {
+    let min_block_size = reader.read_u16(16)?;
+    let max_block_size = reader.read_u16(16)?;
+    let min_frame_size = reader.read_u32(24)?;
+    let max_frame_size = reader.read_u32(24)?;
+    let sample_rate = reader.read_u32(20)?;
+    let channels = reader.read_u8(3)? + 1;
+    let bits_per_sample = reader.read_u8(5)? + 1;
+    let total_samples = u64::from(reader.read_u8(4)?) << 32 | u64::from(reader.read_u32(32)?);
+    // The MD5 is byte aligned after the 18 bytes above
+    let mut md5 = [0; 16];
+    md5.copy_from_slice(block.get(18..34).ok_or(BitError {
+        position: reader.position(),
+        requested: 128,
+    })?);
+
+    Ok(StreamInfo {
+        min_block_size,
+        max_block_size,
+        min_frame_size,
+        max_frame_size,
+        sample_rate,
+        channels,
+        bits_per_sample,
+        total_samples,
+        md5,
+    })
+}
+
+/// The fields of a FLAC frame header, RFC 9639 9.1
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct FlacFrameHeader {
+    /// Whether frames have varying block sizes and are numbered by their
+    /// first sample instead of their index
+    pub variable_block_size: bool,
+    /// Frame number, or number of the first sample with variable block sizes
+    pub number: u64,
+    /// Samples per channel in the frame
+    pub block_size: u32,
+    /// `None` if only STREAMINFO has it
+    pub sample_rate: Option<u32>,
+    pub channel_assignment: u8,
+    /// Bits per sample, `None` if only STREAMINFO has it
+    pub sample_size: Option<u8>,
+    /// Length of the header in bytes, including its CRC-8
+    pub header_length: usize,
+}
+
+impl FlacFrameHeader {
+    pub fn channels(&self) -> u8 {
+        match self.channel_assignment {
+            count @ 0..=7 => count + 1,
+            _ => 2,
+        }
+    }
+
+    /// Human readable channel assignment, including the stereo decorrelation
+    pub fn channel_assignment_name(&self) -> String {
+        match self.channel_assignment {
+            8 => "left/side".to_string(),
+            9 => "side/right".to_string(),
+            10 => "mid/side".to_string(),
+            count => format!("{} independent", count + 1),
+        }
+    }
+
+    /// Duration of the frame in seconds, if the header has the sample rate
+    pub fn duration(&self) -> Option<f64> {
+        self.sample_rate
+            .map(|rate| f64::from(self.block_size) / f64::from(rate))
+    }
+}
+
+/// Parse the FLAC frame header at the start of `data`, checking its CRC-8
+pub fn parse_frame_header(data: &[u8]) -> Option<FlacFrameHeader> {
+    if data.len() < 2 || data[0] != 0xFF || data[1] & 0xFE != 0xF8 {
+        return None;
+    }
+    let mut reader = BitCursor::new(data, 0);
+    let header = read_frame_header(&mut reader).ok().flatten()?;
+    if crc8(&data[..header.header_length]) != 0 {
+        return None;
+    }
+    Some(header)
+}
+
+fn read_frame_header(reader: &mut BitCursor) -> Result<Option<FlacFrameHeader>, BitError> {
+    reader.skip(15)?;
+    let variable_block_size = reader.read_bool()?;
+    let block_size_code = reader.read_u8(4)?;
+    let sample_rate_code = reader.read_u8(4)?;
+    let channel_assignment = reader.read_u8(4)?;
+    let sample_size_code = reader.read_u8(3)?;
+    let reserved = reader.read_bool()?;
+    if block_size_code == 0
+        || sample_rate_code == 15
+        || channel_assignment > 10
+        || sample_size_code == 3
+        || reserved
+    {
+        return Ok(None);
+    }
+
+    let number = match read_coded_number(reader)? {
+        Some(number) => number,
+        None => return Ok(None),
+    };
+    let block_size = match block_size_code {
+        1 => 192,
+        2..=5 => 576 << (block_size_code - 2),
+        6 => u32::from(reader.read_u8(8)?) + 1,
+        7 => u32::from(reader.read_u16(16)?) + 1,
+        _ => 256 << (block_size_code - 8),
+    };
+    let sample_rate = match sample_rate_code {
+        0 => None,
+        12 => Some(u32::from(reader.read_u8(8)?) * 1000),
+        13 => Some(u32::from(reader.read_u16(16)?)),
+        14 => Some(u32::from(reader.read_u16(16)?) * 10),
+        code => Some(SAMPLE_RATES[usize::from(code)]),
+    };
+    if sample_rate == Some(0) {
+        return Ok(None);
+    }
+    // CRC-8
+    reader.skip(8)?;
+
+    Ok(Some(FlacFrameHeader {
+        variable_block_size,
+        number,
+        block_size,
+        sample_rate,
+        channel_assignment,
+        sample_size: Some(SAMPLE_SIZES[usize::from(sample_size_code)]).filter(|bits| *bits > 0),
+        header_length: (reader.position() / 8) as usize,
+    }))
+}
+
+/// Read a number coded like UTF-8, extended to 36 bits
+fn read_coded_number(reader: &mut BitCursor) -> Result<Option<u64>, BitError> {
+    let first = reader.read_u8(8)?;
+    let extra = first.leading_ones();
+    if extra == 1 || extra > 7 {
+        return Ok(None);
+    }
+    if extra == 0 {
+        return Ok(Some(u64::from(first)));
+    }
+    let mut number = u64::from(first & (0x7F >> extra));
+    for _ in 1..extra {
+        let byte = reader.read_u8(8)?;
+        if byte & 0xC0 != 0x80 {
+            return Ok(None);
+        }
+        number = number << 6 | u64::from(byte & 0x3F);
+    }
+    Ok(Some(number))
+}
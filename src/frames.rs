@@ -0,0 +1,184 @@
+use std::io::{ErrorKind, Read};
+
+use crate::header::{AdtsError, AdtsHeader};
+use crate::reader::AdtsReader;
+
+/// One frame yielded by `AdtsFrames`
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct AdtsFrame {
+    /// Byte offset of the frame in the input
+    pub offset: u64,
+    pub header: AdtsHeader,
+    /// The whole frame including its header, if payloads are read
+    pub data: Option<Vec<u8>>,
+}
+
+impl AdtsFrame {
+    /// The raw data blocks following the header, if payloads are read
+    pub fn payload(&self) -> Option<&[u8]> {
+        self.data
+            .as_ref()
+            .map(|data| &data[self.header.header_length()..])
+    }
+}
+
+/// Iterator over the frames of an ADTS stream
+///
+/// Data before the first syncword is skipped. Iteration ends at the end of
+/// the input, or after yielding the first error, since there is no way to
+/// know where the next frame starts after a broken one.
+pub struct AdtsFrames<R> {
+    reader: AdtsReader<R>,
+    read_payloads: bool,
+    started: bool,
+    done: bool,
+}
+
+impl<R: Read> AdtsFrames<R> {
+    pub fn new(reader: R) -> AdtsFrames<R> {
+        AdtsFrames {
+            reader: AdtsReader::new(reader),
+            read_payloads: false,
+            started: false,
+            done: false,
+        }
+    }
+
+    /// Also yield the bytes of each frame, instead of skipping them
+    pub fn with_payloads(mut self) -> AdtsFrames<R> {
+        self.read_payloads = true;
+        self
+    }
+
+    fn next_frame(&mut self) -> Result<Option<AdtsFrame>, AdtsError> {
+        if !self.started {
+            self.started = true;
+            match self.reader.seek_startcode() {
+                Err(ref err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                result => result?,
+            };
+        }
+
+        let offset = self.reader.position();
+        let header = match self.reader.peek_header() {
+            Err(AdtsError::Io(ref err))
+                if err.kind() == ErrorKind::UnexpectedEof && self.reader.end() == Some(offset) =>
+            {
+                return Ok(None)
+            }
+            result => result?,
+        };
+        let data = if self.read_payloads {
+            let mut data = Vec::new();
+            self.reader.read_frame(&header, &mut data)?;
+            Some(data)
+        } else {
+            self.reader.skip_frame(&header)?;
+            None
+        };
+
+        Ok(Some(AdtsFrame {
+            offset,
+            header,
+            data,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for AdtsFrames<R> {
+    type Item = Result<AdtsFrame, AdtsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_frame() {
+            Ok(Some(frame)) => Some(Ok(frame)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::tests::adts_frame;
+
+    fn stream(lengths: &[u16]) -> Vec<u8> {
+        lengths
+            .iter()
+            .flat_map(|length| adts_frame(*length, false))
+            .collect()
+    }
+
+    #[test]
+    fn yields_every_frame() {
+        let data = stream(&[100, 120, 90]);
+        let frames: Vec<(u64, u16)> = AdtsFrames::new(&data[..])
+            .map(|frame| {
+                let frame = frame.unwrap();
+                (frame.offset, frame.header.frame_length)
+            })
+            .collect();
+        assert_eq!(frames, [(0, 100), (100, 120), (220, 90)]);
+    }
+
+    #[test]
+    fn skips_data_before_the_first_syncword() {
+        let mut data = vec![0x00, 0x12, 0xFF, 0x00];
+        data.extend(stream(&[100, 100]));
+        let offsets: Vec<u64> = AdtsFrames::new(&data[..])
+            .map(|frame| frame.unwrap().offset)
+            .collect();
+        assert_eq!(offsets, [4, 104]);
+        assert_eq!(AdtsFrames::new(&[0x00; 16][..]).count(), 0);
+    }
+
+    #[test]
+    fn reads_payloads() {
+        let mut data = stream(&[20]);
+        data[7..].copy_from_slice(&[1; 13]);
+        let frame = AdtsFrames::new(&data[..]).with_payloads().next().unwrap();
+        let frame = frame.unwrap();
+        assert_eq!(frame.data.as_deref(), Some(&data[..]));
+        assert_eq!(frame.payload(), Some(&[1; 13][..]));
+    }
+
+    #[test]
+    fn ends_after_a_truncated_frame() {
+        for cut in [250, 205] {
+            let data = stream(&[100, 100, 100]);
+            let mut frames = AdtsFrames::new(&data[..cut]).with_payloads();
+            assert!(frames.next().unwrap().is_ok());
+            assert!(frames.next().unwrap().is_ok());
+            match frames.next() {
+                Some(Err(AdtsError::Io(err))) => assert_eq!(err.kind(), ErrorKind::UnexpectedEof),
+                other => panic!("expected the frame to be cut off, got {:?}", other),
+            }
+            assert!(frames.next().is_none());
+        }
+    }
+
+    #[test]
+    fn ends_after_a_broken_header() {
+        let mut data = stream(&[100, 100, 100]);
+        data[102] = 0x40 | (15 << 2);
+        let mut frames = AdtsFrames::new(&data[..]);
+        assert!(frames.next().unwrap().is_ok());
+        match frames.next() {
+            Some(Err(AdtsError::InvalidField { header_offset, .. })) => {
+                assert_eq!(header_offset, 100)
+            }
+            other => panic!("expected an invalid header, got {:?}", other),
+        }
+        assert!(frames.next().is_none());
+    }
+}
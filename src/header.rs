@@ -0,0 +1,551 @@
+use std::fmt;
+use std::io;
+
+use crate::bitcursor::{BitCursor, BitError};
+use crate::mpeg_audio::{self, MpegAudioGuess};
+
+/*
+Note from https://stackoverflow.com/a/4678183
+
+ADTS sample rates and channel counts are for HE-AAC and HE-AACv2 to try to maintain
+compatibility with LC only decoders. The good news is that it they are inaccurate in
+a precise manner. HE-AAC will report half the sample rate and HE-AACv2 will always
+report a mono stream. This is because HE-AAC adds SBR which double the sample rate
+and HE-AACv2 adds parametric stereo to SBR and PS turning a mono stream into a stereo
+image. The SBR payload lives inside an AAC fill element which is ignored by an LC only
+encoder and the PS payload lives inside the SBR payload.
+
+Some decoders assume SBR if the sample rate <= 24kHz and always decode mono streams to
+stereo to avoid detecting these features up front. In that case the SBR decoder can be
+run in a pure upsampling mode if SBR data is not found.
+*/
+
+pub const ADTS_HDR_MIN_LEN: usize = 7;
+pub const ADTS_HDR_MAX_LEN: usize = 9;
+/// Largest value of the 13 bit frame_length field
+pub const ADTS_MAX_FRAME_LEN: usize = 8191;
+
+/// Samples per channel in one raw data block
+pub const AAC_FRAME_SAMPLES: u32 = 1024;
+
+/// Sampling frequencies in Hz by sampling_frequency_index
+pub const SAMPLING_FREQUENCIES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MPEGVersion {
+    MPEG4 = 0,
+    MPEG2 = 1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[non_exhaustive]
+pub enum MPEGAudioObjectType {
+    NULL = 0,
+    AAC_MAIN = 1,
+    AAC_LC = 2,
+    AAC_SSR = 3,
+    AAC_LTP = 4,
+    SBR = 5,
+    AAC_SCALABLE = 6,
+    TWIN_VQ = 7,
+    CELP = 8,
+    LAYER1 = 32,
+    LAYER2 = 33,
+    LAYER3 = 34,
+}
+
+/// The fixed and variable header fields of one ADTS frame
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct AdtsHeader {
+    pub syncword: u16,
+    pub id: MPEGVersion,
+    pub layer: u8,
+    pub protection_absent: bool,
+    pub profile: MPEGAudioObjectType,
+    pub sampling_frequency_index: u8,
+    pub channel_configuration: u8,
+    pub frame_length: u16,
+    /// Set if the header also reads as a plausible MPEG audio header
+    pub mpeg_audio: Option<MpegAudioGuess>,
+    pub adts_buffer_fullness: u16,
+    /// One less than the number of raw data blocks in the frame
+    pub num_raw_data_blocks: u8,
+}
+
+impl AdtsHeader {
+    /// Length of the header, including the CRC if present
+    pub fn header_length(&self) -> usize {
+        if self.protection_absent {
+            ADTS_HDR_MIN_LEN
+        } else {
+            ADTS_HDR_MAX_LEN
+        }
+    }
+
+    pub fn sampling_frequency(&self) -> u32 {
+        SAMPLING_FREQUENCIES[usize::from(self.sampling_frequency_index)]
+    }
+
+    /// Number of samples per channel in the frame
+    pub fn samples(&self) -> u32 {
+        AAC_FRAME_SAMPLES * (u32::from(self.num_raw_data_blocks) + 1)
+    }
+
+    /// Duration of the frame in seconds
+    pub fn duration(&self) -> f64 {
+        f64::from(self.samples()) / f64::from(self.sampling_frequency())
+    }
+
+    /// Whether the buffer fullness is the 0x7FF sentinel of variable
+    /// bitrate streams
+    pub fn is_vbr(&self) -> bool {
+        self.adts_buffer_fullness == 0x7FF
+    }
+
+    pub fn raw_data_blocks(&self) -> usize {
+        usize::from(self.num_raw_data_blocks) + 1
+    }
+
+    /// Human readable channel layout
+    pub fn channel_layout(&self) -> &'static str {
+        channel_layout(self.channel_configuration)
+    }
+
+    /// Number of channels including LFE, `None` if a program_config_element
+    /// defines them
+    pub fn channels(&self) -> Option<u8> {
+        channel_count(self.channel_configuration)
+    }
+
+    /// Whether `other` has the stream parameters of this header, so both can
+    /// be frames of the same stream
+    pub fn same_stream(&self, other: &AdtsHeader) -> bool {
+        self.id == other.id
+            && self.profile == other.profile
+            && self.sampling_frequency_index == other.sampling_frequency_index
+            && self.channel_configuration == other.channel_configuration
+    }
+}
+
+/// Why no valid ADTS header could be read
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AdtsError {
+    Io(io::Error),
+    Truncated(BitError),
+    InvalidField {
+        field: &'static str,
+        value: u64,
+        constraint: &'static str,
+        /// Byte offset of the header in the input
+        header_offset: u64,
+        /// Absolute bit position of the field in the input
+        position: u64,
+        width: u8,
+    },
+    /// The header failed to parse and looks like MPEG audio instead
+    MpegAudio {
+        guess: MpegAudioGuess,
+        cause: Box<AdtsError>,
+    },
+}
+
+impl AdtsError {
+    fn invalid(
+        field: &'static str,
+        value: u64,
+        constraint: &'static str,
+        header_offset: u64,
+        position: u64,
+        width: u8,
+    ) -> AdtsError {
+        AdtsError::InvalidField {
+            field,
+            value,
+            constraint,
+            header_offset,
+            position,
+            width,
+        }
+    }
+}
+
+/// Fields whose values are bit patterns, written in hex like their
+/// constraints
+const HEX_FIELDS: [&str; 1] = ["syncword"];
+
+impl fmt::Display for AdtsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AdtsError::Io(err) => write!(f, "{}", err),
+            AdtsError::Truncated(err) => write!(f, "{}", err),
+            AdtsError::InvalidField {
+                field,
+                value,
+                constraint,
+                header_offset,
+                position,
+                width,
+            } => {
+                let first_bit = position - header_offset * 8;
+                let value = if HEX_FIELDS.contains(field) {
+                    format!("{:#X}", value)
+                } else {
+                    value.to_string()
+                };
+                write!(
+                    f,
+                    "{}={} ({}) at byte 0x{:X}, bits {}\u{2013}{}",
+                    field,
+                    value,
+                    constraint,
+                    header_offset,
+                    first_bit,
+                    first_bit + u64::from(*width) - 1
+                )
+            }
+            AdtsError::MpegAudio { guess, cause } => {
+                write!(f, "{} (looks like {}, not ADTS)", cause, guess)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AdtsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AdtsError::Io(err) => Some(err),
+            AdtsError::Truncated(err) => Some(err),
+            AdtsError::InvalidField { .. } => None,
+            AdtsError::MpegAudio { cause, .. } => Some(cause.as_ref()),
+        }
+    }
+}
+
+impl From<io::Error> for AdtsError {
+    fn from(err: io::Error) -> AdtsError {
+        AdtsError::Io(err)
+    }
+}
+
+impl From<BitError> for AdtsError {
+    fn from(err: BitError) -> AdtsError {
+        AdtsError::Truncated(err)
+    }
+}
+
+/// Layout of a channel_configuration value, ISO 14496-3 table 1.19
+pub fn channel_layout(channel_configuration: u8) -> &'static str {
+    match channel_configuration {
+        0 => "defined in the stream by a program_config_element",
+        1 => "mono (C)",
+        2 => "stereo (L R)",
+        3 => "3.0 (C L R)",
+        4 => "4.0 (C L R Cs)",
+        5 => "5.0 (C L R Ls Rs)",
+        6 => "5.1 (C L R Ls Rs LFE)",
+        _ => "7.1 (C Lc Rc L R Ls Rs LFE)",
+    }
+}
+
+/// Number of channels of a `channel_configuration` including LFE, `None`
+/// for 0, where a program_config_element in the stream defines them
+pub fn channel_count(channel_configuration: u8) -> Option<u8> {
+    match channel_configuration {
+        0 => None,
+        7 => Some(8),
+        channels => Some(channels),
+    }
+}
+
+/// Parse the header starting `start` bytes into the concatenation of
+/// `bufs`, like the segments of a ring buffer, without joining them first
+///
+/// Only the header bytes are copied. `offset` is the position of the header
+/// in the input and is only used to locate errors.
+pub fn parse_header_vectored(
+    bufs: &[&[u8]],
+    start: usize,
+    offset: u64,
+) -> Result<AdtsHeader, AdtsError> {
+    let mut buffer = [0; ADTS_HDR_MIN_LEN];
+    let mut filled = 0;
+    let mut skip = start;
+    for buf in bufs {
+        if skip >= buf.len() {
+            skip -= buf.len();
+            continue;
+        }
+        let available = &buf[skip..];
+        skip = 0;
+        let len = available.len().min(ADTS_HDR_MIN_LEN - filled);
+        buffer[filled..filled + len].copy_from_slice(&available[..len]);
+        filled += len;
+        if filled == ADTS_HDR_MIN_LEN {
+            return parse_header(&buffer, offset);
+        }
+    }
+    Err(AdtsError::Io(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "buffers end inside the header",
+    )))
+}
+
+/// Parse the fixed and variable header of the frame starting at `offset`
+///
+/// `offset` is only used to locate errors in the input.
+pub fn parse_header(buffer: &[u8; ADTS_HDR_MIN_LEN], offset: u64) -> Result<AdtsHeader, AdtsError> {
+    let guess = mpeg_audio::guess(buffer);
+
+    match parse_adts_fields(buffer, offset) {
+        Ok(header) => Ok(AdtsHeader {
+            mpeg_audio: guess,
+            ..header
+        }),
+        Err(cause) => match guess {
+            Some(guess) => Err(AdtsError::MpegAudio {
+                guess,
+                cause: Box::new(cause),
+            }),
+            None => Err(cause),
+        },
+    }
+}
+
+fn parse_adts_fields(
+    buffer: &[u8; ADTS_HDR_MIN_LEN],
+    offset: u64,
+) -> Result<AdtsHeader, AdtsError> {
+    let mut reader = BitCursor::new(buffer, offset);
+
+    // Check syncword
+    let position = reader.position();
+    let syncword = reader.read_u16(12)?;
+    if syncword != 0xFFF {
+        return Err(AdtsError::invalid(
+            "syncword",
+            u64::from(syncword),
+            "must be 0xFFF",
+            offset,
+            position,
+            12,
+        ));
+    }
+
+    // MPEG Version
+    let mpeg_version = match reader.read_u8(1)? {
+        0 => MPEGVersion::MPEG4,
+        _ => MPEGVersion::MPEG2,
+    };
+
+    // Layer (always 0)
+    let layer = reader.read_u8(2)?;
+
+    // Protection absent
+    let protection_absent = reader.read_bool()?;
+
+    // Profile
+    let profile = match reader.read_u8(2)? {
+        0 => MPEGAudioObjectType::AAC_MAIN,
+        1 => MPEGAudioObjectType::AAC_LC,
+        2 => MPEGAudioObjectType::AAC_SSR,
+        _ => MPEGAudioObjectType::AAC_LTP,
+    };
+
+    // Sampling frequency index
+    let position = reader.position();
+    let sampling_frequency_index = reader.read_u8(4)?;
+    match sampling_frequency_index {
+        13 | 14 => {
+            return Err(AdtsError::invalid(
+                "sampling_frequency_index",
+                u64::from(sampling_frequency_index),
+                "reserved",
+                offset,
+                position,
+                4,
+            ))
+        }
+        15 => {
+            return Err(AdtsError::invalid(
+                "sampling_frequency_index",
+                u64::from(sampling_frequency_index),
+                "explicit frequency escape, not allowed in ADTS",
+                offset,
+                position,
+                4,
+            ))
+        }
+        _ => {}
+    }
+
+    // Private bit
+    reader.skip(1)?;
+    // Channel config
+    let channel_configuration = reader.read_u8(3)?;
+    // Originality
+    reader.skip(1)?;
+    // Home
+    reader.skip(1)?;
+    // Copyrighted ID
+    reader.skip(1)?;
+    // Copyright ID start signal bit
+    reader.skip(1)?;
+
+    // Frame length (including the header)
+    let position = reader.position();
+    let frame_length = reader.read_u16(13)?;
+    let header_length = if protection_absent {
+        ADTS_HDR_MIN_LEN
+    } else {
+        ADTS_HDR_MAX_LEN
+    };
+    if usize::from(frame_length) < header_length {
+        return Err(AdtsError::invalid(
+            "frame_length",
+            u64::from(frame_length),
+            "shorter than the header",
+            offset,
+            position,
+            13,
+        ));
+    }
+
+    let adts_buffer_fullness = reader.read_u16(11)?;
+    let num_raw_data_blocks = reader.read_u8(2)?;
+
+    // The CRC, if present, follows these 7 bytes and is checked with
+    // crc::check_adts_frame
+
+    Ok(AdtsHeader {
+        syncword,
+        id: mpeg_version,
+        layer,
+        profile,
+        sampling_frequency_index,
+        channel_configuration,
+        protection_absent,
+        frame_length,
+        mpeg_audio: None,
+        adts_buffer_fullness,
+        num_raw_data_blocks,
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An MPEG-4 AAC LC stereo frame at 44.1 kHz of `length` bytes, with a
+    /// zeroed CRC field if `crc` is set and zeroed raw data, but never
+    /// shorter than the fixed header
+    pub(crate) fn adts_frame(length: u16, crc: bool) -> Vec<u8> {
+        let mut frame = vec![0; usize::from(length).max(ADTS_HDR_MIN_LEN)];
+        frame[..7].copy_from_slice(&[
+            0xFF,
+            if crc { 0xF0 } else { 0xF1 },
+            0x50,
+            0x80 | (length >> 11) as u8,
+            (length >> 3) as u8,
+            ((length & 0x07) << 5) as u8 | 0x1F,
+            0xFC,
+        ]);
+        frame
+    }
+
+    fn header_bytes(frame: &[u8]) -> [u8; ADTS_HDR_MIN_LEN] {
+        let mut buffer = [0; ADTS_HDR_MIN_LEN];
+        buffer.copy_from_slice(&frame[..ADTS_HDR_MIN_LEN]);
+        buffer
+    }
+
+    #[test]
+    fn parses_header_fields() {
+        let header = parse_header(&header_bytes(&adts_frame(371, false)), 0).unwrap();
+        assert_eq!(header.id, MPEGVersion::MPEG4);
+        assert_eq!(header.layer, 0);
+        assert!(header.protection_absent);
+        assert_eq!(header.profile, MPEGAudioObjectType::AAC_LC);
+        assert_eq!(header.sampling_frequency(), 44100);
+        assert_eq!(header.channel_configuration, 2);
+        assert_eq!(header.frame_length, 371);
+        assert!(header.is_vbr());
+        assert_eq!(header.raw_data_blocks(), 1);
+        assert_eq!(header.header_length(), ADTS_HDR_MIN_LEN);
+        assert!(header.mpeg_audio.is_none());
+    }
+
+    #[test]
+    fn header_with_crc_is_longer() {
+        let header = parse_header(&header_bytes(&adts_frame(371, true)), 0).unwrap();
+        assert!(!header.protection_absent);
+        assert_eq!(header.header_length(), ADTS_HDR_MAX_LEN);
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        let field = |buffer: [u8; ADTS_HDR_MIN_LEN]| match parse_header(&buffer, 0x10) {
+            Err(AdtsError::InvalidField {
+                field,
+                header_offset,
+                ..
+            }) => {
+                assert_eq!(header_offset, 0x10);
+                field
+            }
+            other => panic!("expected an invalid field, got {:?}", other),
+        };
+
+        let mut buffer = header_bytes(&adts_frame(371, false));
+        buffer[1] = 0xE1;
+        assert_eq!(field(buffer), "syncword");
+
+        let mut buffer = header_bytes(&adts_frame(371, false));
+        buffer[2] = 0x40 | (13 << 2);
+        assert_eq!(field(buffer), "sampling_frequency_index");
+
+        let buffer = header_bytes(&adts_frame(6, false));
+        assert_eq!(field(buffer), "frame_length");
+        let buffer = header_bytes(&adts_frame(8, true));
+        assert_eq!(field(buffer), "frame_length");
+    }
+
+    #[test]
+    fn invalid_field_locates_its_bits() {
+        let mut buffer = header_bytes(&adts_frame(371, false));
+        buffer[2] = 0x40 | (15 << 2);
+        let err = parse_header(&buffer, 0x20).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "sampling_frequency_index=15 (explicit frequency escape, not allowed in ADTS) \
+             at byte 0x20, bits 18\u{2013}21"
+        );
+
+        let mut buffer = header_bytes(&adts_frame(371, false));
+        buffer[1] = 0x41;
+        let err = parse_header(&buffer, 0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "syncword=0xFF4 (must be 0xFFF) at byte 0x0, bits 0\u{2013}11"
+        );
+    }
+
+    #[test]
+    fn vectored_header_spans_buffers() {
+        let frame = adts_frame(371, false);
+        let expected = parse_header(&header_bytes(&frame), 0).unwrap();
+        let junk = [0x12, 0x34];
+        let header = parse_header_vectored(&[&junk, &frame[..3], &[], &frame[3..]], 2, 0).unwrap();
+        assert_eq!(header.frame_length, expected.frame_length);
+        assert!(header.same_stream(&expected));
+
+        match parse_header_vectored(&[&frame[..5]], 0, 0) {
+            Err(AdtsError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+            other => panic!("expected the buffers to end, got {:?}", other),
+        }
+    }
+}
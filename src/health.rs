@@ -0,0 +1,130 @@
+use crate::verdict::{Ending, Findings};
+
+/// Relative weight of each component of the health score
+#[derive(Debug, Clone, Copy)]
+pub struct Weights {
+    /// Truncation, corruption and read errors
+    pub errors: f64,
+    /// Stream parameter changes between frames
+    pub stability: f64,
+    /// Invalid header fields, MPEG audio misdetection, CRC mismatches and
+    /// raw data contradicting the channel configuration
+    pub headers: f64,
+    /// Variation of the bitrate over time
+    pub bitrate: f64,
+    /// Bytes skipped between frames
+    pub gaps: f64,
+    /// Repeating content
+    pub loops: f64,
+}
+
+impl Weights {
+    fn total(&self) -> f64 {
+        self.errors + self.stability + self.headers + self.bitrate + self.gaps + self.loops
+    }
+}
+
+impl Default for Weights {
+    fn default() -> Weights {
+        Weights {
+            errors: 30.0,
+            stability: 20.0,
+            headers: 20.0,
+            bitrate: 10.0,
+            gaps: 5.0,
+            loops: 15.0,
+        }
+    }
+}
+
+/// Parse weights given as `name=weight` pairs separated by commas
+///
+/// Components that are not mentioned keep their default weight.
+pub fn parse_weights(value: &str) -> Result<Weights, String> {
+    let mut weights = Weights::default();
+    for pair in value.split(',') {
+        let (name, weight) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("expected name=weight, got '{}'", pair))?;
+        let weight: f64 = weight
+            .parse()
+            .ok()
+            .filter(|weight: &f64| weight.is_finite() && *weight >= 0.0)
+            .ok_or_else(|| format!("invalid weight '{}'", weight))?;
+        match name {
+            "errors" => weights.errors = weight,
+            "stability" => weights.stability = weight,
+            "headers" => weights.headers = weight,
+            "bitrate" => weights.bitrate = weight,
+            "gaps" => weights.gaps = weight,
+            "loops" => weights.loops = weight,
+            _ => {
+                return Err(format!(
+                    "unknown component '{}' (expected errors, stability, headers, bitrate, \
+                     gaps or loops)",
+                    name
+                ))
+            }
+        }
+    }
+    if weights.total() == 0.0 {
+        return Err("at least one weight must be positive".to_string());
+    }
+    Ok(weights)
+}
+
+/// Parameter changes, resyncs and loops at which their component is fully penalized
+const MAX_EVENTS: f64 = 4.0;
+
+/// Deviation of the bitrate from the average at which its component is
+/// fully penalized, as a share of the average
+const MAX_BITRATE_DEVIATION: f64 = 0.5;
+
+/// Share of skipped bytes at which the gap component is fully penalized
+const MAX_GAP_SHARE: f64 = 0.05;
+
+/// Score the stream from 0 (unusable) to 100 (no problems found)
+///
+/// Each component is penalized between 0 and 1: errors by how the walk
+/// ended or the number of resyncs, whichever is worse, header problems by
+/// the share of affected frames, and parameter changes and loops by their
+/// number, saturating at `MAX_EVENTS`. The bitrate is penalized by how far
+/// it strays from the average, gaps by the share of bytes skipped by
+/// resyncs.
+pub fn score(findings: &Findings, weights: &Weights) -> u8 {
+    if findings.frames == 0 {
+        return 0;
+    }
+
+    let errors = match findings.ending {
+        Ending::Clean => 0.0,
+        Ending::Truncated { .. } => 0.25,
+        Ending::Corrupt { .. } | Ending::ReadError => 1.0,
+    };
+    let errors = f64::max(
+        errors,
+        (findings.resyncs.len() as f64 / MAX_EVENTS).min(1.0),
+    );
+    let stability = (findings.parameter_changes.len() as f64 / MAX_EVENTS).min(1.0);
+    let bad_headers = findings.header_warnings as f64
+        + findings.mpeg_audio_frames as f64
+        + findings.crc_mismatches as f64
+        + findings.element_mismatches as f64;
+    let headers = (bad_headers / findings.frames as f64).min(1.0);
+    let bitrate = findings.bitrate_deviation.map_or(0.0, |deviation| {
+        (deviation / MAX_BITRATE_DEVIATION).min(1.0)
+    });
+    let skipped: u64 = findings.resyncs.iter().map(|resync| resync.skipped).sum();
+    let gaps = skipped as f64 / (skipped + findings.frame_bytes).max(1) as f64;
+    let gaps = (gaps / MAX_GAP_SHARE).min(1.0);
+    let loops = (findings.loops as f64 / MAX_EVENTS).min(1.0);
+
+    let penalty = (weights.errors * errors
+        + weights.stability * stability
+        + weights.headers * headers
+        + weights.bitrate * bitrate
+        + weights.gaps * gaps
+        + weights.loops * loops)
+        / weights.total();
+    (100.0 * (1.0 - penalty)).round() as u8
+}
@@ -0,0 +1,300 @@
+use std::convert::TryInto;
+
+/// Start of an ID3v2 tag
+pub const ID3V2_ID: [u8; 3] = *b"ID3";
+/// Length of the ID3v2 header, and of the footer if there is one
+pub const ID3V2_HEADER_LEN: usize = 10;
+
+/// The header of an ID3v2 tag
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct Id3v2Header {
+    /// 2, 3 or 4 for ID3v2.2, ID3v2.3 and ID3v2.4
+    pub major_version: u8,
+    pub revision: u8,
+    /// Whether the tag is unsynchronised, with 0x00 stuffed after 0xFF
+    pub unsynchronisation: bool,
+    pub extended_header: bool,
+    pub footer: bool,
+    /// Length of the tag after the header, without the footer
+    pub size: usize,
+}
+
+impl Id3v2Header {
+    /// Length of the whole tag including header and footer
+    pub fn total_length(&self) -> usize {
+        let footer = if self.footer { ID3V2_HEADER_LEN } else { 0 };
+        ID3V2_HEADER_LEN + self.size + footer
+    }
+}
+
+/// Parse the ID3v2 header at the start of `data`
+pub fn parse_header(data: &[u8]) -> Option<Id3v2Header> {
+    let header = data.get(..ID3V2_HEADER_LEN)?;
+    if header[..3] != ID3V2_ID || header[3] == 0xFF || header[4] == 0xFF {
+        return None;
+    }
+    let flags = header[5];
+    Some(Id3v2Header {
+        major_version: header[3],
+        revision: header[4],
+        unsynchronisation: flags & 0x80 != 0,
+        extended_header: flags & 0x40 != 0 && header[3] >= 3,
+        footer: flags & 0x10 != 0 && header[3] >= 4,
+        size: synchsafe(&header[6..10])?,
+    })
+}
+
+/// A frame of an ID3v2 tag
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Id3v2Frame {
+    /// Frame ID, like TIT2, or TT2 in ID3v2.2
+    pub id: String,
+    /// Length of the frame content in bytes
+    pub length: usize,
+    /// Text of text information frames and comments, the description and
+    /// value separated by ": " for TXXX and COMM
+    pub text: Option<String>,
+}
+
+impl Id3v2Frame {
+    /// What the frame holds, for the frames commonly shown by players
+    pub fn name(&self) -> Option<&'static str> {
+        let name = match self.id.as_str() {
+            "TIT2" | "TT2" => "title",
+            "TPE1" | "TP1" => "artist",
+            "TPE2" | "TP2" => "album artist",
+            "TALB" | "TAL" => "album",
+            "TYER" | "TYE" | "TDRC" => "year",
+            "TRCK" | "TRK" => "track",
+            "TCON" | "TCO" => "genre",
+            "TENC" | "TEN" => "encoded by",
+            "TSSE" | "TSS" => "encoder settings",
+            "COMM" | "COM" => "comment",
+            "TXXX" | "TXX" => "user text",
+            "APIC" | "PIC" => "picture",
+            "PRIV" => "private",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+/// Parse the frames of the whole ID3v2 tag at the start of `data`
+///
+/// Frames that do not fit in `data` or the tag are left out.
+pub fn parse_frames(data: &[u8], header: &Id3v2Header) -> Vec<Id3v2Frame> {
+    let end = header.total_length().min(data.len());
+    let body = &data[ID3V2_HEADER_LEN.min(end)..end];
+    let body = if header.unsynchronisation {
+        resynchronise(body)
+    } else {
+        body.to_vec()
+    };
+
+    let mut pos = 0;
+    if header.extended_header {
+        // Its size excludes itself in ID3v2.3, and includes it in ID3v2.4
+        let size = body.get(..4).and_then(|size| {
+            if header.major_version == 3 {
+                Some(4 + u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize)
+            } else {
+                synchsafe(size)
+            }
+        });
+        pos = match size {
+            Some(size) => size,
+            None => return Vec::new(),
+        };
+    }
+
+    let (id_len, header_len) = if header.major_version == 2 {
+        (3, 6)
+    } else {
+        (4, 10)
+    };
+    let mut frames = Vec::new();
+    while let Some(frame_header) = body.get(pos..pos + header_len) {
+        let id = &frame_header[..id_len];
+        // Padding after the last frame
+        if !id
+            .iter()
+            .all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit())
+        {
+            break;
+        }
+        let size = match header.major_version {
+            2 => Some(
+                usize::from(frame_header[3]) << 16
+                    | usize::from(frame_header[4]) << 8
+                    | usize::from(frame_header[5]),
+            ),
+            3 => Some(u32::from_be_bytes(frame_header[4..8].try_into().unwrap()) as usize),
+            _ => synchsafe(&frame_header[4..8]),
+        };
+        let content =
+            match size.and_then(|size| body.get(pos + header_len..pos + header_len + size)) {
+                Some(content) => content,
+                None => break,
+            };
+        let id = String::from_utf8_lossy(id).into_owned();
+        frames.push(Id3v2Frame {
+            text: frame_text(&id, content),
+            id,
+            length: content.len(),
+        });
+        pos += header_len + content.len();
+    }
+    frames
+}
+
+/// Undo the unsynchronisation scheme, dropping the 0x00 after every 0xFF
+fn resynchronise(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for (pos, byte) in data.iter().enumerate() {
+        if *byte == 0 && pos > 0 && data[pos - 1] == 0xFF {
+            continue;
+        }
+        out.push(*byte);
+    }
+    out
+}
+
+/// A 28 bit integer stored in the low 7 bits of 4 bytes
+fn synchsafe(bytes: &[u8]) -> Option<usize> {
+    if bytes.len() != 4 || bytes.iter().any(|byte| byte & 0x80 != 0) {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .fold(0, |size, byte| size << 7 | usize::from(*byte)),
+    )
+}
+
+/// The text of a text information or comment frame
+fn frame_text(id: &str, content: &[u8]) -> Option<String> {
+    let (&encoding, rest) = content.split_first()?;
+    if id.starts_with('T') && id != "TXXX" && id != "TXX" {
+        return Some(decode_strings(encoding, rest).join(" / "));
+    }
+    let rest = match id {
+        "TXXX" | "TXX" => rest,
+        // Language before the description
+        "COMM" | "COM" => rest.get(3..)?,
+        _ => return None,
+    };
+    let strings = decode_strings(encoding, rest);
+    match strings.as_slice() {
+        [description, value, ..] if !description.is_empty() => {
+            Some(format!("{}: {}", description, value))
+        }
+        [_, value, ..] => Some(value.clone()),
+        [value] => Some(value.clone()),
+        [] => None,
+    }
+}
+
+/// Decode the strings of a frame, separated by the terminator of the
+/// encoding
+fn decode_strings(encoding: u8, data: &[u8]) -> Vec<String> {
+    let mut strings: Vec<String> = match encoding {
+        1 | 2 => {
+            let units: Vec<u16> = data
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            units
+                .split(|unit| *unit == 0)
+                .map(|units| decode_utf16(encoding, units))
+                .collect()
+        }
+        // ISO-8859-1 maps to the first 256 code points
+        0 => data
+            .split(|byte| *byte == 0)
+            .map(|bytes| bytes.iter().map(|byte| char::from(*byte)).collect())
+            .collect(),
+        _ => data
+            .split(|byte| *byte == 0)
+            .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+            .collect(),
+    };
+    // The terminator after the last string is optional
+    if strings.len() > 1 && strings.last().is_some_and(|last| last.is_empty()) {
+        strings.pop();
+    }
+    strings
+}
+
+/// Decode UTF-16, big endian unless a byte order mark says otherwise
+fn decode_utf16(encoding: u8, units: &[u16]) -> String {
+    let (little_endian, units) = match units.split_first() {
+        Some((0xFEFF, rest)) if encoding == 1 => (false, rest),
+        Some((0xFFFE, rest)) if encoding == 1 => (true, rest),
+        _ => (false, units),
+    };
+    let units = units.iter().map(|unit| {
+        if little_endian {
+            unit.swap_bytes()
+        } else {
+            *unit
+        }
+    });
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Start of an ID3v1 tag
+pub const ID3V1_ID: [u8; 3] = *b"TAG";
+/// Length of an ID3v1 tag, always the last bytes of a file
+pub const ID3V1_LEN: usize = 128;
+
+/// An ID3v1 or ID3v1.1 tag
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Id3v1Tag {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub year: String,
+    pub comment: String,
+    /// Track number, only ID3v1.1 has one in the last bytes of the comment
+    pub track: Option<u8>,
+    /// Index into the list of Winamp genres, `None` if unset
+    pub genre: Option<u8>,
+}
+
+/// Parse the ID3v1 tag at the start of `data`
+pub fn parse_v1(data: &[u8]) -> Option<Id3v1Tag> {
+    let tag = data.get(..ID3V1_LEN)?;
+    if tag[..3] != ID3V1_ID {
+        return None;
+    }
+    // A zero before a non-zero last byte of the comment marks ID3v1.1
+    let (comment, track) = if tag[125] == 0 && tag[126] != 0 {
+        (&tag[97..125], Some(tag[126]))
+    } else {
+        (&tag[97..127], None)
+    };
+    Some(Id3v1Tag {
+        title: v1_text(&tag[3..33]),
+        artist: v1_text(&tag[33..63]),
+        album: v1_text(&tag[63..93]),
+        year: v1_text(&tag[93..97]),
+        comment: v1_text(comment),
+        track,
+        genre: Some(tag[127]).filter(|genre| *genre != 0xFF),
+    })
+}
+
+/// A fixed length ISO-8859-1 field, padded with zeros or spaces
+fn v1_text(field: &[u8]) -> String {
+    let end = field
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(field.len());
+    let text: String = field[..end].iter().map(|byte| char::from(*byte)).collect();
+    text.trim_end().to_string()
+}
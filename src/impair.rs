@@ -0,0 +1,237 @@
+use std::io::{self, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::json::{self, Object};
+use crate::sample::Rng;
+use crate::timefmt::TimeFormat;
+
+/// Network-like impairments applied to segments of frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Impairments {
+    /// Seconds of audio per segment
+    pub segment: f64,
+    /// Chance in percent that a segment swaps places with the next one
+    pub reorder: f64,
+    /// Chance in percent that a segment is written twice
+    pub duplicate: f64,
+    /// Largest random delay of a segment beyond its real time position, in
+    /// milliseconds, 0 to write the copy as fast as possible
+    pub jitter: f64,
+    pub seed: u64,
+}
+
+impl Default for Impairments {
+    fn default() -> Impairments {
+        Impairments {
+            segment: 2.0,
+            reorder: 5.0,
+            duplicate: 2.0,
+            jitter: 0.0,
+            seed: 1,
+        }
+    }
+}
+
+/// Parse impairments given as `name=value` pairs separated by commas
+///
+/// Impairments that are not mentioned keep their default.
+pub fn parse_impairments(value: &str) -> Result<Impairments, String> {
+    let mut impairments = Impairments::default();
+    for pair in value.split(',') {
+        let (name, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("expected name=value, got '{}'", pair))?;
+        let number: f64 = value
+            .parse()
+            .ok()
+            .filter(|number: &f64| *number >= 0.0)
+            .ok_or_else(|| format!("invalid value '{}'", value))?;
+        match name {
+            "segment" if number > 0.0 => impairments.segment = number,
+            "segment" => return Err("segment must be above 0 seconds".to_string()),
+            "reorder" | "duplicate" if number > 100.0 => {
+                return Err(format!("{} must be at most 100 percent", name))
+            }
+            "reorder" => impairments.reorder = number,
+            "duplicate" => impairments.duplicate = number,
+            "jitter" => impairments.jitter = number,
+            "seed" => impairments.seed = number as u64,
+            _ => {
+                return Err(format!(
+                    "unknown impairment '{}' (expected segment, reorder, duplicate, \
+                     jitter or seed)",
+                    name
+                ))
+            }
+        }
+    }
+    Ok(impairments)
+}
+
+/// Consecutive frames of the input that are impaired together
+struct Segment {
+    index: u64,
+    /// Offset of the first frame in the input
+    offset: u64,
+    /// Start time in the input in seconds
+    time: f64,
+    duration: f64,
+    frames: u64,
+    data: Vec<u8>,
+}
+
+/// What was done to the segments of a degraded copy
+#[derive(Debug, Default)]
+pub struct ImpairCounts {
+    pub segments: u64,
+    pub reordered: u64,
+    pub duplicated: u64,
+}
+
+/// Writes a degraded copy of the frames, with segments reordered,
+/// duplicated and delayed, and a log of each segment as written
+///
+/// The log is a JSON object with a `segments` array in output order, so
+/// players' recovery can be checked against what really happened.
+pub struct Impairer<W: Write, L: Write> {
+    out: W,
+    log: Option<L>,
+    impairments: Impairments,
+    rng: Rng,
+    current: Option<Segment>,
+    /// A segment waiting to be written after the next one
+    held: Option<Segment>,
+    next_index: u64,
+    written: u64,
+    /// When the copy started, for jitter
+    started: Instant,
+    counts: ImpairCounts,
+    time_format: TimeFormat,
+}
+
+impl<W: Write, L: Write> Impairer<W, L> {
+    pub fn new(
+        out: W,
+        mut log: Option<L>,
+        impairments: Impairments,
+        file_name: &str,
+        time_format: TimeFormat,
+    ) -> io::Result<Impairer<W, L>> {
+        if let Some(log) = log.as_mut() {
+            writeln!(log, "{{")?;
+            writeln!(log, "  \"file\": {},", json::string(file_name))?;
+            write!(log, "  \"segments\": [")?;
+        }
+        Ok(Impairer {
+            out,
+            log,
+            impairments,
+            rng: Rng::new(impairments.seed),
+            current: None,
+            held: None,
+            next_index: 0,
+            written: 0,
+            started: Instant::now(),
+            counts: ImpairCounts::default(),
+            time_format,
+        })
+    }
+
+    /// Add the frame starting at `offset` and `time` seconds in the input
+    pub fn frame(&mut self, offset: u64, time: f64, duration: f64, frame: &[u8]) -> io::Result<()> {
+        let index = self.next_index;
+        let segment = self.current.get_or_insert_with(|| Segment {
+            index,
+            offset,
+            time,
+            duration: 0.0,
+            frames: 0,
+            data: Vec::new(),
+        });
+        segment.data.extend_from_slice(frame);
+        segment.duration += duration;
+        segment.frames += 1;
+        if segment.duration >= self.impairments.segment {
+            self.next_index += 1;
+            if let Some(segment) = self.current.take() {
+                self.close(segment)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, segment: Segment) -> io::Result<()> {
+        if let Some(held) = self.held.take() {
+            self.write(&segment, "none")?;
+            self.counts.reordered += 1;
+            return self.write(&held, "reordered");
+        }
+        if self.chance(self.impairments.reorder) {
+            self.held = Some(segment);
+            return Ok(());
+        }
+        self.write(&segment, "none")?;
+        if self.chance(self.impairments.duplicate) {
+            self.counts.duplicated += 1;
+            self.write(&segment, "duplicate")?;
+        }
+        Ok(())
+    }
+
+    fn chance(&mut self, percent: f64) -> bool {
+        percent > 0.0 && self.rng.next_f64() * 100.0 < percent
+    }
+
+    fn write(&mut self, segment: &Segment, impairment: &str) -> io::Result<()> {
+        let mut delay = 0.0;
+        if self.impairments.jitter > 0.0 {
+            // Never before the segment's real time position, and never
+            // before the segments already written
+            let jitter = self.rng.next_f64() * self.impairments.jitter / 1000.0;
+            let due = Duration::from_secs_f64(segment.time + jitter);
+            if let Some(wait) = due.checked_sub(self.started.elapsed()) {
+                thread::sleep(wait);
+            }
+            delay = self.started.elapsed().as_secs_f64() - segment.time;
+        }
+
+        self.out.write_all(&segment.data)?;
+        self.out.flush()?;
+        if let Some(log) = self.log.as_mut() {
+            let separator = if self.counts.segments == 0 { "" } else { "," };
+            let entry = Object::new()
+                .number("segment", segment.index)
+                .number("offset", segment.offset)
+                .raw("time", &self.time_format.format_json(segment.time))
+                .number("frames", segment.frames)
+                .number("length", segment.data.len())
+                .number("output_offset", self.written)
+                .string("impairment", impairment)
+                .raw("delay", &self.time_format.format_json(delay))
+                .finish();
+            write!(log, "{}\n    {}", separator, entry)?;
+        }
+        self.written += segment.data.len() as u64;
+        self.counts.segments += 1;
+        Ok(())
+    }
+
+    /// Write the last segments and finish the log
+    pub fn finish(mut self) -> io::Result<ImpairCounts> {
+        if let Some(segment) = self.current.take() {
+            self.close(segment)?;
+        }
+        // Nothing came after it to swap with
+        if let Some(held) = self.held.take() {
+            self.write(&held, "none")?;
+        }
+        self.out.flush()?;
+        if let Some(log) = self.log.as_mut() {
+            writeln!(log, "\n  ]")?;
+            writeln!(log, "}}")?;
+            log.flush()?;
+        }
+        Ok(self.counts)
+    }
+}
@@ -0,0 +1,371 @@
+use std::fmt;
+
+/// Quote and escape `value` as a JSON string
+pub fn string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// A value written as a JSON number
+pub trait Number {
+    fn to_json(&self) -> String;
+}
+
+macro_rules! impl_integer {
+    ($($ty:ty),*) => {
+        $(impl Number for $ty {
+            fn to_json(&self) -> String {
+                self.to_string()
+            }
+        })*
+    };
+}
+
+impl_integer!(u8, u16, u32, u64, usize, i32, i64);
+
+/// JSON has no NaN or infinity, they are written as null
+impl Number for f64 {
+    fn to_json(&self) -> String {
+        if self.is_finite() {
+            self.to_string()
+        } else {
+            "null".to_string()
+        }
+    }
+}
+
+/// Builds a JSON object on a single line
+pub struct Object {
+    out: String,
+}
+
+impl Object {
+    pub fn new() -> Object {
+        Object {
+            out: String::from("{"),
+        }
+    }
+
+    /// Add a field whose value is already valid JSON
+    pub fn raw(mut self, name: &str, value: &str) -> Object {
+        if self.out.len() > 1 {
+            self.out.push_str(", ");
+        }
+        self.out.push_str(&string(name));
+        self.out.push_str(": ");
+        self.out.push_str(value);
+        self
+    }
+
+    /// Add fields whose values are already valid JSON
+    pub fn raw_fields(self, fields: Vec<(String, String)>) -> Object {
+        fields
+            .iter()
+            .fold(self, |object, (name, value)| object.raw(name, value))
+    }
+
+    pub fn string(self, name: &str, value: &str) -> Object {
+        self.raw(name, &string(value))
+    }
+
+    pub fn number<T: Number>(self, name: &str, value: T) -> Object {
+        self.raw(name, &value.to_json())
+    }
+
+    pub fn bool(self, name: &str, value: bool) -> Object {
+        self.raw(name, if value { "true" } else { "false" })
+    }
+
+    pub fn finish(mut self) -> String {
+        self.out.push('}');
+        self.out
+    }
+}
+
+/// Join values that are already valid JSON into an array
+pub fn array<I: IntoIterator<Item = String>>(values: I) -> String {
+    format!("[{}]", values.into_iter().collect::<Vec<_>>().join(", "))
+}
+
+/// A value read back from JSON
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Fields in the order they were written
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// The field `name` of an object
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Written back as JSON on a single line
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Number(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", string(value)),
+            Value::Array(values) => write!(f, "{}", array(values.iter().map(Value::to_string))),
+            Value::Object(fields) => {
+                let object = fields.iter().fold(Object::new(), |object, (name, value)| {
+                    object.raw(name, &value.to_string())
+                });
+                write!(f, "{}", object.finish())
+            }
+        }
+    }
+}
+
+/// Parse the JSON value that makes up all of `text`
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser { text, pos: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < text.len() {
+        return Err(format!("unexpected data at column {}", parser.pos + 1));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\n', '\r']).len();
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        if !self.text[self.pos..].starts_with(token) {
+            return Err(format!("expected '{}' at column {}", token, self.pos + 1));
+        }
+        self.pos += token.len();
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'n') => self.expect("null").map(|_| Value::Null),
+            Some(b't') => self.expect("true").map(|_| Value::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return Ok(Value::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    self.skip_whitespace();
+                    if self.peek() == Some(b']') {
+                        self.pos += 1;
+                        return Ok(Value::Array(values));
+                    }
+                    self.expect(",")?;
+                }
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let mut fields = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                    return Ok(Value::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let name = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(":")?;
+                    fields.push((name, self.value()?));
+                    self.skip_whitespace();
+                    if self.peek() == Some(b'}') {
+                        self.pos += 1;
+                        return Ok(Value::Object(fields));
+                    }
+                    self.expect(",")?;
+                }
+            }
+            Some(b'-') | Some(b'0'..=b'9') => self.number(),
+            _ => Err(format!("expected a value at column {}", self.pos + 1)),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let rest = &self.text[self.pos..];
+        let len = rest
+            .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+            .unwrap_or(rest.len());
+        let number = rest[..len]
+            .parse()
+            .map_err(|_| format!("invalid number at column {}", self.pos + 1))?;
+        self.pos += len;
+        Ok(Value::Number(number))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect("\"")?;
+        let mut value = String::new();
+        let mut chars = self.text[self.pos..].char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += index + 1;
+                    return Ok(value);
+                }
+                '\\' => {
+                    let escaped = match chars.next().map(|(_, c)| c) {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => {
+                            let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                            let code = u32::from_str_radix(&hex, 16).ok();
+                            // Surrogate pairs are not put together, only
+                            // control characters are written escaped
+                            code.and_then(char::from_u32).unwrap_or('\u{fffd}')
+                        }
+                        Some(c) => c,
+                        None => break,
+                    };
+                    value.push(escaped);
+                }
+                c => value.push(c),
+            }
+        }
+        Err(format!("unterminated string at column {}", self.pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(string("a \"b\"\\"), r#""a \"b\"\\""#);
+        assert_eq!(string("\n\t\u{1}"), r#""\n\t\u0001""#);
+    }
+
+    #[test]
+    fn non_finite_numbers_are_null() {
+        let object = Object::new()
+            .number("frames", 3u64)
+            .number("mean", 0.5)
+            .number("max", f64::NAN)
+            .finish();
+        assert_eq!(object, r#"{"frames": 3, "mean": 0.5, "max": null}"#);
+    }
+
+    #[test]
+    fn written_objects_read_back() {
+        let written = Object::new()
+            .string("type", "frame")
+            .string("id", "x,y.aac/0@0#\"quoted\"\n")
+            .number("offset", 142u64)
+            .number("time", -0.25)
+            .bool("vbr", true)
+            .raw("channels", "null")
+            .raw("labels", &Object::new().string("run", "1").finish())
+            .raw("list", &array(vec!["1".to_string(), "[]".to_string()]))
+            .finish();
+
+        let value = parse(&written).unwrap();
+        assert_eq!(value.get("type").and_then(Value::as_str), Some("frame"));
+        assert_eq!(
+            value.get("id").and_then(Value::as_str),
+            Some("x,y.aac/0@0#\"quoted\"\n")
+        );
+        assert_eq!(value.get("offset"), Some(&Value::Number(142.0)));
+        assert_eq!(value.get("time"), Some(&Value::Number(-0.25)));
+        assert_eq!(value.get("vbr"), Some(&Value::Bool(true)));
+        assert_eq!(value.get("channels"), Some(&Value::Null));
+        assert_eq!(
+            value.get("labels").and_then(|labels| labels.get("run")),
+            Some(&Value::String("1".to_string()))
+        );
+        assert_eq!(
+            value.get("list"),
+            Some(&Value::Array(vec![
+                Value::Number(1.0),
+                Value::Array(vec![])
+            ]))
+        );
+        assert_eq!(value.to_string(), written);
+    }
+
+    #[test]
+    fn parses_whitespace_and_escapes() {
+        let value = parse(" { \"a\" :\n[ true , false ] , \"b\": \"\\u00e9\\/\" } ").unwrap();
+        assert_eq!(
+            value,
+            Value::Object(vec![
+                (
+                    "a".to_string(),
+                    Value::Array(vec![Value::Bool(true), Value::Bool(false)])
+                ),
+                ("b".to_string(), Value::String("\u{e9}/".to_string())),
+            ])
+        );
+    }
+
+    #[test]
+    fn reports_where_parsing_failed() {
+        assert_eq!(
+            parse("{\"a\": 1} x").unwrap_err(),
+            "unexpected data at column 10"
+        );
+        assert_eq!(parse("{\"a\" 1}").unwrap_err(), "expected ':' at column 6");
+        assert_eq!(parse("[1, ]").unwrap_err(), "expected a value at column 5");
+        assert!(parse("\"open").is_err());
+        assert!(parse("-").is_err());
+    }
+}
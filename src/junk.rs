@@ -0,0 +1,130 @@
+/// Bytes at the start of a junk region the classifiers look at
+pub const SAMPLE_LEN: usize = 4096;
+
+const TS_PACKET_LEN: usize = 188;
+const TS_SYNC: u8 = 0x47;
+
+/// Header names that start a line of HTTP or ICY response headers
+const HTTP_HEADERS: [&str; 6] = [
+    "content-type:",
+    "content-length:",
+    "server:",
+    "icy-",
+    "connection:",
+    "cache-control:",
+];
+
+/// What the data of a junk region most likely is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    ZeroPadding,
+    Id3,
+    Http,
+    TransportStream,
+    Text,
+    Noise,
+    Unknown,
+}
+
+impl Kind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::ZeroPadding => "zero padding",
+            Kind::Id3 => "ID3 tag",
+            Kind::Http => "HTTP headers",
+            Kind::TransportStream => "MPEG-TS packets",
+            Kind::Text => "text",
+            Kind::Noise => "random noise",
+            Kind::Unknown => "unknown data",
+        }
+    }
+}
+
+/// Data between or before frames that is not part of the stream
+pub struct Junk {
+    pub offset: u64,
+    pub length: u64,
+    pub kind: Kind,
+}
+
+impl Junk {
+    /// Classify the junk region of `length` bytes at `offset` that starts
+    /// with `sample`
+    pub fn new(offset: u64, length: u64, sample: &[u8]) -> Junk {
+        let len = sample.len().min(length as usize);
+        Junk {
+            offset,
+            length,
+            kind: classify(&sample[..len]),
+        }
+    }
+}
+
+/// Guess what `data` is, from quick checks of its first bytes
+pub fn classify(data: &[u8]) -> Kind {
+    let data = &data[..data.len().min(SAMPLE_LEN)];
+    if data.is_empty() {
+        return Kind::Unknown;
+    }
+
+    if data.iter().all(|&byte| byte == 0) {
+        Kind::ZeroPadding
+    } else if data.starts_with(b"ID3") || (data.starts_with(b"TAG") && data.len() <= 128) {
+        Kind::Id3
+    } else if looks_like_http(data) {
+        Kind::Http
+    } else if looks_like_ts(data) {
+        Kind::TransportStream
+    } else if looks_like_text(data) {
+        Kind::Text
+    } else if looks_like_noise(data) {
+        Kind::Noise
+    } else {
+        Kind::Unknown
+    }
+}
+
+fn looks_like_http(data: &[u8]) -> bool {
+    if data.starts_with(b"HTTP/") || data.starts_with(b"ICY ") {
+        return true;
+    }
+    let text = String::from_utf8_lossy(data).to_ascii_lowercase();
+    text.lines()
+        .any(|line| HTTP_HEADERS.iter().any(|name| line.starts_with(name)))
+}
+
+/// Sync bytes at packet distance, for at least two packets
+fn looks_like_ts(data: &[u8]) -> bool {
+    let packets = data.len().div_ceil(TS_PACKET_LEN);
+    packets >= 2 && (0..packets).all(|packet| data[packet * TS_PACKET_LEN] == TS_SYNC)
+}
+
+/// Mostly printable ASCII
+fn looks_like_text(data: &[u8]) -> bool {
+    let printable = data
+        .iter()
+        .filter(|&&byte| {
+            byte == b'\n' || byte == b'\r' || byte == b'\t' || (0x20..0x7F).contains(&byte)
+        })
+        .count();
+    printable * 100 >= data.len() * 95
+}
+
+/// Byte entropy close to the maximum possible for the amount of data
+fn looks_like_noise(data: &[u8]) -> bool {
+    let mut counts = [0usize; 256];
+    for &byte in data {
+        counts[usize::from(byte)] += 1;
+    }
+    let total = data.len() as f64;
+    let entropy: f64 = counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum();
+    let max = total.min(256.0).log2();
+    entropy >= max * 0.9
+}
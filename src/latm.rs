@@ -0,0 +1,214 @@
+use crate::bitcursor::{BitCursor, BitError};
+use crate::header::SAMPLING_FREQUENCIES;
+
+/// Syncword of the LOAS AudioSyncStream, ISO 14496-3 1.7.2
+pub const LOAS_SYNCWORD: u16 = 0x2B7;
+/// Length of the AudioSyncStream header before the AudioMuxElement
+pub const LOAS_HEADER_LEN: usize = 3;
+
+/// Audio object types that use a GASpecificConfig
+const GA_OBJECT_TYPES: [u8; 12] = [1, 2, 3, 4, 6, 7, 17, 19, 20, 21, 22, 23];
+
+/// The fields of an AudioSpecificConfig needed to describe the stream,
+/// ISO 14496-3 1.6.2.1
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct AudioSpecificConfig {
+    /// Object type of the core, the one under SBR and PS
+    pub object_type: u8,
+    pub sampling_frequency: u32,
+    pub channel_configuration: u8,
+    /// Whether SBR is signalled explicitly, and the output sampling
+    /// frequency it doubles the core to
+    pub sbr_frequency: Option<u32>,
+    /// Whether parametric stereo is signalled explicitly
+    pub ps: bool,
+    /// Samples per channel in a frame of the core
+    pub frame_samples: u32,
+}
+
+impl AudioSpecificConfig {
+    pub fn object_type_name(&self) -> String {
+        object_type_name(self.object_type)
+    }
+}
+
+/// Human readable name of an MPEG-4 audio object type
+pub fn object_type_name(object_type: u8) -> String {
+    match object_type {
+        1 => "AAC Main".to_string(),
+        2 => "AAC LC".to_string(),
+        3 => "AAC SSR".to_string(),
+        4 => "AAC LTP".to_string(),
+        5 => "SBR".to_string(),
+        6 => "AAC Scalable".to_string(),
+        17 => "ER AAC LC".to_string(),
+        23 => "ER AAC LD".to_string(),
+        29 => "PS".to_string(),
+        39 => "ER AAC ELD".to_string(),
+        42 => "USAC".to_string(),
+        other => format!("object type {}", other),
+    }
+}
+
+/// The fields of a StreamMuxConfig, ISO 14496-3 1.7.3.1
+///
+/// Only the first program's first layer is described.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct StreamMuxConfig {
+    pub audio_mux_version: u8,
+    pub all_streams_same_time_framing: bool,
+    /// Number of payloads in each AudioMuxElement
+    pub sub_frames: u8,
+    pub programs: u8,
+    /// Layers of the first program
+    pub layers: u8,
+    pub audio_specific_config: AudioSpecificConfig,
+}
+
+impl StreamMuxConfig {
+    /// Samples per channel in an AudioMuxElement
+    pub fn samples(&self) -> u32 {
+        u32::from(self.sub_frames) * self.audio_specific_config.frame_samples
+    }
+
+    /// Duration of an AudioMuxElement in seconds
+    pub fn duration(&self) -> f64 {
+        f64::from(self.samples()) / f64::from(self.audio_specific_config.sampling_frequency)
+    }
+}
+
+/// An AudioSyncStream frame, holding one AudioMuxElement
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct LoasFrame {
+    /// Length in bytes, including the sync layer header
+    pub length: usize,
+    /// The StreamMuxConfig of the element, `None` if it uses the one of an
+    /// earlier element
+    pub config: Option<StreamMuxConfig>,
+    /// Whether the StreamMuxConfig is one this parser does not support, like
+    /// audioMuxVersionA 1
+    pub unsupported: bool,
+}
+
+/// Parse the AudioSyncStream frame at the start of `data`
+///
+/// Only the sync layer header has to be complete, the StreamMuxConfig is
+/// read if `data` has it.
+pub fn parse_frame(data: &[u8]) -> Option<LoasFrame> {
+    let header = data.get(..LOAS_HEADER_LEN)?;
+    if u16::from(header[0]) << 3 | u16::from(header[1] >> 5) != LOAS_SYNCWORD {
+        return None;
+    }
+    let mux_length = usize::from(header[1] & 0x1F) << 8 | usize::from(header[2]);
+    let length = LOAS_HEADER_LEN + mux_length;
+    if mux_length == 0 {
+        return None;
+    }
+
+    let element = &data[LOAS_HEADER_LEN..length.min(data.len())];
+    let mut reader = BitCursor::new(element, LOAS_HEADER_LEN as u64);
+    let (config, unsupported) = match reader.read_bool() {
+        Ok(true) | Err(_) => (None, false),
+        Ok(false) => match read_stream_mux_config(&mut reader) {
+            Ok(Some(config)) => (Some(config), false),
+            Ok(None) | Err(_) => (None, true),
+        },
+    };
+    Some(LoasFrame {
+        length,
+        config,
+        unsupported,
+    })
+}
+
+fn read_stream_mux_config(reader: &mut BitCursor) -> Result<Option<StreamMuxConfig>, BitError> {
+    let audio_mux_version = reader.read_u8(1)?;
+    if audio_mux_version == 1 && reader.read_bool()? {
+        // audioMuxVersionA 1 is reserved
+        return Ok(None);
+    }
+    if audio_mux_version == 1 {
+        // taraBufferFullness
+        read_latm_value(reader)?;
+    }
+    let all_streams_same_time_framing = reader.read_bool()?;
+    let sub_frames = reader.read_u8(6)? + 1;
+    let programs = reader.read_u8(4)? + 1;
+    let layers = reader.read_u8(3)? + 1;
+    if audio_mux_version == 1 {
+        // ascLen
+        read_latm_value(reader)?;
+    }
+    let audio_specific_config = match read_audio_specific_config(reader)? {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+
+    Ok(Some(StreamMuxConfig {
+        audio_mux_version,
+        all_streams_same_time_framing,
+        sub_frames,
+        programs,
+        layers,
+        audio_specific_config,
+    }))
+}
+
+fn read_latm_value(reader: &mut BitCursor) -> Result<u32, BitError> {
+    let bytes = reader.read_u8(2)? + 1;
+    let mut value = 0;
+    for _ in 0..bytes {
+        value = value << 8 | u32::from(reader.read_u8(8)?);
+    }
+    Ok(value)
+}
+
+fn read_audio_specific_config(
+    reader: &mut BitCursor,
+) -> Result<Option<AudioSpecificConfig>, BitError> {
+    let mut object_type = read_object_type(reader)?;
+    let sampling_frequency = match read_sampling_frequency(reader)? {
+        Some(frequency) => frequency,
+        None => return Ok(None),
+    };
+    let channel_configuration = reader.read_u8(4)?;
+    let mut sbr_frequency = None;
+    let mut ps = false;
+    if object_type == 5 || object_type == 29 {
+        ps = object_type == 29;
+        sbr_frequency = read_sampling_frequency(reader)?;
+        object_type = read_object_type(reader)?;
+    }
+    // frameLengthFlag of the GASpecificConfig
+    let frame_samples = if GA_OBJECT_TYPES.contains(&object_type) && reader.read_bool()? {
+        960
+    } else {
+        1024
+    };
+
+    Ok(Some(AudioSpecificConfig {
+        object_type,
+        sampling_frequency,
+        channel_configuration,
+        sbr_frequency,
+        ps,
+        frame_samples,
+    }))
+}
+
+fn read_object_type(reader: &mut BitCursor) -> Result<u8, BitError> {
+    match reader.read_u8(5)? {
+        31 => Ok(32 + reader.read_u8(6)?),
+        object_type => Ok(object_type),
+    }
+}
+
+fn read_sampling_frequency(reader: &mut BitCursor) -> Result<Option<u32>, BitError> {
+    match reader.read_u8(4)? {
+        15 => Ok(Some(reader.read_u32(24)?)),
+        index => Ok(SAMPLING_FREQUENCIES.get(usize::from(index)).copied()),
+    }
+}
@@ -0,0 +1,127 @@
+use streamreader::{AdtsHeader, MPEGAudioObjectType, AAC_FRAME_SAMPLES};
+
+use crate::bitrate;
+
+/// Levels of the AAC Profile with their highest number of main channels
+/// and sampling frequency, ISO 14496-3 1.5.2.2
+const LEVELS: [(u8, u8, u32); 6] = [
+    (1, 2, 24000),
+    (2, 2, 48000),
+    (4, 5, 48000),
+    (5, 5, 96000),
+    (6, 7, 48000),
+    (7, 7, 96000),
+];
+
+/// Size of the decoder input buffer per channel in bits, which no raw data
+/// block may exceed
+const BUFFER_BITS_PER_CHANNEL: u64 = 6144;
+
+/// Collects what the minimum decoder level depends on over all frames
+pub struct LevelCheck {
+    channels: u8,
+    sample_rate: u32,
+    peak_bitrate: f64,
+    /// Why no level of the AAC Profile fits, from the first frame found
+    unsupported: Option<String>,
+}
+
+impl LevelCheck {
+    pub fn new() -> LevelCheck {
+        LevelCheck {
+            channels: 0,
+            sample_rate: 0,
+            peak_bitrate: 0.0,
+            unsupported: None,
+        }
+    }
+
+    pub fn push(&mut self, offset: u64, header: &AdtsHeader) {
+        let channels = main_channels(header.channel_configuration);
+        self.channels = self.channels.max(channels);
+        self.sample_rate = self.sample_rate.max(header.sampling_frequency());
+        self.peak_bitrate = self.peak_bitrate.max(bitrate::frame_bitrate(
+            header.frame_length,
+            header.duration(),
+        ));
+        if self.unsupported.is_some() {
+            return;
+        }
+
+        if header.profile != MPEGAudioObjectType::AAC_LC {
+            self.unsupported = Some(format!(
+                "frame at offset {} is {:?}, the AAC Profile only has AAC_LC",
+                offset, header.profile
+            ));
+        } else if header.channel_configuration == 0 {
+            self.unsupported = Some(format!(
+                "frame at offset {} has no channel configuration to count channels by",
+                offset
+            ));
+        } else {
+            let payload_bits =
+                (usize::from(header.frame_length) - header.header_length()) as u64 * 8;
+            let limit =
+                BUFFER_BITS_PER_CHANNEL * u64::from(channels) * header.raw_data_blocks() as u64;
+            if payload_bits > limit {
+                self.unsupported = Some(format!(
+                    "frame at offset {} has {} bits of raw data, more than the decoder \
+                     buffer of {} bits",
+                    offset, payload_bits, limit
+                ));
+            }
+        }
+    }
+
+    /// Lowest level of the AAC Profile a decoder needs for the stream, or
+    /// why there is none
+    ///
+    /// HE-AAC signals its SBR data implicitly in ADTS, so these streams are
+    /// rated by their AAC core.
+    pub fn level(&self) -> Result<u8, String> {
+        if let Some(reason) = self.unsupported.as_ref() {
+            return Err(reason.clone());
+        }
+        LEVELS
+            .iter()
+            .find(|(_, channels, rate)| self.channels <= *channels && self.sample_rate <= *rate)
+            .map(|(level, _, _)| *level)
+            .ok_or_else(|| {
+                format!(
+                    "{} channels at {} Hz exceed every level",
+                    self.channels, self.sample_rate
+                )
+            })
+    }
+
+    /// Highest number of main channels, without LFE
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Highest bitrate of a single frame in bit/s
+    pub fn peak_bitrate(&self) -> f64 {
+        self.peak_bitrate
+    }
+}
+
+/// Full bandwidth channels of a channel configuration, LFE channels do not
+/// count towards the level
+fn main_channels(channel_configuration: u8) -> u8 {
+    match channel_configuration {
+        6 => 5,
+        7 => 7,
+        count => count,
+    }
+}
+
+/// Highest bitrate the decoder buffer allows for `channels` at
+/// `sample_rate`, in bit/s
+pub fn max_bitrate(channels: u8, sample_rate: u32) -> f64 {
+    BUFFER_BITS_PER_CHANNEL as f64 * f64::from(channels) * f64::from(sample_rate)
+        / f64::from(AAC_FRAME_SAMPLES)
+}
@@ -0,0 +1,47 @@
+//! Parser for AAC streams in ADTS framing
+//!
+//! `AdtsParser` walks any `Read + Seek` source frame by frame, `AdtsReader`
+//! does the same for sources that cannot seek, and `parse_header` decodes a
+//! single header from memory. `parse_header_vectored` does so for a header
+//! split across several buffers. `AdtsFrames` iterates over the frames of any
+//! `Read` source.
+//!
+//! `sniff` rates an arbitrary buffer against ADTS and the other supported
+//! framings, for content type detection from the first bytes of an upload.
+//! `ts::TsDemuxer` reads the AAC stream out of an MPEG transport stream, for
+//! any of the above.
+//!
+//! `prelude` has the common types for a glob import. Enums that will grow
+//! with new formats or errors and the structs of parsed headers are
+//! `#[non_exhaustive]`, so new variants and fields are not breaking changes:
+//! match them with a wildcard arm and read their fields instead of building
+//! or destructuring them.
+
+pub mod ac3;
+pub mod adif;
+pub mod ape;
+pub mod bitcursor;
+pub mod crc;
+pub mod dts;
+pub mod elements;
+pub mod flac;
+mod frames;
+mod header;
+pub mod id3;
+pub mod latm;
+pub mod mpeg_audio;
+pub mod ogg;
+mod parser;
+pub mod prelude;
+mod reader;
+pub mod sniff;
+pub mod ts;
+
+pub use crate::frames::{AdtsFrame, AdtsFrames};
+pub use crate::header::{
+    channel_count, channel_layout, parse_header, parse_header_vectored, AdtsError, AdtsHeader,
+    MPEGAudioObjectType, MPEGVersion, AAC_FRAME_SAMPLES, ADTS_HDR_MAX_LEN, ADTS_HDR_MIN_LEN,
+    ADTS_MAX_FRAME_LEN, SAMPLING_FREQUENCIES,
+};
+pub use crate::parser::AdtsParser;
+pub use crate::reader::AdtsReader;
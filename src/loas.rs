@@ -0,0 +1,252 @@
+use std::io::{self, Write};
+
+use streamreader::latm::{self, StreamMuxConfig};
+use streamreader::{channel_layout, AdtsHeader};
+
+use crate::walk::Frame;
+
+/// Syncword of the LOAS AudioSyncStream, ISO 14496-3 1.7.2
+const LOAS_SYNC: u32 = 0x2B7;
+/// Largest value of the 13 bit audioMuxLengthBytes field
+const MAX_MUX_LENGTH: usize = 8191;
+/// The StreamMuxConfig is repeated this often, so players can start
+/// decoding in the middle of the output
+const CONFIG_INTERVAL: u32 = 20;
+
+/// Rewraps ADTS frames into LOAS/LATM, for players that only accept that
+///
+/// Every frame becomes one AudioMuxElement with a single subframe. The
+/// StreamMuxConfig is built from the ADTS header and sent with the first
+/// frame, whenever the stream parameters change and every
+/// `CONFIG_INTERVAL` frames.
+pub struct LoasWriter<W: Write> {
+    out: W,
+    /// Object type, sampling frequency index and channel configuration of
+    /// the last StreamMuxConfig sent
+    config: Option<(u8, u8, u8)>,
+    since_config: u32,
+    skipped: u64,
+}
+
+impl<W: Write> LoasWriter<W> {
+    pub fn new(out: W) -> LoasWriter<W> {
+        LoasWriter {
+            out,
+            config: None,
+            since_config: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Write `frame`, unless LATM cannot carry it without parsing the
+    /// payload
+    ///
+    /// Without a channel configuration the program_config_element would
+    /// have to move from the payload to the AudioSpecificConfig, and the
+    /// boundaries of several raw data blocks in one frame are not known.
+    pub fn frame(&mut self, header: &AdtsHeader, frame: &[u8]) -> io::Result<()> {
+        let payload = &frame[header.header_length()..];
+        if header.channel_configuration == 0 || header.num_raw_data_blocks != 0 {
+            self.skipped += 1;
+            return Ok(());
+        }
+
+        let config = (
+            header.profile as u8,
+            header.sampling_frequency_index,
+            header.channel_configuration,
+        );
+        let send_config = self.config != Some(config) || self.since_config >= CONFIG_INTERVAL;
+
+        let mut element = BitWriter::new();
+        // useSameStreamMux
+        element.write(u32::from(!send_config), 1);
+        if send_config {
+            write_stream_mux_config(&mut element, config);
+            self.config = Some(config);
+            self.since_config = 0;
+        }
+        self.since_config += 1;
+        // PayloadLengthInfo for frameLengthType 0
+        let mut length = payload.len();
+        while length >= 255 {
+            element.write(255, 8);
+            length -= 255;
+        }
+        element.write(length as u32, 8);
+        // PayloadMux
+        for &byte in payload {
+            element.write(u32::from(byte), 8);
+        }
+        let element = element.finish();
+        if element.len() > MAX_MUX_LENGTH {
+            self.skipped += 1;
+            return Ok(());
+        }
+
+        let sync = (LOAS_SYNC << 13) | element.len() as u32;
+        self.out.write_all(&sync.to_be_bytes()[1..])?;
+        self.out.write_all(&element)
+    }
+
+    /// Flush the output, returning the number of frames left out
+    pub fn finish(mut self) -> io::Result<u64> {
+        self.out.flush()?;
+        Ok(self.skipped)
+    }
+}
+
+/// StreamMuxConfig for audioMuxVersion 0 with one program of one layer
+fn write_stream_mux_config(element: &mut BitWriter, config: (u8, u8, u8)) {
+    let (object_type, sampling_frequency_index, channel_configuration) = config;
+    // audioMuxVersion
+    element.write(0, 1);
+    // allStreamsSameTimeFraming
+    element.write(1, 1);
+    // numSubFrames
+    element.write(0, 6);
+    // numProgram
+    element.write(0, 4);
+    // numLayer
+    element.write(0, 3);
+
+    // AudioSpecificConfig
+    element.write(u32::from(object_type), 5);
+    element.write(u32::from(sampling_frequency_index), 4);
+    element.write(u32::from(channel_configuration), 4);
+    // GASpecificConfig: frameLengthFlag, dependsOnCoreCoder, extensionFlag
+    element.write(0, 3);
+
+    // frameLengthType
+    element.write(0, 3);
+    // latmBufferFullness
+    element.write(0xFF, 8);
+    // otherDataPresent
+    element.write(0, 1);
+    // crcCheckPresent
+    element.write(0, 1);
+}
+
+/// Collects fields MSB first, padding the last byte with zeros
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter {
+            bytes: Vec::new(),
+            bits: 0,
+        }
+    }
+
+    /// Append the lowest `width` bits of `value`
+    fn write(&mut self, value: u32, width: u8) {
+        for bit in (0..width).rev() {
+            if self.bits == 0 {
+                self.bytes.push(0);
+            }
+            let last = self.bytes.len() - 1;
+            self.bytes[last] |= (((value >> bit) & 1) as u8) << (7 - self.bits);
+            self.bits = (self.bits + 1) % 8;
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Warning about explicitly signalled SBR whose output rate is not twice
+/// the core rate
+///
+/// Decoders without SBR play the core at the core rate, so an encoder
+/// that signals the output rate as the core rate, leaving both the same,
+/// makes them play at the wrong speed.
+fn sbr_rate_mismatch(config: &latm::AudioSpecificConfig) -> Option<(&'static str, String)> {
+    let output = config.sbr_frequency?;
+    let core = config.sampling_frequency;
+    if output == 2 * core {
+        return None;
+    }
+    let extension = if config.ps { "SBR and PS" } else { "SBR" };
+    Some((
+        "sbr-rate",
+        format!(
+            "{} output rate {} Hz is not twice the core rate {} Hz",
+            extension, output, core
+        ),
+    ))
+}
+
+/// Parser for LOAS input, for `walk`
+///
+/// AudioMuxElements that reuse the StreamMuxConfig of an earlier one are
+/// described by the last config seen. Until there is one, their duration
+/// is not known and counts as 0.
+pub fn parser() -> impl FnMut(&[u8]) -> Option<Frame> {
+    let mut current: Option<StreamMuxConfig> = None;
+    move |data| {
+        let frame = latm::parse_frame(data)?;
+        let mut lines = vec![format!("Len is {}", frame.length)];
+        let mut warnings = Vec::new();
+        if frame.unsupported {
+            lines.push("Stream mux config is not supported".to_string());
+            current = None;
+        } else if let Some(config) = frame.config {
+            lines.push(format!(
+                "Stream mux config is new, audio mux version {}",
+                config.audio_mux_version
+            ));
+            warnings.extend(sbr_rate_mismatch(&config.audio_specific_config));
+            current = Some(config);
+        } else {
+            lines.push("Stream mux config is the previous one".to_string());
+        }
+
+        let config = match current.as_ref() {
+            Some(config) => config,
+            None => {
+                return Some(Frame {
+                    length: frame.length,
+                    duration: 0.0,
+                    lines,
+                    warnings,
+                    advertised: None,
+                })
+            }
+        };
+        let asc = &config.audio_specific_config;
+        let mut profile = format!("Profile is {}", asc.object_type_name());
+        if let Some(frequency) = asc.sbr_frequency {
+            let extension = if asc.ps { "SBR and PS" } else { "SBR" };
+            profile.push_str(&format!(" with {} at {} Hz", extension, frequency));
+        }
+        lines.push(profile);
+        lines.push(format!(
+            "Sampling frequency is {} Hz",
+            asc.sampling_frequency
+        ));
+        lines.push(format!(
+            "Channel configuration is {}, {}",
+            asc.channel_configuration,
+            channel_layout(asc.channel_configuration)
+        ));
+        lines.push(format!("Subframes: {}", config.sub_frames));
+        if config.programs > 1 || config.layers > 1 {
+            lines.push(format!(
+                "Programs: {}, layers of the first: {}, only the first layer is described",
+                config.programs, config.layers
+            ));
+        }
+
+        Some(Frame {
+            length: frame.length,
+            duration: config.duration(),
+            lines,
+            warnings,
+            advertised: None,
+        })
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+/// Position of a frame in the scanned stream
+#[derive(Debug, Clone, Copy)]
+pub struct FrameRef {
+    pub index: usize,
+    pub offset: u64,
+    /// Start time in seconds
+    pub time: f64,
+    /// Duration in seconds
+    pub duration: f64,
+}
+
+impl FrameRef {
+    /// End time in seconds
+    pub fn end_time(&self) -> f64 {
+        self.time + self.duration
+    }
+}
+
+/// A stretch of frames repeating earlier content exactly
+#[derive(Debug)]
+pub struct Loop {
+    /// First frame of the content that is being repeated
+    pub source: FrameRef,
+    /// First frame of the repetition
+    pub start: FrameRef,
+    /// Last repeated frame
+    pub end: FrameRef,
+    /// Length of the repeated content in frames
+    pub period: usize,
+}
+
+impl Loop {
+    pub fn repeated_frames(&self) -> usize {
+        self.end.index - self.start.index + 1
+    }
+}
+
+struct Candidate {
+    source: FrameRef,
+    start: FrameRef,
+    end: FrameRef,
+}
+
+/// Detects recurring sequences of frame payload hashes
+///
+/// A loop is reported once at least `min_frames` consecutive frames repeat
+/// the frames exactly one period earlier. Periods shorter than `min_frames`
+/// are ignored, so runs of identical frames (digital silence) do not count.
+pub struct LoopDetector {
+    min_frames: usize,
+    frames: Vec<(u64, FrameRef)>,
+    first_seen: HashMap<u64, usize>,
+    candidate: Option<Candidate>,
+    loops: Vec<Loop>,
+}
+
+impl LoopDetector {
+    pub fn new(min_frames: usize) -> LoopDetector {
+        LoopDetector {
+            min_frames,
+            frames: Vec::new(),
+            first_seen: HashMap::new(),
+            candidate: None,
+            loops: Vec::new(),
+        }
+    }
+
+    /// Feed the payload hash of the next frame
+    pub fn push(&mut self, hash: u64, frame: FrameRef) {
+        let index = frame.index;
+
+        if let Some(candidate) = self.candidate.as_mut() {
+            let period = candidate.start.index - candidate.source.index;
+            if self.frames[index - period].0 == hash {
+                candidate.end = frame;
+            } else {
+                self.end_candidate();
+            }
+        }
+
+        if self.candidate.is_none() {
+            if let Some(&source) = self.first_seen.get(&hash) {
+                if index - source >= self.min_frames {
+                    self.candidate = Some(Candidate {
+                        source: self.frames[source].1,
+                        start: frame,
+                        end: frame,
+                    });
+                }
+            }
+        }
+
+        self.first_seen.entry(hash).or_insert(index);
+        self.frames.push((hash, frame));
+    }
+
+    /// Finish detection, returning all loops found
+    pub fn finish(&mut self) -> &[Loop] {
+        self.end_candidate();
+        &self.loops
+    }
+
+    fn end_candidate(&mut self) {
+        if let Some(candidate) = self.candidate.take() {
+            let found = Loop {
+                period: candidate.start.index - candidate.source.index,
+                source: candidate.source,
+                start: candidate.start,
+                end: candidate.end,
+            };
+            if found.repeated_frames() >= self.min_frames {
+                self.loops.push(found);
+            }
+        }
+    }
+}
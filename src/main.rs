@@ -1,253 +1,3514 @@
-use exitcode;
+mod bitrate;
+mod boundaries;
+mod carve;
+mod checkpoint;
+mod chunks;
+mod compare;
+mod dca;
+mod decoder;
+mod describe;
+mod digest;
+mod dolby;
+mod extract;
+mod hash;
+mod health;
+mod impair;
+mod json;
+mod junk;
+mod level;
+mod loas;
+mod loops;
+mod marker;
+mod mp3;
+mod mp4;
+mod output;
+mod pages;
+mod pairing;
+mod profile;
+mod realign;
+mod redact;
+mod references;
+mod report;
+mod retime;
+mod sample;
+mod sha256;
+mod stats;
+mod timefmt;
+mod timeline;
+mod trailers;
+mod verdict;
+mod walk;
+mod warnings;
+mod xiph;
 
-use std::env;
+use std::cell::RefCell;
+use std::convert::TryInto;
 use std::fs;
+use std::io;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::ErrorKind;
 use std::io::Read;
 use std::io::Seek;
 use std::io::SeekFrom;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::rc::Rc;
+use std::thread;
 
-use bitreader::BitReader;
 use structopt::StructOpt;
 
-/*
-Note from https://stackoverflow.com/a/4678183
+use streamreader::adif::{self, AdifHeader};
+use streamreader::crc::{self, CrcCheck};
+use streamreader::elements;
+use streamreader::id3::{self, Id3v2Header};
+use streamreader::sniff::{self, Acceptance, FrameRun};
+use streamreader::ts::{
+    AacFraming, AacStream, ElementaryStream, GapOutcome, MuxStats, TsDemuxer, NULL_PID,
+};
+use streamreader::{
+    parse_header, AdtsError, AdtsFrames, AdtsHeader, AdtsParser, AdtsReader, ADTS_HDR_MIN_LEN,
+    ADTS_MAX_FRAME_LEN, SAMPLING_FREQUENCIES,
+};
 
-ADTS sample rates and channel counts are for HE-AAC and HE-AACv2 to try to maintain
-compatibility with LC only decoders. The good news is that it they are inaccurate in
-a precise manner. HE-AAC will report half the sample rate and HE-AACv2 will always
-report a mono stream. This is because HE-AAC adds SBR which double the sample rate
-and HE-AACv2 adds parametric stereo to SBR and PS turning a mono stream into a stereo
-image. The SBR payload lives inside an AAC fill element which is ignored by an LC only
-encoder and the PS payload lives inside the SBR payload.
-
-Some decoders assume SBR if the sample rate <= 24kHz and always decode mono streams to
-stereo to avoid detecting these features up front. In that case the SBR decoder can be
-run in a pure upsampling mode if SBR data is not found.
-*/
+use crate::bitrate::BitrateStats;
+use crate::boundaries::BoundaryWriter;
+use crate::carve::Run;
+use crate::checkpoint::{CrcCounts, Progress};
+use crate::chunks::ChunkWriter;
+use crate::compare::Report;
+use crate::decoder::Decoder;
+use crate::digest::{Digest, DigestPool};
+use crate::extract::Extractor;
+use crate::hash::fnv1a;
+use crate::impair::{Impairer, Impairments};
+use crate::junk::Junk;
+use crate::level::LevelCheck;
+use crate::loas::LoasWriter;
+use crate::loops::{FrameRef, Loop, LoopDetector};
+use crate::marker::{Marker, MarkerInjector};
+use crate::mp4::Mp4Writer;
+use crate::output::{Format, Summary};
+use crate::pairing::PairedStream;
+use crate::profile::{Profile, Stage};
+use crate::realign::Transform;
+use crate::redact::Redactor;
+use crate::references::{Match, Reference, ReferenceMatcher};
+use crate::retime::Retiming;
+use crate::stats::FrameStats;
+use crate::timefmt::TimeFormat;
+use crate::timeline::Event;
+use crate::trailers::Trailer;
+use crate::verdict::{Ending, Findings, ParameterChange, Resync};
+use crate::walk::{InputFormat, ParseFrame, TotalsCheck};
+use crate::warnings::Warnings;
 
 #[derive(Debug, StructOpt)]
 struct CliArgs {
     /// Input File
-    filepath: PathBuf,
+    #[structopt(
+        parse(from_os_str),
+        raw(required_unless_one = r#"&["describe_json", "compare_reports"]"#)
+    )]
+    filepath: Option<PathBuf>,
     /// Offset within the input file
     #[structopt(default_value = "0")]
     offset: u32,
+    /// Report where time was spent at the end of the run
+    #[structopt(long = "profile-self")]
+    profile_self: bool,
+    /// Print at most this many warnings of each kind
+    #[structopt(long = "warn-limit")]
+    warn_limit: Option<u64>,
+    /// Detect frame sequences repeating earlier content exactly
+    #[structopt(long = "detect-loops")]
+    detect_loops: bool,
+    /// ADTS clip to search for in the input, may be given multiple times
+    #[structopt(long = "reference", parse(from_os_str), number_of_values = 1)]
+    references: Vec<PathBuf>,
+    /// Print a classification of the stream's overall health at the end
+    #[structopt(long = "verdict")]
+    verdict: bool,
+    /// Print a 0-100 health score of the stream
+    #[structopt(long = "health")]
+    health: bool,
+    /// Weights of the health score components errors, stability, headers,
+    /// bitrate, gaps and loops, e.g. "errors=50,loops=0"
+    #[structopt(long = "health-weights", parse(try_from_str = "health::parse_weights"))]
+    health_weights: Option<health::Weights>,
+    /// Write detected loops and reference matches as a CUE sheet
+    #[structopt(long = "cue", parse(from_os_str))]
+    cue: Option<PathBuf>,
+    /// Write detected loops and reference matches as Matroska chapter XML
+    #[structopt(long = "chapters", parse(from_os_str))]
+    chapters: Option<PathBuf>,
+    /// Write a copy of the input with all content but the frame headers
+    /// zeroed, for sharing in bug reports
+    #[structopt(long = "redact", parse(from_os_str))]
+    redact: Option<PathBuf>,
+    /// Write all frames without the data between them, recovering the
+    /// elementary stream
+    #[structopt(long = "extract", parse(from_os_str))]
+    extract: Option<PathBuf>,
+    /// Strip the ADTS headers from --extract, writing only raw data blocks
+    #[structopt(long = "extract-raw")]
+    extract_raw: bool,
+    /// Write all frames rewrapped into LOAS/LATM, for players that do not
+    /// accept ADTS
+    #[structopt(long = "loas", parse(from_os_str))]
+    loas: Option<PathBuf>,
+    /// Write all frames into an MP4/M4A file with a single AAC track
+    #[structopt(long = "mp4", parse(from_os_str))]
+    mp4: Option<PathBuf>,
+    /// Write a copy of the input with latency markers, the wall clock time
+    /// in a data stream element, for --detect-markers at the far end of a
+    /// distribution chain
+    #[structopt(long = "inject-markers", parse(from_os_str))]
+    inject_markers: Option<PathBuf>,
+    /// Seconds of audio between the markers of --inject-markers
+    #[structopt(
+        long = "marker-interval",
+        default_value = "1",
+        parse(try_from_str = "parse_interval")
+    )]
+    marker_interval: f64,
+    /// Report the latency of markers written by --inject-markers, which
+    /// needs the clocks of both ends in sync
+    #[structopt(long = "detect-markers")]
+    detect_markers: bool,
+    /// Write a copy of the frames degraded like a bad network would, with
+    /// segments reordered, duplicated and delayed
+    #[structopt(long = "impair", parse(from_os_str))]
+    impair: Option<PathBuf>,
+    /// Log every segment written by --impair with what was done to it, as
+    /// JSON
+    #[structopt(long = "impair-log", parse(from_os_str))]
+    impair_log: Option<PathBuf>,
+    /// Impairments of --impair, e.g. "segment=2,reorder=5,duplicate=2,jitter=200,seed=1",
+    /// segment in seconds, reorder and duplicate in percent of segments and
+    /// jitter in milliseconds
+    #[structopt(
+        long = "impairments",
+        default_value = "segment=2",
+        parse(try_from_str = "impair::parse_impairments")
+    )]
+    impairments: Impairments,
+    /// Leave frames with invalid headers or CRCs out of --extract, --loas
+    /// and --mp4, which also checks the CRC of protected frames
+    #[structopt(long = "drop-invalid")]
+    drop_invalid: bool,
+    /// Split the frames into content-addressed chunks in this directory,
+    /// with a manifest, for archives that deduplicate across recordings
+    #[structopt(long = "chunks", parse(from_os_str))]
+    chunks: Option<PathBuf>,
+    /// Only spot check frames at sample positions, covering about this
+    /// percentage of the input's frames
+    #[structopt(long = "sample", parse(try_from_str = "parse_percent"))]
+    sample: Option<f64>,
+    /// Spread --sample positions evenly instead of randomly
+    #[structopt(long = "sample-periodic")]
+    sample_periodic: bool,
+    /// Seed for random --sample positions
+    #[structopt(long = "sample-seed", default_value = "1")]
+    sample_seed: u64,
+    /// Recover streams from a raw disk image into this directory
+    #[structopt(long = "carve", parse(from_os_str))]
+    carve: Option<PathBuf>,
+    /// Detect captures that are byte-swapped or shifted by a few bits
+    #[structopt(long = "realign")]
+    realign: bool,
+    /// Write a copy of the capture with the detected misalignment undone
+    #[structopt(long = "realign-output", parse(from_os_str))]
+    realign_output: Option<PathBuf>,
+    /// Periodically save scan progress to this file
+    #[structopt(long = "checkpoint", parse(from_os_str))]
+    checkpoint: Option<PathBuf>,
+    /// Pipe the ADTS frames into this decoder command, e.g. "ffplay -f aac -"
+    #[structopt(long = "pipe-decoder")]
+    pipe_decoder: Option<String>,
+    /// Only pipe frames in this time range, e.g. "12.5-30"
+    #[structopt(long = "pipe-range", parse(try_from_str = "decoder::parse_range"))]
+    pipe_range: Option<(f64, f64)>,
+    /// Write frame boundaries and timestamps as JSON to this file
+    #[structopt(long = "boundaries", parse(from_os_str))]
+    boundaries: Option<PathBuf>,
+    /// Write times as unix (seconds), iso8601 (durations) or hms (clock
+    /// times), in the text report, the JSON and CSV output and the boundary
+    /// and impairment exports. JSON output keeps times as seconds and adds
+    /// the formatted ones in fields ending in _text. CUE sheets and
+    /// chapters keep the times their formats require
+    #[structopt(
+        long = "timestamp-format",
+        parse(try_from_str = "timefmt::parse_style")
+    )]
+    timestamp_format: Option<timefmt::Style>,
+    /// Number of decimals of times and durations, by default 3 in the text
+    /// report and 6 in machine readable output
+    #[structopt(long = "decimals", parse(try_from_str = "timefmt::parse_decimals"))]
+    decimals: Option<usize>,
+    /// Print the frames of an ID3v2 tag at the start of the input, like the
+    /// title and artist, the tag is skipped either way
+    #[structopt(long = "show-id3")]
+    show_id3: bool,
+    /// Print everything derivable from the first confirmed frame and exit
+    #[structopt(long = "first-frame-report")]
+    first_frame_report: bool,
+    /// Report the framing the input fits best, with the byte ranges of its
+    /// runs of frames, and exit
+    #[structopt(long = "probe")]
+    probe: bool,
+    /// Make --probe report every framing that finds runs of frames in the
+    /// input, for dumps holding several formats
+    #[structopt(long = "exhaustive")]
+    exhaustive: bool,
+    /// Describe the command line interface as JSON and exit
+    #[structopt(long = "describe-json")]
+    describe_json: bool,
+    /// Compare two reports of the same input written with --format json,
+    /// listing changes of the frame count, the payload hashes of
+    /// --frame-ids and the findings, and exit
+    #[structopt(
+        long = "compare-reports",
+        parse(from_os_str),
+        raw(number_of_values = "2", value_names = r#"&["OLD", "NEW"]"#)
+    )]
+    compare_reports: Vec<PathBuf>,
+    /// Continue the scan saved with --checkpoint
+    #[structopt(long = "resume")]
+    resume: bool,
+    /// Write results as text, json (one object per frame and a summary) or csv
+    #[structopt(
+        long = "format",
+        default_value = "text",
+        parse(try_from_str = "output::parse_format")
+    )]
+    format: Format,
+    /// Version of the JSON and CSV schemas to write, the newest if not
+    /// given. Within a version fields are only ever added, the version is
+    /// in the JSON summary
+    #[structopt(
+        long = "output-version",
+        parse(try_from_str = "output::parse_output_version")
+    )]
+    output_version: Option<u32>,
+    /// Label the results with key=value, in the JSON frame and summary
+    /// objects and as label_key CSV columns, may be given multiple times
+    #[structopt(
+        long = "label",
+        parse(try_from_str = "parse_label"),
+        number_of_values = 1
+    )]
+    labels: Vec<(String, String)>,
+    /// Consecutive headers of the same stream needed to trust a sync point
+    #[structopt(
+        long = "sync-confirm",
+        default_value = "2",
+        parse(try_from_str = "parse_sync_confirm")
+    )]
+    sync_confirm: usize,
+    /// Framing of the input: adts, or mp3, ac3, dts, flac, loas or ogg to
+    /// walk MPEG audio, AC-3 and E-AC-3, DTS, FLAC, LOAS/LATM frames or Ogg
+    /// pages instead, or ts to demux the AAC stream of an MPEG transport
+    /// stream
+    #[structopt(
+        long = "input-format",
+        default_value = "adts",
+        parse(try_from_str = "walk::parse_input_format")
+    )]
+    input_format: InputFormat,
+    /// PID of the AAC stream to demux with --input-format ts, decimal or
+    /// hex like 0x100, the first AAC stream of the program map tables if
+    /// not given
+    #[structopt(long = "ts-pid", parse(try_from_str = "parse_pid"))]
+    ts_pid: Option<u16>,
+    /// Check the audio description stream of the program against its main
+    /// audio with --input-format ts, their parameters and their drift, and
+    /// exit
+    #[structopt(long = "ts-check-ad")]
+    ts_check_ad: bool,
+    /// Report how far the PTS of the stream deviate from clean ones
+    /// regenerated from its frame durations, the timeline --mp4 writes,
+    /// with --input-format ts, and exit
+    #[structopt(long = "ts-pts-report")]
+    ts_pts_report: bool,
+    /// Stop at the first corrupt header instead of skipping to the next
+    /// frame. A scan that skipped corrupt data exits with DATAERR even if
+    /// it reached the end of the input
+    #[structopt(long = "no-resync")]
+    no_resync: bool,
+    /// Verify the CRC of protected frames
+    #[structopt(long = "check-crc")]
+    check_crc: bool,
+    /// Worker threads for the payload hashes and CRC checks of --check-crc
+    /// with --detect-loops or --reference, 1 to keep them on the parsing
+    /// thread, as many as there are CPUs if not given. The CRC warnings of
+    /// the workers may come a few frames late
+    #[structopt(long = "threads")]
+    threads: Option<usize>,
+    /// Check that the first channel element of every frame matches the
+    /// channel configuration
+    #[structopt(long = "check-elements")]
+    check_elements: bool,
+    /// Treat frames longer than this as corruption, instead of a limit
+    /// derived from the channel configuration
+    #[structopt(long = "max-frame-length")]
+    max_frame_length: Option<u16>,
+    /// Print average, lowest and highest bitrate and whether it is constant
+    #[structopt(long = "bitrate")]
+    bitrate: bool,
+    /// Give every frame an ID, consistent across outputs and named in the
+    /// warnings about the frame, which requires reading all payloads
+    #[structopt(long = "frame-ids")]
+    frame_ids: bool,
+    /// Estimate the lowest level of the AAC Profile a decoder needs for the
+    /// stream
+    #[structopt(long = "level")]
+    level: bool,
+    /// Fail if the stream needs a higher level of the AAC Profile than this,
+    /// or none of its levels
+    #[structopt(long = "max-level")]
+    max_level: Option<u8>,
+    /// Print aggregate statistics at the end instead of every frame
+    #[structopt(long = "summary")]
+    summary: bool,
 }
 
-const ADTS_HDR_MIN_LEN: usize = 7;
-const ADTS_HDR_MAX_LEN: usize = 9;
+fn parse_percent(value: &str) -> Result<f64, String> {
+    let percent: f64 = value
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("invalid percentage '{}'", value))?;
+    if percent <= 0.0 || percent > 100.0 {
+        return Err(format!("percentage '{}' not in (0, 100]", value));
+    }
+    Ok(percent)
+}
 
-fn find_startcode(buf: [u8; ADTS_HDR_MAX_LEN]) -> Option<usize> {
-    buf.windows(2)
-        .position(|b| (b[0] == 0xFF) && ((b[1] & 0xF0) == 0xF0))
+fn parse_label(value: &str) -> Result<(String, String), String> {
+    match value.find('=') {
+        Some(at) if at > 0 => Ok((value[..at].to_string(), value[at + 1..].to_string())),
+        _ => Err(format!("invalid label '{}', use key=value", value)),
+    }
 }
 
-fn seek_startcode(mut file: &fs::File) -> std::io::Result<u64> {
-    let mut buffer = [0; ADTS_HDR_MAX_LEN as usize];
+fn parse_interval(value: &str) -> Result<f64, String> {
+    match value.parse() {
+        Ok(seconds) if seconds > 0.0 => Ok(seconds),
+        _ => Err(format!(
+            "invalid interval '{}', must be above 0 seconds",
+            value
+        )),
+    }
+}
 
-    loop {
+fn parse_pid(value: &str) -> Result<u16, String> {
+    let pid = match value.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    match pid {
+        Ok(pid) if pid <= 0x1FFF => Ok(pid),
+        _ => Err(format!("invalid PID '{}', expected 0 to 8191", value)),
+    }
+}
+
+fn parse_sync_confirm(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(0) | Err(_) => Err(format!(
+            "invalid header count '{}', must be at least 1",
+            value
+        )),
+        Ok(count) => Ok(count),
+    }
+}
+
+/// Minimum number of frames in a repeating sequence before it is reported
+/// as a loop, about one second of audio
+const LOOP_MIN_FRAMES: usize = 48;
+
+/// Enough for an ADIF header with the largest program config elements
+const ADIF_PEEK_LEN: usize = 8 * 1024;
+
+/// Bytes kept of the data skipped at the end of a stream, to find the tags
+/// after its frames in
+const TRAILER_TAIL_LEN: usize = 1024 * 1024;
+
+/// How far after a sample position a confirmed frame must be found
+const SAMPLE_WINDOW: usize = 16 * 1024;
+
+/// Bytes needed to confirm any frame starting within `SAMPLE_WINDOW`
+fn sample_window(confirm: usize) -> u64 {
+    (SAMPLE_WINDOW + (confirm - 1) * ADTS_MAX_FRAME_LEN + ADTS_HDR_MIN_LEN) as u64
+}
+
+/// Largest raw data a decoder has to accept per channel and raw data block, 6144
+/// bits by the decoder input buffer size of ISO 14496-3 4.5.3.1
+const MAX_CHANNEL_BYTES: usize = 6144 / 8;
+
+/// Largest plausible length of a frame with `header`
+fn max_frame_length(opts: &CliArgs, header: &AdtsHeader) -> usize {
+    if let Some(limit) = opts.max_frame_length {
+        return usize::from(limit);
+    }
+    let channels = match header.channels() {
+        Some(channels) => usize::from(channels),
+        // Defined by a program_config_element, which is not parsed
+        None => return ADTS_MAX_FRAME_LEN,
+    };
+    header.header_length() + header.raw_data_blocks() * channels * MAX_CHANNEL_BYTES
+}
+
+/// Bytes at the start of the input that misalignment detection looks at
+const REALIGN_WINDOW: u64 = 256 * 1024;
+/// Consecutive valid frames needed to accept an alignment
+const REALIGN_MIN_FRAMES: usize = 8;
+
+/// Consecutive valid frames needed to carve a run
+const CARVE_MIN_FRAMES: usize = 16;
+/// Largest gap between two runs of the same carved stream
+const CARVE_MAX_GAP: u64 = 1024 * 1024;
+/// Bytes searched for a sync at once when carving
+const CARVE_WINDOW: u64 = 1024 * 1024;
+
+/// Input bytes scanned between two checkpoints
+const CHECKPOINT_INTERVAL: u64 = 16 * 1024 * 1024;
+
+/// Differences --compare-reports lists, the rest are only counted
+const MAX_LISTED_DIFFERENCES: usize = 20;
+
+/// Seconds a PTS may move against the frame durations before it counts as
+/// a jump, well above the rounding to 90 kHz ticks
+const PTS_JUMP_TOLERANCE: f64 = 0.001;
+
+/// PTS jumps --ts-pts-report lists, the rest are only counted
+const MAX_LISTED_JUMPS: usize = 20;
+
+/// Parse the header at the current position, attributing the time taken
+fn peek_header(mut file: &fs::File, profile: &mut Profile) -> Result<AdtsHeader, AdtsError> {
+    let mut buffer = [0; ADTS_HDR_MIN_LEN];
+
+    let offset = profile.measure(Stage::Io, || {
+        let offset = file.stream_position()?;
         file.read_exact(&mut buffer)?;
-        let startcode_pos = match find_startcode(buffer) {
-            Some(n) => n,
-            None => {
-                //println!("Not found anything at {0}", )
-                file.seek(SeekFrom::Current(ADTS_HDR_MAX_LEN as i64 - 4))?;
-                continue;
-            }
-        };
+        Ok::<_, std::io::Error>(offset)
+    })?;
+    let header = profile.measure(Stage::HeaderDecode, || parse_header(&buffer, offset))?;
 
-        // Seek back to header start
-        let offset = -(ADTS_HDR_MAX_LEN as i64 - startcode_pos as i64);
-        return file.seek(SeekFrom::Current(offset));
-    }
-
-    // Unreachable
-}
-
-#[derive(Debug)]
-enum MPEGVersion {
-    MPEG4 = 0,
-    MPEG2 = 1,
-}
-
-#[derive(Debug)]
-enum MPEGAudioObjectType {
-    NULL = 0,
-    AAC_MAIN = 1,
-    AAC_LC = 2,
-    AAC_SSR = 3,
-    AAC_LTP = 4,
-    SBR = 5,
-    AAC_SCALABLE = 6,
-    TWIN_VQ = 7,
-    CELP = 8,
-    LAYER1 = 32,
-    LAYER2 = 33,
-    LAYER3 = 34,
-}
-
-struct ADTSHeader {
-    syncword: u16,
-    id: MPEGVersion,
-    protection_absent: bool,
-    profile: MPEGAudioObjectType,
-    sampling_frequency_index: u8,
-    //channel_configuration: u8,
-    frame_length: u16,
-    //adts_buffer_fullness: u16,
-    //num_raw_data_blocks: u8,
-    //crc: u16,
-}
-
-fn peek_header(mut file: &fs::File) -> Option<ADTSHeader> {
-    //let mut header : ADTSHeader;
-    let mut buffer = [0; ADTS_HDR_MIN_LEN as usize];
-
-    file.read_exact(&mut buffer).ok()?;
-    let mut reader = BitReader::new(&buffer);
-
-    // Check syncword
-    let syncword = reader.read_u16(12).ok()?;
-    if syncword != 0xFFF {
-        return None;
-    }
-
-    // MPEG Version
-    let mpeg_version = match reader.read_u8(1).ok()? {
-        0 => MPEGVersion::MPEG4,
-        1 => MPEGVersion::MPEG2,
-        _ => return None,
-    };
-
-    // Layer (always 0)
-    reader.skip(2).ok()?;
-
-    // Protection absend
-    let protection_absent = reader.read_bool().ok()?;
-
-    // Profile
-    //reader.skip(2).ok()?;
-    let profile = match reader.read_u8(2).ok()? {
-        0 => MPEGAudioObjectType::AAC_MAIN,
-        1 => MPEGAudioObjectType::AAC_LC,
-        2 => MPEGAudioObjectType::AAC_SSR,
-        3 => MPEGAudioObjectType::AAC_LTP,
-        4 => MPEGAudioObjectType::SBR,
-        5 => MPEGAudioObjectType::AAC_SCALABLE,
-        6 => MPEGAudioObjectType::TWIN_VQ,
-        7 => MPEGAudioObjectType::CELP,
-        31 => MPEGAudioObjectType::LAYER1,
-        32 => MPEGAudioObjectType::LAYER2,
-        33 => MPEGAudioObjectType::LAYER3,
-        _ => return None,
-    };
-
-    // Sampling frequency index
-    //reader.skip(4).ok()?;
-    let sampling_frequency_index = reader.read_u8(4).ok()?;
-
-    // Private bit
-    reader.skip(1).ok()?;
-    // Channel config
-    reader.skip(3).ok()?;
-    // Originality
-    reader.skip(1).ok()?;
-    // Home
-    reader.skip(1).ok()?;
-    // Copyrighted ID
-    reader.skip(1).ok()?;
-    // Copyright ID start signal bit
-    reader.skip(1).ok()?;
-
-    // Frame length
-    let frame_length = reader.read_u16(13).ok()?;
-
-    // Buffer fullness
-    reader.skip(11).ok()?;
-    // Number of frames
-    reader.skip(2).ok()?;
-
-    // CRC (if protection absent is 0)
-    // TODO
-
-    file.seek(SeekFrom::Current(-(ADTS_HDR_MIN_LEN as i64)))
-        .ok()?;
-
-    return Some(ADTSHeader {
-        syncword,
-        id: mpeg_version,
-        profile: profile,
-        sampling_frequency_index: sampling_frequency_index,
-        protection_absent,
-        frame_length,
-    });
+    profile.measure(Stage::Io, || {
+        file.seek(SeekFrom::Current(-(ADTS_HDR_MIN_LEN as i64)))
+    })?;
+
+    Ok(header)
+}
+
+/// Collect the payload hashes of all frames in an ADTS clip
+///
+/// A truncated frame at the end of the clip is ignored.
+fn index_reference(path: &Path) -> Result<Vec<u64>, AdtsError> {
+    let file = BufReader::new(fs::File::open(path)?);
+    let mut hashes = Vec::new();
+
+    for frame in AdtsFrames::new(file).with_payloads() {
+        match frame {
+            Ok(frame) => hashes.push(fnv1a(frame.payload().unwrap_or_default())),
+            Err(AdtsError::Io(ref err)) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        }
+    }
+
+    if hashes.is_empty() {
+        return Err(AdtsError::Io(std::io::Error::new(
+            ErrorKind::InvalidData,
+            "no frames found",
+        )));
+    }
+    Ok(hashes)
+}
+
+impl CliArgs {
+    /// The input file, only missing with --describe-json and
+    /// --compare-reports
+    fn input_path(&self) -> &Path {
+        self.filepath
+            .as_deref()
+            .expect("the input file is required without --describe-json or --compare-reports")
+    }
 }
 
 fn main() {
     // Argument handling
     let opts = CliArgs::from_args();
+    if opts.describe_json {
+        if let Err(err) = describe::write(io::stdout(), &CliArgs::clap()) {
+            eprintln!("error: failed writing description: {}", err);
+            process::exit(exitcode::IOERR);
+        }
+        process::exit(exitcode::OK);
+    }
+    if let [old, new] = opts.compare_reports.as_slice() {
+        process::exit(run_compare(old, new));
+    }
+    let mut profile = Profile::new(opts.profile_self);
+    let mut warnings = Warnings::new(opts.warn_limit);
 
-    println!(
-        "Reading file '{0}' starting at {1}",
-        opts.filepath.display(),
-        opts.offset
-    );
+    let code = run(&opts, &mut profile, &mut warnings);
+
+    warnings.finish();
+    profile.report();
+    process::exit(code);
+}
+
+/// Where the frames of a scan are read from
+enum Input<'a> {
+    /// A file, of which payloads are only read when needed
+    File {
+        parser: AdtsParser<&'a fs::File>,
+        length: u64,
+        /// Tags at the end, before which the frames end
+        trailers: Vec<Trailer>,
+    },
+    /// A pipe or demuxed stream, which is read completely
+    Stream {
+        reader: AdtsReader<Box<dyn Read>>,
+        /// Tags found in the data skipped up to the end
+        trailers: Vec<Trailer>,
+    },
+}
+
+impl Input<'_> {
+    fn peek_header(&mut self, profile: &mut Profile) -> Result<AdtsHeader, AdtsError> {
+        let end = self.end();
+        match self {
+            Input::File { parser, .. } => {
+                let mut file = *parser.get_ref();
+                let position = file.stream_position()?;
+                if end.is_some_and(|end| position >= end) {
+                    return Err(AdtsError::Io(ErrorKind::UnexpectedEof.into()));
+                }
+                peek_header(file, profile)
+            }
+            Input::Stream { reader, .. } => profile.measure(Stage::Io, || reader.peek_header()),
+        }
+    }
+
+    /// Offset of the frame the next call reads
+    fn position(&mut self) -> std::io::Result<u64> {
+        match self {
+            Input::File { parser, .. } => parser.get_mut().stream_position(),
+            Input::Stream { reader, .. } => Ok(reader.position()),
+        }
+    }
 
-    let mut file = match fs::OpenOptions::new().read(true).open(opts.filepath) {
+    fn read_frame(&mut self, header: &AdtsHeader, frame: &mut Vec<u8>) -> std::io::Result<()> {
+        self.check_fits(header)?;
+        match self {
+            Input::File { parser, .. } => parser.read_frame(header, frame),
+            Input::Stream { reader, .. } => reader.read_frame(header, frame),
+        }
+    }
+
+    fn skip_frame(&mut self, header: &AdtsHeader) -> std::io::Result<()> {
+        self.check_fits(header)?;
+        match self {
+            Input::File { parser, .. } => parser
+                .get_mut()
+                .seek(SeekFrom::Current(header.frame_length as i64))
+                .map(|_| ()),
+            Input::Stream { reader, .. } => reader.skip_frame(header),
+        }
+    }
+
+    /// Fail at a file frame that runs past the end of the frames, where a
+    /// stream fails reading it, so a truncated last frame is not counted
+    fn check_fits(&mut self, header: &AdtsHeader) -> std::io::Result<()> {
+        let end = self.end();
+        if let Input::File { parser, .. } = self {
+            let frame_end = parser.get_mut().stream_position()? + u64::from(header.frame_length);
+            if end.is_some_and(|end| frame_end > end) {
+                return Err(ErrorKind::UnexpectedEof.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Length of the input, for a stream only once its end was reached
+    fn length(&self) -> Option<u64> {
+        match self {
+            Input::File { length, .. } => Some(*length),
+            Input::Stream { reader, .. } => reader.end(),
+        }
+    }
+
+    /// Where the frames end, before the trailing tags of a file
+    fn end(&self) -> Option<u64> {
+        match self {
+            Input::File {
+                length, trailers, ..
+            } => Some(trailers.first().map_or(*length, Trailer::offset)),
+            Input::Stream { reader, trailers } => trailers
+                .first()
+                .map(Trailer::offset)
+                .or_else(|| reader.end()),
+        }
+    }
+
+    fn trailers(&self) -> &[Trailer] {
+        match self {
+            Input::File { trailers, .. } => trailers,
+            Input::Stream { trailers, .. } => trailers,
+        }
+    }
+
+    /// Classify the data at `offset`, where no valid frame starts, and skip
+    /// it up to the next sync or the end of the input
+    fn skip_junk(&mut self, offset: u64, confirm: usize) -> std::io::Result<Junk> {
+        let mut sample = Vec::new();
+        let end = match self {
+            Input::File { parser, .. } => {
+                parser.get_mut().seek(SeekFrom::Start(offset))?;
+                parser
+                    .get_mut()
+                    .take(junk::SAMPLE_LEN as u64)
+                    .read_to_end(&mut sample)?;
+                parser.get_mut().seek(SeekFrom::Start(offset))?;
+                parser.seek_sync(confirm)
+            }
+            Input::Stream { reader, trailers } => {
+                sample.extend_from_slice(reader.peek(junk::SAMPLE_LEN)?);
+                let end = reader.seek_sync(confirm);
+                // Tags after the last frame are only seen as data without
+                // a sync up to the end
+                let at_end = matches!(end, Err(ref err) if err.kind() == ErrorKind::UnexpectedEof);
+                if let (true, Some(length)) = (at_end, reader.end()) {
+                    let mut tail = reader.skipped().to_vec();
+                    tail.extend_from_slice(reader.peek(ADTS_HDR_MIN_LEN)?);
+                    let start = length - tail.len() as u64;
+                    tail.drain(..(offset.max(start) - start) as usize);
+                    *trailers = trailers::find_in(&tail, length);
+                }
+                end
+            }
+        };
+        let end = match end {
+            Ok(end) => end,
+            Err(ref err) if err.kind() == ErrorKind::UnexpectedEof => {
+                self.end().expect("input ended without a length")
+            }
+            Err(err) => return Err(err),
+        };
+        // A sync found in the trailing tags is not one of a frame
+        let end = self.end().map_or(end, |tags| end.min(tags));
+        Ok(Junk::new(offset, end - offset, &sample))
+    }
+}
+
+fn run(opts: &CliArgs, profile: &mut Profile, warnings: &mut Warnings) -> exitcode::ExitCode {
+    if opts.format != Format::Text {
+        let csv = opts.format == Format::Csv;
+        let unsupported = [
+            (opts.carve.is_some(), "--carve"),
+            (opts.realign || opts.realign_output.is_some(), "--realign"),
+            (opts.first_frame_report, "--first-frame-report"),
+            (opts.sample.is_some(), "--sample"),
+            (opts.ts_check_ad, "--ts-check-ad"),
+            (opts.ts_pts_report, "--ts-pts-report"),
+            (csv && opts.probe, "--probe"),
+            (csv && opts.detect_loops, "--detect-loops"),
+            (csv && !opts.references.is_empty(), "--reference"),
+            (csv && opts.verdict, "--verdict"),
+            (csv && opts.health, "--health"),
+            (csv && opts.check_crc, "--check-crc"),
+            (csv && opts.check_elements, "--check-elements"),
+            (csv && opts.detect_markers, "--detect-markers"),
+            (csv && opts.bitrate, "--bitrate"),
+            (csv && opts.summary, "--summary"),
+            (csv && (opts.level || opts.max_level.is_some()), "--level"),
+            (
+                !matches!(opts.input_format, InputFormat::Adts | InputFormat::Ts),
+                "--input-format",
+            ),
+        ];
+        if let Some((_, flag)) = unsupported.iter().find(|(used, _)| *used) {
+            let format = if csv { "csv" } else { "json" };
+            eprintln!("error: {} has no {} output", flag, format);
+            return exitcode::USAGE;
+        }
+    } else if opts.output_version.is_some() {
+        eprintln!("error: --output-version needs --format json or csv");
+        return exitcode::USAGE;
+    } else if !opts.labels.is_empty() {
+        eprintln!("error: --label needs --format json or csv");
+        return exitcode::USAGE;
+    } else {
+        println!(
+            "Reading file '{0}' starting at {1}",
+            opts.input_path().display(),
+            opts.offset
+        );
+    }
+
+    for (index, (key, _)) in opts.labels.iter().enumerate() {
+        if opts.labels[..index]
+            .iter()
+            .any(|(earlier, _)| earlier == key)
+        {
+            eprintln!("error: --label {} is given more than once", key);
+            return exitcode::USAGE;
+        }
+    }
+    if opts.threads == Some(0) {
+        eprintln!("error: --threads must be at least 1");
+        return exitcode::USAGE;
+    }
+    if opts.exhaustive && !opts.probe {
+        eprintln!("error: --exhaustive needs --probe");
+        return exitcode::USAGE;
+    }
+    if opts.ts_pid.is_some() && opts.input_format != InputFormat::Ts {
+        eprintln!("error: --ts-pid needs --input-format ts");
+        return exitcode::USAGE;
+    }
+    if opts.ts_check_ad && opts.input_format != InputFormat::Ts {
+        eprintln!("error: --ts-check-ad needs --input-format ts");
+        return exitcode::USAGE;
+    }
+    if opts.ts_pts_report && opts.input_format != InputFormat::Ts {
+        eprintln!("error: --ts-pts-report needs --input-format ts");
+        return exitcode::USAGE;
+    }
+    if opts.probe {
+        return run_probe(opts);
+    }
+    if opts.input_format == InputFormat::Ts {
+        return run_ts(opts, profile, warnings);
+    }
+    if let Some(parse) = opts.input_format.parser() {
+        let trailers = match input_trailers(opts) {
+            Ok(trailers) => trailers,
+            Err(code) => return code,
+        };
+        let mut input = match open_input(opts) {
+            Ok(input) => input,
+            Err(code) => return code,
+        };
+        if let Some(first) = trailers.first() {
+            input = Box::new(input.take(first.offset() - u64::from(opts.offset)));
+        }
+        return run_walk(
+            opts,
+            input,
+            u64::from(opts.offset),
+            parse,
+            opts.input_format.name(),
+            &trailers,
+            warnings,
+        );
+    }
+    if opts.input_path().as_os_str() == "-" {
+        return run_stream(
+            opts,
+            Box::new(io::stdin().lock()),
+            u64::from(opts.offset),
+            "stdin",
+            profile,
+            warnings,
+        );
+    }
+
+    let input = match fs::OpenOptions::new().read(true).open(opts.input_path()) {
         Ok(result) => result,
         Err(err) => {
             eprintln!("error: failed opening file: {0}", err);
-            process::exit(exitcode::NOINPUT);
+            return exitcode::NOINPUT;
         }
     };
+    let mut file = &input;
 
     match file.seek(SeekFrom::Current(opts.offset as i64)) {
         Ok(_) => {}
         Err(err) => {
-            eprintln!("error: failed seeking to offset: {}", opts.offset);
-            process::exit(exitcode::DATAERR);
+            eprintln!("error: failed seeking to offset {}: {}", opts.offset, err);
+            return exitcode::DATAERR;
         }
     }
 
-    // Read header
-    match seek_startcode(&file) {
-        Ok(pos) => {
-            println!("Found startcode at offset {}", pos);
+    let mut start = Vec::new();
+    let peeked = file
+        .take(ADIF_PEEK_LEN as u64)
+        .read_to_end(&mut start)
+        .and_then(|_| file.seek(SeekFrom::Start(u64::from(opts.offset))));
+    if let Err(err) = peeked {
+        eprintln!("error: failed reading input: {}", err);
+        return exitcode::IOERR;
+    }
+    if adif::is_adif(&start) {
+        return run_adif(opts, &start);
+    }
+
+    let mut parser = AdtsParser::new(file);
+    let file_length = match file.metadata() {
+        Ok(metadata) => metadata.len(),
+        Err(err) => {
+            eprintln!("error: failed reading file metadata: {}", err);
+            return exitcode::NOINPUT;
+        }
+    };
+
+    if let Some(dir) = opts.carve.as_ref() {
+        return run_carve(file, file_length, dir, &time_format(opts));
+    }
+    if opts.realign || opts.realign_output.is_some() {
+        return run_realign(opts, file);
+    }
+
+    // A tag holds enough data to find false syncwords in
+    let mut start_offset = u64::from(opts.offset);
+    if let Some(tag) = id3::parse_header(&start) {
+        let mut data = Vec::new();
+        let read = if opts.show_id3 {
+            file.take(tag.total_length() as u64).read_to_end(&mut data)
+        } else {
+            Ok(0)
+        };
+        start_offset += tag.total_length() as u64;
+        if let Err(err) = read.and_then(|_| file.seek(SeekFrom::Start(start_offset))) {
+            eprintln!("error: failed reading ID3v2 tag: {}", err);
+            return exitcode::IOERR;
         }
+        print_id3(opts, &tag, &data, u64::from(opts.offset));
+    }
+    let trailers = trailers::find(file, start_offset, file_length)
+        .and_then(|trailers| file.seek(SeekFrom::Start(start_offset)).map(|_| trailers));
+    let trailers = match trailers {
+        Ok(trailers) => trailers,
         Err(err) => {
-            eprintln!("error: failed seeking to startcode: '{}'", err);
-            process::exit(exitcode::DATAERR);
+            eprintln!("error: failed reading trailing tags: {}", err);
+            return exitcode::IOERR;
+        }
+    };
+
+    let resumed = if opts.resume {
+        match resume(opts, file_length) {
+            Ok(progress) => Some(progress),
+            Err(err) => {
+                eprintln!("error: cannot resume: {}", err);
+                return exitcode::USAGE;
+            }
+        }
+    } else {
+        None
+    };
+
+    // Read header
+    let next_offset = if let Some(progress) = resumed.as_ref() {
+        if let Err(err) = file.seek(SeekFrom::Start(progress.next_offset)) {
+            eprintln!("error: failed seeking to resume offset: {}", err);
+            return exitcode::DATAERR;
+        }
+        if opts.format == Format::Text {
+            println!(
+                "Resuming at offset {} after {} frames",
+                progress.next_offset, progress.frames
+            );
+        }
+        progress.next_offset
+    } else {
+        let found = profile.measure(Stage::SyncSearch, || {
+            parser.seek_sync(opts.sync_confirm).or_else(|err| {
+                if err.kind() == ErrorKind::UnexpectedEof {
+                    seek_mpeg_audio(&mut parser, start_offset)
+                } else {
+                    Err(err)
+                }
+            })
+        });
+        match found {
+            Ok(pos) => {
+                if opts.format == Format::Text {
+                    println!("Found startcode at offset {}", pos);
+                }
+                pos
+            }
+            Err(err) => {
+                eprintln!("error: failed seeking to startcode: '{}'", err);
+                return exitcode::DATAERR;
+            }
+        }
+    };
+    let leading_junk = if resumed.is_none() && next_offset > start_offset {
+        match read_junk(file, start_offset, next_offset) {
+            Ok(junk) => Some(junk),
+            Err(err) => {
+                eprintln!("error: failed reading input: {}", err);
+                return exitcode::IOERR;
+            }
         }
+    } else {
+        None
     };
 
+    if opts.first_frame_report {
+        return run_first_frame_report(file, next_offset, file_length, opts.sync_confirm);
+    }
+    if let Some(percent) = opts.sample {
+        return run_sampled(opts, file, next_offset, file_length, percent, profile);
+    }
+
+    let input = Input::File {
+        parser,
+        length: file_length,
+        trailers,
+    };
+    scan(
+        opts,
+        input,
+        next_offset,
+        resumed,
+        leading_junk,
+        profile,
+        warnings,
+    )
+}
+
+/// Classify the data from `start` up to the sync at `sync`, leaving the file
+/// at the sync
+fn read_junk(mut file: &fs::File, start: u64, sync: u64) -> io::Result<Junk> {
+    let mut sample = Vec::new();
+    file.seek(SeekFrom::Start(start))?;
+    file.take(junk::SAMPLE_LEN as u64)
+        .read_to_end(&mut sample)?;
+    file.seek(SeekFrom::Start(sync))?;
+    Ok(Junk::new(start, sync - start, &sample))
+}
+
+/// Seek to the first header from `start` on that reads as MPEG audio
+///
+/// Such headers never confirm a sync, as their ADTS frame length is
+/// meaningless, but the scan should still report the misdetected stream.
+fn seek_mpeg_audio(parser: &mut AdtsParser<&fs::File>, start: u64) -> io::Result<u64> {
+    parser.get_mut().seek(SeekFrom::Start(start))?;
     loop {
-        let header = match peek_header(&file) {
-            Some(val) => val,
-            None => {
-                eprintln!("error: Failed reading ADTS header");
-                process::exit(exitcode::DATAERR);
+        let pos = parser.seek_startcode()?;
+        if let Ok(header) = parser.peek_header() {
+            if header.mpeg_audio.is_some() {
+                return Ok(pos);
             }
-        };
+        }
+        parser.get_mut().seek(SeekFrom::Start(pos + 1))?;
+    }
+}
 
-        let cur_pos = file
-            .seek(SeekFrom::Current(0))
-            .expect("failed obtaining current file position");
-        println!("Header at: {}", cur_pos);
-        println!("Len is {}", header.frame_length);
-        println!("ID is {:?}", header.id);
-        println!("Profile is {:?}", header.profile);
+/// Report the header of ADIF input, whose raw data blocks have no framing
+/// to walk
+fn run_adif(opts: &CliArgs, start: &[u8]) -> exitcode::ExitCode {
+    let header = match adif::parse_header(start) {
+        Ok(header) => header,
+        Err(err) => {
+            eprintln!("error: failed reading ADIF header: {}", err);
+            return exitcode::DATAERR;
+        }
+    };
+    match opts.format {
+        Format::Text => print_adif(&header, u64::from(opts.offset)),
+        Format::Json => println!("{}", output::adif_json(&header, u64::from(opts.offset))),
+        Format::Csv => {}
+    }
+    eprintln!(
+        "error: input is ADIF, not ADTS, its raw data blocks cannot be walked without decoding"
+    );
+    exitcode::DATAERR
+}
+
+/// Find the runs of frames of every supported framing in the whole input
+///
+/// Each framing is searched on its own, so streams of different formats
+/// one after the other or interleaved are all found.
+fn run_probe(opts: &CliArgs) -> exitcode::ExitCode {
+    let mut input = match open_input(opts) {
+        Ok(input) => input,
+        Err(code) => return code,
+    };
+    let offset = u64::from(opts.offset);
+    let mut data = Vec::new();
+    if let Err(err) = input.read_to_end(&mut data) {
+        eprintln!("error: failed reading input: {}", err);
+        return exitcode::IOERR;
+    }
+
+    let mut probes: Vec<_> = sniff::SniffFormat::ALL
+        .iter()
+        .map(|format| {
+            // A lone frame at the end of the input is too likely a chance
+            // match to report in a large dump
+            let mut runs = sniff::runs(&data, *format);
+            runs.retain(|run| run.frames > 1);
+            (sniff::rate(*format, data.len(), &runs), runs)
+        })
+        .filter(|(acceptance, _)| acceptance.frames > 0)
+        .collect();
+    probes.sort_by(|a, b| b.0.confidence.partial_cmp(&a.0.confidence).unwrap());
+    if !opts.exhaustive {
+        probes.truncate(1);
+    }
+
+    match opts.format {
+        Format::Json => println!("{}", output::probe_json(&probes, offset)),
+        _ => print_probes(&probes, data.len(), offset),
+    }
+    if probes.is_empty() {
+        eprintln!("error: no runs of frames of any supported framing found");
+        return exitcode::DATAERR;
+    }
+    exitcode::OK
+}
+
+/// Compare two JSON reports, exiting with DATAERR if they differ
+fn run_compare(old: &Path, new: &Path) -> exitcode::ExitCode {
+    let mut reports = Vec::new();
+    for path in [old, new].iter() {
+        match Report::load(path) {
+            Ok(report) => reports.push(report),
+            Err(err) => {
+                eprintln!("error: failed reading report '{}': {}", path.display(), err);
+                return if err.kind() == ErrorKind::InvalidData {
+                    exitcode::DATAERR
+                } else {
+                    exitcode::NOINPUT
+                };
+            }
+        }
+    }
+    println!("Comparing '{}' with '{}'", old.display(), new.display());
+    if !reports[0].has_frame_ids() || !reports[1].has_frame_ids() {
+        println!("Payload hashes are not compared, both reports need --frame-ids");
+    }
+
+    let differences = compare::compare(&reports[0], &reports[1]);
+    for difference in differences.iter().take(MAX_LISTED_DIFFERENCES) {
+        let missing = || "missing".to_string();
         println!(
-            "Sampling frequency index is {:?}",
-            header.sampling_frequency_index
+            "{}: {} -> {}",
+            difference.path,
+            difference.old.clone().unwrap_or_else(missing),
+            difference.new.clone().unwrap_or_else(missing)
         );
-        match file.seek(SeekFrom::Current(header.frame_length as i64)) {
-            Ok(_) => {}
+    }
+    if differences.len() > MAX_LISTED_DIFFERENCES {
+        println!(
+            "... and {} more differences",
+            differences.len() - MAX_LISTED_DIFFERENCES
+        );
+    }
+    if differences.is_empty() {
+        println!("No differences");
+        exitcode::OK
+    } else {
+        println!("{} differences", differences.len());
+        exitcode::DATAERR
+    }
+}
+
+/// Open the input file, or stdin for "-", and skip to the offset
+fn open_input(opts: &CliArgs) -> Result<Box<dyn Read>, exitcode::ExitCode> {
+    let mut input: Box<dyn Read> = if opts.input_path().as_os_str() == "-" {
+        Box::new(io::stdin().lock())
+    } else {
+        match fs::File::open(opts.input_path()) {
+            Ok(file) => Box::new(BufReader::new(file)),
             Err(err) => {
-                eprintln!("error: Failed seeking to next header: {}", err);
-                process::exit(exitcode::DATAERR);
+                eprintln!("error: failed opening file: {0}", err);
+                return Err(exitcode::NOINPUT);
+            }
+        }
+    };
+    let offset = u64::from(opts.offset);
+    if let Err(err) = io::copy(&mut input.by_ref().take(offset), &mut io::sink()) {
+        eprintln!("error: failed seeking to offset {}: {}", opts.offset, err);
+        return Err(exitcode::DATAERR);
+    }
+    Ok(input)
+}
+
+/// Find the tags at the end of the input file after `--offset`, there are
+/// none on stdin
+fn input_trailers(opts: &CliArgs) -> Result<Vec<Trailer>, exitcode::ExitCode> {
+    if opts.input_path().as_os_str() == "-" {
+        return Ok(Vec::new());
+    }
+    let trailers = fs::File::open(opts.input_path()).and_then(|file| {
+        let length = file.metadata()?.len();
+        trailers::find(&file, u64::from(opts.offset).min(length), length)
+    });
+    trailers.map_err(|err| {
+        eprintln!("error: failed reading trailing tags: {}", err);
+        exitcode::IOERR
+    })
+}
+
+/// Demux the AAC stream of an MPEG transport stream and scan it like a
+/// piped stream
+///
+/// Offsets are positions in the demuxed elementary stream, not in the
+/// transport stream.
+fn run_ts(opts: &CliArgs, profile: &mut Profile, warnings: &mut Warnings) -> exitcode::ExitCode {
+    if opts.ts_check_ad {
+        return run_ad_check(opts);
+    }
+    let input = match open_input(opts) {
+        Ok(input) => input,
+        Err(code) => return code,
+    };
+    let demuxer = match TsDemuxer::new(input, opts.ts_pid) {
+        Ok(demuxer) => demuxer,
+        Err(err) if err.kind() == ErrorKind::InvalidData => {
+            eprintln!("error: {}", err);
+            return exitcode::DATAERR;
+        }
+        Err(err) => {
+            eprintln!("error: failed reading input: {}", err);
+            return exitcode::IOERR;
+        }
+    };
+    let stream = demuxer.stream();
+    if opts.format == Format::Text {
+        println!(
+            "Demuxing PID {} of program {}, AAC in {}",
+            stream.pid,
+            stream.program_number,
+            stream.framing.name()
+        );
+    }
+    if opts.ts_pts_report {
+        if stream.framing != AacFraming::Adts {
+            eprintln!(
+                "error: --ts-pts-report needs AAC in ADTS, not {}",
+                stream.framing.name()
+            );
+            return exitcode::USAGE;
+        }
+        return run_pts_report(opts, Retiming::read(demuxer));
+    }
+
+    // Kept to report the lost packets once the scan is done
+    let demuxer = Rc::new(RefCell::new(demuxer));
+    let reader = Box::new(SharedReader(Rc::clone(&demuxer)));
+    let code = match stream.framing {
+        AacFraming::Adts => run_stream(opts, reader, 0, "a transport stream", profile, warnings),
+        AacFraming::Latm if opts.format == Format::Text => run_walk(
+            opts,
+            reader,
+            0,
+            Box::new(loas::parser()),
+            "LOAS",
+            &[],
+            warnings,
+        ),
+        framing => {
+            let format = if opts.format == Format::Csv {
+                "csv"
+            } else {
+                "json"
+            };
+            eprintln!("error: AAC in {} has no {} output", framing.name(), format);
+            return exitcode::USAGE;
+        }
+    };
+
+    let time_format = time_format(opts);
+    for gap in demuxer.borrow().discontinuities() {
+        let mut message = format!(
+            "{} packets of PID {} lost before offset {} of the transport stream",
+            gap.lost,
+            gap.pid,
+            u64::from(opts.offset) + gap.offset
+        );
+        if gap.pid == stream.pid {
+            if let Some(time) = gap.time {
+                message += &format!(", in the audio at {}", time_format.format(time));
+            }
+            message += &match gap.outcome {
+                GapOutcome::Recovered => ", no audio data is missing".to_string(),
+                GapOutcome::Lost(Some(bytes)) => {
+                    format!(", {} bytes of audio data are missing", bytes)
+                }
+                GapOutcome::Lost(None) => ", audio data is missing".to_string(),
+                _ => ", the audio data may be damaged".to_string(),
+            };
+        }
+        warnings.warn("ts-continuity", gap.stream_offset, message);
+    }
+
+    let demuxer = demuxer.borrow();
+    let mux = demuxer.mux_stats();
+    match opts.format {
+        Format::Text => print_mux(mux, &stream),
+        Format::Json => {
+            if let Err(err) = write_line(&output::mux_json(mux, &stream)) {
+                return output_failed(err, code);
             }
+        }
+        Format::Csv => {}
+    }
+    code
+}
+
+/// Print how far the time stamps of a demuxed stream are off its frame
+/// durations
+fn run_pts_report(opts: &CliArgs, retiming: Retiming) -> exitcode::ExitCode {
+    let time_format = time_format(opts);
+    print!(
+        "Read {} frames, {}",
+        retiming.frames,
+        time_format.format(retiming.duration)
+    );
+    match retiming.error.as_ref() {
+        Some(err) => println!(", cut short by a broken frame: {}", err),
+        None => println!(),
+    }
+    let (largest, mean) = match (retiming.largest(), retiming.mean()) {
+        (Some(largest), Some(mean)) => (largest, mean),
+        _ => {
+            eprintln!("error: no PES packet with a time stamp starts a frame");
+            return exitcode::DATAERR;
+        }
+    };
+    println!(
+        "Compared {} PTS with the timeline regenerated from the frame durations",
+        retiming.deviations.len()
+    );
+    println!(
+        "PTS deviate by up to {} at offset {}, {} on average",
+        time_format.format_signed(largest.deviation),
+        largest.offset,
+        time_format.format(mean)
+    );
+    let jumps = retiming.jumps(PTS_JUMP_TOLERANCE);
+    for (deviation, jump) in jumps.iter().take(MAX_LISTED_JUMPS) {
+        println!(
+            "PTS jump by {} at offset {}, {} into the stream, to {} off the regenerated timeline",
+            time_format.format_signed(*jump),
+            deviation.offset,
+            time_format.format(deviation.time),
+            time_format.format_signed(deviation.deviation)
+        );
+    }
+    if jumps.len() > MAX_LISTED_JUMPS {
+        println!("{} more jumps", jumps.len() - MAX_LISTED_JUMPS);
+    }
+    exitcode::OK
+}
+
+/// Print the mux rate of a transport stream and the share of each PID
+fn print_mux(mux: &MuxStats, stream: &AacStream) {
+    match mux.mux_rate() {
+        Some(rate) => println!(
+            "Mux rate is {:.1} kbit/s, by the clock of PID {}",
+            rate / 1000.0,
+            stream.pcr_pid
+        ),
+        None => println!(
+            "Mux rate is unknown, PID {} has less than two clock references",
+            stream.pcr_pid
+        ),
+    }
+    for (pid, packets) in mux.packets.iter() {
+        let role = match *pid {
+            pid if pid == stream.pid => " (audio)",
+            NULL_PID => " (padding)",
+            _ => "",
         };
+        print!(
+            "PID {}{}: {} packets, {:.1}%",
+            pid,
+            role,
+            packets,
+            mux.share(*pid) * 100.0
+        );
+        match mux.bitrate(*pid) {
+            Some(bitrate) => println!(", {:.1} kbit/s", bitrate / 1000.0),
+            None => println!(),
+        }
+    }
+    println!(
+        "Padding is {:.1}% of the multiplex, audio {:.1}%",
+        mux.share(NULL_PID) * 100.0,
+        mux.share(stream.pid) * 100.0
+    );
+}
+
+/// Compare the audio description stream of a transport stream program with
+/// the main audio it goes with
+///
+/// Each stream is demuxed in a pass of its own, so the input has to be a
+/// file.
+fn run_ad_check(opts: &CliArgs) -> exitcode::ExitCode {
+    if opts.input_path().as_os_str() == "-" {
+        eprintln!("error: --ts-check-ad needs a file, it cannot read from stdin");
+        return exitcode::USAGE;
+    }
+    let demux = |pid| {
+        let input = open_input(opts)?;
+        TsDemuxer::new(input, pid).map_err(|err| {
+            if err.kind() == ErrorKind::InvalidData {
+                eprintln!("error: {}", err);
+                exitcode::DATAERR
+            } else {
+                eprintln!("error: failed reading input: {}", err);
+                exitcode::IOERR
+            }
+        })
+    };
+    let program = match demux(opts.ts_pid) {
+        Ok(demuxer) => demuxer.program().clone(),
+        Err(code) => return code,
+    };
+
+    let adts = |stream: &&ElementaryStream| stream.aac_framing() == Some(AacFraming::Adts);
+    let description = program
+        .streams
+        .iter()
+        .filter(adts)
+        .find_map(|stream| Some((stream.pid, stream.audio_description()?)));
+    let (description_pid, mix) = match description {
+        Some(description) => description,
+        None => {
+            println!(
+                "Program {} has no audio description stream in ADTS",
+                program.program_number
+            );
+            return exitcode::OK;
+        }
+    };
+    let main_pid = match program
+        .streams
+        .iter()
+        .filter(adts)
+        .find(|stream| stream.audio_description().is_none())
+    {
+        Some(stream) => stream.pid,
+        None => {
+            eprintln!(
+                "error: program {} has no main audio in ADTS for the audio description on PID {}",
+                program.program_number, description_pid
+            );
+            return exitcode::DATAERR;
+        }
+    };
+    println!(
+        "Program {}: main audio on PID {}, audio description on PID {} ({})",
+        program.program_number,
+        main_pid,
+        description_pid,
+        mix.name()
+    );
+
+    let main = match demux(Some(main_pid)) {
+        Ok(demuxer) => PairedStream::read(demuxer),
+        Err(code) => return code,
+    };
+    let description = match demux(Some(description_pid)) {
+        Ok(demuxer) => PairedStream::read(demuxer),
+        Err(code) => return code,
+    };
+    let time_format = time_format(opts);
+    for stream in [&main, &description].iter() {
+        print_paired_stream(stream, &time_format);
+    }
+
+    let (main_parameters, description_parameters) = match (main.parameters, description.parameters)
+    {
+        (Some(main), Some(description)) => (main, description),
+        _ => {
+            eprintln!("error: no frames to compare in one of the streams");
+            return exitcode::DATAERR;
+        }
+    };
+    let mut code = exitcode::OK;
+    for mismatch in pairing::mismatches(&main_parameters, &description_parameters, mix) {
+        eprintln!("error: {}", mismatch);
+        code = exitcode::DATAERR;
+    }
+    let lengthened = description.duration - main.duration;
+    match pairing::start_drift(&main, &description) {
+        Some(start) => println!(
+            "Audio description starts {} and ends {} from the main audio",
+            time_format.format_signed(start),
+            time_format.format_signed(start + lengthened)
+        ),
+        None => println!(
+            "Audio description is {} longer than the main audio, their drift is unknown without time stamps",
+            time_format.format_signed(lengthened)
+        ),
+    }
+    code
+}
+
+fn print_paired_stream(stream: &PairedStream, time_format: &TimeFormat) {
+    print!(
+        "PID {}: {} frames, {}",
+        stream.pid,
+        stream.frames,
+        time_format.format(stream.duration)
+    );
+    if let Some(parameters) = stream.parameters {
+        print!(
+            ", {:?}, {} Hz, channel configuration {}",
+            parameters.profile, parameters.sample_rate, parameters.channel_configuration
+        );
+    }
+    match stream.error.as_ref() {
+        Some(err) => println!(", cut short by a broken frame: {}", err),
+        None => println!(),
+    }
+}
+
+/// Reads from a reader that is also kept elsewhere
+struct SharedReader<R>(Rc<RefCell<R>>);
+
+impl<R: Read> Read for SharedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+/// Walk the frames of an input that is not ADTS, starting at `offset`
+///
+/// Only the frames and the junk between them are reported, all checks of
+/// the stream are for ADTS.
+fn run_walk(
+    opts: &CliArgs,
+    input: Box<dyn Read>,
+    offset: u64,
+    parse: ParseFrame,
+    name: &str,
+    trailers: &[Trailer],
+    warnings: &mut Warnings,
+) -> exitcode::ExitCode {
+    let time_format = time_format(opts);
+    let mut found = false;
+    let mut totals: Option<TotalsCheck> = None;
+    let result = walk::walk(input, offset, parse, |offset, time, frame| {
+        if !found {
+            println!("Found startcode at offset {}", offset);
+            found = true;
+        }
+        match totals.as_mut() {
+            Some(check) => check.push(frame),
+            None => {
+                totals = frame
+                    .advertised
+                    .map(|advertised| TotalsCheck::new(offset, frame, advertised));
+            }
+        }
+        for (kind, message) in frame.warnings.iter() {
+            warnings.warn(kind, offset, message.clone());
+        }
+        if opts.summary {
+            return;
+        }
+        println!("Header at: {}", offset);
+        println!("Timestamp is {}", time_format.format(time));
+        for line in frame.lines.iter() {
+            println!("{}", line);
+        }
+    });
+    let walk = match result {
+        Ok(walk) => walk,
+        Err(err) => {
+            eprintln!("error: failed reading input: {}", err);
+            return exitcode::IOERR;
+        }
+    };
+
+    if let Some(offset) = walk.truncated {
+        warnings.warn(
+            "truncated",
+            offset,
+            "input ends inside this frame".to_string(),
+        );
+    }
+    println!(
+        "Read {} frames, total duration {}",
+        walk.frames,
+        time_format.format(walk.duration)
+    );
+    if let Some(check) = totals {
+        println!("{}", check.describe());
+        let mismatches = check.mismatches();
+        if !mismatches.is_empty() {
+            warnings.warn(
+                "advertised-totals",
+                check.offset(),
+                format!(
+                    "the {} header does not fit the stream, {}",
+                    check.source(),
+                    mismatches.join(", ")
+                ),
+            );
+        }
+    }
+    print_junk(&walk.junk);
+    print_trailers(trailers);
+    if walk.frames == 0 {
+        eprintln!("error: no {} frames found", name);
+        return exitcode::DATAERR;
+    }
+    exitcode::OK
+}
+
+/// Feed a digest of the worker pool to the loop detector and reference
+/// matcher, and count its CRC check
+fn apply_digest(
+    frame_ref: FrameRef,
+    frame_id: Option<&str>,
+    digest: Digest,
+    loops: &mut Option<LoopDetector>,
+    matcher: &mut Option<ReferenceMatcher>,
+    progress: &mut Progress,
+    warnings: &mut Warnings,
+) {
+    if let Some(detector) = loops.as_mut() {
+        detector.push(digest.hash, frame_ref);
+    }
+    if let Some(matcher) = matcher.as_mut() {
+        matcher.push(digest.hash, frame_ref);
+    }
+    if let Some(check) = digest.crc {
+        count_crc(check, frame_ref.offset, frame_id, progress, warnings);
+    }
+}
+
+/// Count the result of the CRC check of the frame at `offset`, returning
+/// false for a mismatch
+fn count_crc(
+    check: CrcCheck,
+    offset: u64,
+    frame_id: Option<&str>,
+    progress: &mut Progress,
+    warnings: &mut Warnings,
+) -> bool {
+    match check {
+        CrcCheck::Valid => progress.crc.valid += 1,
+        CrcCheck::Mismatch { stored, computed } => {
+            progress.crc.mismatches += 1;
+            warnings.warn_frame(
+                "crc",
+                offset,
+                frame_id,
+                format!(
+                    "CRC mismatch, stored 0x{:04X}, computed 0x{:04X}",
+                    stored, computed
+                ),
+            );
+            return false;
+        }
+        // Unverifiable
+        _ => progress.crc.unverifiable += 1,
+    }
+    true
+}
+
+/// Scan a stream that can only be read once from start to end, like a pipe
+/// on stdin, skipping to `start` first
+fn run_stream(
+    opts: &CliArgs,
+    input: Box<dyn Read>,
+    start: u64,
+    source: &str,
+    profile: &mut Profile,
+    warnings: &mut Warnings,
+) -> exitcode::ExitCode {
+    let unsupported = [
+        (opts.carve.is_some(), "--carve"),
+        (opts.realign || opts.realign_output.is_some(), "--realign"),
+        (opts.first_frame_report, "--first-frame-report"),
+        (opts.sample.is_some(), "--sample"),
+        (opts.redact.is_some(), "--redact"),
+        (opts.checkpoint.is_some(), "--checkpoint"),
+    ];
+    if let Some((_, flag)) = unsupported.iter().find(|(used, _)| *used) {
+        eprintln!(
+            "error: {} needs a file, it cannot read from {}",
+            flag, source
+        );
+        return exitcode::USAGE;
+    }
+
+    let mut reader = AdtsReader::new(input);
+    reader.keep_skipped(TRAILER_TAIL_LEN);
+    if let Err(err) = reader.skip(start) {
+        eprintln!("error: failed seeking to offset {}: {}", start, err);
+        return exitcode::DATAERR;
+    }
+    let mut sample = match reader.peek(junk::SAMPLE_LEN) {
+        Ok(sample) => sample.to_vec(),
+        Err(err) => {
+            eprintln!("error: failed reading input: {}", err);
+            return exitcode::IOERR;
+        }
+    };
+    if adif::is_adif(&sample) {
+        return match reader.peek(ADIF_PEEK_LEN) {
+            Ok(start) => run_adif(opts, start),
+            Err(err) => {
+                eprintln!("error: failed reading input: {}", err);
+                exitcode::IOERR
+            }
+        };
+    }
+    let mut start = start;
+    if let Some(tag) = id3::parse_header(&sample) {
+        let data = if opts.show_id3 {
+            reader.peek(tag.total_length()).map(|data| data.to_vec())
+        } else {
+            Ok(Vec::new())
+        };
+        let skipped = data.and_then(|data| {
+            print_id3(opts, &tag, &data, start);
+            reader.skip(tag.total_length() as u64)
+        });
+        match skipped {
+            Ok(()) => {}
+            Err(ref err) if err.kind() == ErrorKind::UnexpectedEof => {}
+            Err(err) => {
+                eprintln!("error: failed reading ID3v2 tag: {}", err);
+                return exitcode::IOERR;
+            }
+        }
+        start = reader.position();
+        sample = match reader.peek(junk::SAMPLE_LEN) {
+            Ok(sample) => sample.to_vec(),
+            Err(err) => {
+                eprintln!("error: failed reading input: {}", err);
+                return exitcode::IOERR;
+            }
+        };
+    }
+    let next_offset =
+        match profile.measure(Stage::SyncSearch, || reader.seek_sync(opts.sync_confirm)) {
+            Ok(pos) => {
+                if opts.format == Format::Text {
+                    println!("Found startcode at offset {}", pos);
+                }
+                pos
+            }
+            Err(err) => {
+                eprintln!("error: failed seeking to startcode: '{}'", err);
+                return exitcode::DATAERR;
+            }
+        };
+    let leading_junk = if next_offset > start {
+        Some(Junk::new(start, next_offset - start, &sample))
+    } else {
+        None
+    };
+    scan(
+        opts,
+        Input::Stream {
+            reader,
+            trailers: Vec::new(),
+        },
+        next_offset,
+        None,
+        leading_junk,
+        profile,
+        warnings,
+    )
+}
+
+/// Walk the input frame by frame from the header at `next_offset`, or from
+/// the `resumed` progress of an earlier scan
+fn scan(
+    opts: &CliArgs,
+    mut input: Input,
+    next_offset: u64,
+    resumed: Option<Progress>,
+    leading_junk: Option<Junk>,
+    profile: &mut Profile,
+    warnings: &mut Warnings,
+) -> exitcode::ExitCode {
+    let mut loops = if opts.detect_loops {
+        Some(LoopDetector::new(LOOP_MIN_FRAMES))
+    } else {
+        None
+    };
+    let mut matcher = if opts.references.is_empty() {
+        None
+    } else {
+        let mut references = Vec::new();
+        for path in opts.references.iter() {
+            match index_reference(path) {
+                Ok(hashes) => references.push(Reference {
+                    name: path.display().to_string(),
+                    hashes,
+                }),
+                Err(err) => {
+                    eprintln!(
+                        "error: failed indexing reference '{}': {}",
+                        path.display(),
+                        err
+                    );
+                    return exitcode::NOINPUT;
+                }
+            }
+        }
+        Some(ReferenceMatcher::new(references))
+    };
+    let mut decoder = match opts.pipe_decoder.as_ref() {
+        Some(command) => match Decoder::spawn(command, opts.pipe_range) {
+            Ok(decoder) => Some(decoder),
+            Err(err) => {
+                eprintln!("error: failed starting decoder '{}': {}", command, err);
+                return exitcode::UNAVAILABLE;
+            }
+        },
+        None => None,
+    };
+    let mut redactor = match opts.redact.as_ref() {
+        Some(path) => match fs::File::create(path) {
+            Ok(out) => Some(Redactor::new(BufWriter::new(out))),
+            Err(err) => {
+                eprintln!("error: failed creating redacted copy: {}", err);
+                return exitcode::CANTCREAT;
+            }
+        },
+        None => None,
+    };
+    let mut injector = match opts.inject_markers.as_ref() {
+        Some(path) => match fs::File::create(path) {
+            Ok(out) => Some(MarkerInjector::new(
+                BufWriter::new(out),
+                opts.marker_interval,
+            )),
+            Err(err) => {
+                eprintln!("error: failed creating marked copy: {}", err);
+                return exitcode::CANTCREAT;
+            }
+        },
+        None => None,
+    };
+    let mut markers: Vec<Marker> = Vec::new();
+    let mut impairer = match opts.impair.as_ref() {
+        Some(path) => {
+            let log = match opts.impair_log.as_ref().map(fs::File::create).transpose() {
+                Ok(log) => log.map(BufWriter::new),
+                Err(err) => {
+                    eprintln!("error: failed creating impairment log: {}", err);
+                    return exitcode::CANTCREAT;
+                }
+            };
+            let impairer = fs::File::create(path).and_then(|out| {
+                Impairer::new(
+                    BufWriter::new(out),
+                    log,
+                    opts.impairments,
+                    &input_name(opts),
+                    time_format(opts),
+                )
+            });
+            match impairer {
+                Ok(impairer) => Some(impairer),
+                Err(err) => {
+                    eprintln!("error: failed creating impaired copy: {}", err);
+                    return exitcode::CANTCREAT;
+                }
+            }
+        }
+        None => None,
+    };
+    let mut extractor = match opts.extract.as_ref() {
+        Some(path) => match fs::File::create(path) {
+            Ok(out) => Some(Extractor::new(BufWriter::new(out), opts.extract_raw)),
+            Err(err) => {
+                eprintln!("error: failed creating extracted stream: {}", err);
+                return exitcode::CANTCREAT;
+            }
+        },
+        None => None,
+    };
+    let mut loas = match opts.loas.as_ref() {
+        Some(path) => match fs::File::create(path) {
+            Ok(out) => Some(LoasWriter::new(BufWriter::new(out))),
+            Err(err) => {
+                eprintln!("error: failed creating LOAS stream: {}", err);
+                return exitcode::CANTCREAT;
+            }
+        },
+        None => None,
+    };
+    let mut mp4 = match opts.mp4.as_ref() {
+        Some(path) => {
+            match fs::File::create(path).and_then(|out| Mp4Writer::new(BufWriter::new(out))) {
+                Ok(writer) => Some(writer),
+                Err(err) => {
+                    eprintln!("error: failed creating MP4 file: {}", err);
+                    return exitcode::CANTCREAT;
+                }
+            }
+        }
+        None => None,
+    };
+    let mut chunks = match opts.chunks.as_ref() {
+        Some(dir) => match ChunkWriter::new(dir) {
+            Ok(writer) => Some(writer),
+            Err(err) => {
+                eprintln!(
+                    "error: failed creating chunks in {}: {}",
+                    dir.display(),
+                    err
+                );
+                return exitcode::CANTCREAT;
+            }
+        },
+        None => None,
+    };
+    let mut boundaries = match opts.boundaries.as_ref() {
+        Some(path) => match fs::File::create(path).and_then(|out| {
+            BoundaryWriter::new(BufWriter::new(out), &input_name(opts), time_format(opts))
+        }) {
+            Ok(writer) => Some(writer),
+            Err(err) => {
+                eprintln!("error: failed creating boundary export: {}", err);
+                return exitcode::CANTCREAT;
+            }
+        },
+        None => None,
+    };
+    // Only worth it with both hashes and CRC checks, and only if no result
+    // is needed before the next frame is read
+    let threads = opts
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |threads| threads.get()));
+    let mut digests = if threads > 1
+        && opts.check_crc
+        && (loops.is_some() || matcher.is_some())
+        && !opts.drop_invalid
+        && !opts.frame_ids
+        && chunks.is_none()
+    {
+        Some(DigestPool::new(threads))
+    } else {
+        None
+    };
+    let mut frame = Vec::new();
+    let mut progress = resumed.unwrap_or_else(|| Progress::new(next_offset));
+    let mut last_checkpoint = progress.next_offset;
+    let mut previous: Option<AdtsHeader> = None;
+    // The health score weighs the bitrate consistency
+    let mut bitrates = if opts.bitrate || opts.health {
+        Some(BitrateStats::new())
+    } else {
+        None
+    };
+    let mut stats = if opts.summary {
+        Some(FrameStats::new())
+    } else {
+        None
+    };
+    let mut levels = if opts.level || opts.max_level.is_some() {
+        Some(LevelCheck::new())
+    } else {
+        None
+    };
+    // Position of the next frame in samples, only needed for the boundary
+    // export, which cannot be resumed
+    let mut sample: u64 = 0;
+    // Frames left out of --extract, --loas and --mp4 by --drop-invalid
+    let mut dropped: u64 = 0;
+    if opts.format == Format::Csv && !opts.resume {
+        let version = opts.output_version.unwrap_or(output::OUTPUT_VERSION);
+        let header = output::csv_header(version, opts.frame_ids, &opts.labels);
+        if let Err(err) = write_line(&header) {
+            return output_failed(err, exitcode::OK);
+        }
+    }
+
+    let mut junk: Vec<Junk> = leading_junk.into_iter().collect();
+    let ending = loop {
+        let parsed = match input.peek_header(profile) {
+            Ok(header) => {
+                let limit = max_frame_length(opts, &header);
+                if usize::from(header.frame_length) > limit {
+                    Err(format!(
+                        "frame_length={} exceeds the plausible maximum of {} bytes",
+                        header.frame_length, limit
+                    ))
+                } else {
+                    Ok(header)
+                }
+            }
+            Err(AdtsError::Io(ref err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                let ending = match input.end() {
+                    Some(length) if progress.next_offset == length => Ending::Clean,
+                    _ => Ending::Truncated {
+                        offset: progress.next_offset,
+                    },
+                };
+                break ending;
+            }
+            Err(AdtsError::Io(err)) => {
+                eprintln!("error: Failed reading ADTS header: {}", err);
+                break Ending::ReadError;
+            }
+            Err(err) => Err(err.to_string()),
+        };
+        let header = match parsed {
+            Ok(header) => header,
+            Err(reason) => {
+                let offset = progress.next_offset;
+                let corrupt = Ending::Corrupt {
+                    offset,
+                    time: progress.time,
+                };
+                if opts.no_resync {
+                    // Whether only tags follow the frames of a stream is
+                    // only known after reading up to its end
+                    if let Input::Stream { .. } = input {
+                        match input.skip_junk(offset, opts.sync_confirm) {
+                            Ok(found) if found.length == 0 && input.end() == Some(offset) => {
+                                break Ending::Clean;
+                            }
+                            Ok(found) => junk.push(found),
+                            Err(err) => eprintln!("error: failed reading junk data: {}", err),
+                        }
+                    }
+                    eprintln!(
+                        "error: Failed reading ADTS header at offset {}: {}",
+                        offset, reason
+                    );
+                    break corrupt;
+                }
+                let found = match input.skip_junk(offset, opts.sync_confirm) {
+                    Ok(found) => found,
+                    Err(err) => {
+                        eprintln!(
+                            "error: Failed reading ADTS header at offset {}: {}",
+                            offset, reason
+                        );
+                        eprintln!("error: failed reading junk data: {}", err);
+                        break Ending::ReadError;
+                    }
+                };
+                let end = found.offset + found.length;
+                let at_end = input.end() == Some(end);
+                // Only tags follow the frames of a stream
+                if at_end && found.length == 0 {
+                    break Ending::Clean;
+                }
+                junk.push(found);
+                if at_end {
+                    eprintln!(
+                        "error: Failed reading ADTS header at offset {}: {}, no frame follows",
+                        offset, reason
+                    );
+                    break corrupt;
+                }
+                warnings.warn(
+                    "resync",
+                    offset,
+                    format!(
+                        "{}, skipped {} bytes to the next frame",
+                        reason,
+                        end - offset
+                    ),
+                );
+                progress.resyncs.push(Resync {
+                    offset,
+                    time: progress.time,
+                    skipped: end - offset,
+                });
+                progress.next_offset = end;
+                continue;
+            }
+        };
+
+        let cur_pos = profile
+            .measure(Stage::Io, || input.position())
+            .expect("failed obtaining current file position");
+        let drop_invalid =
+            opts.drop_invalid && (extractor.is_some() || loas.is_some() || mp4.is_some());
+        let check_crc = (opts.check_crc || drop_invalid) && !header.protection_absent;
+        let need_frame = loops.is_some()
+            || matcher.is_some()
+            || redactor.is_some()
+            || extractor.is_some()
+            || injector.is_some()
+            || impairer.is_some()
+            || loas.is_some()
+            || mp4.is_some()
+            || chunks.is_some()
+            || decoder.is_some()
+            || check_crc
+            || opts.check_elements
+            || opts.detect_markers
+            || opts.frame_ids;
+        // Frame IDs are needed for the output, otherwise the frame is read
+        // after the header is printed, so truncated frames still show up
+        if opts.frame_ids {
+            if let Err(err) = profile.measure(Stage::Io, || input.read_frame(&header, &mut frame)) {
+                break frame_ending(&err, cur_pos);
+            }
+        }
+        let mut hash = None;
+        if opts.frame_ids {
+            hash =
+                Some(profile.measure(Stage::Hashing, || fnv1a(&frame[header.header_length()..])));
+        }
+        let frame_id =
+            hash.map(|hash| output::frame_id(&input_name(opts), progress.frames, cur_pos, hash));
+
+        let written = profile.measure(Stage::Output, || match opts.format {
+            _ if opts.summary => Ok(()),
+            Format::Text => {
+                println!("Header at: {}", cur_pos);
+                if let Some(id) = frame_id.as_ref() {
+                    println!("Frame ID is {}", id);
+                }
+                println!("Timestamp is {}", time_format(opts).format(progress.time));
+                println!("Len is {}", header.frame_length);
+                println!(
+                    "Bitrate is {:.1} kbit/s",
+                    bitrate::frame_bitrate(header.frame_length, header.duration()) / 1000.0
+                );
+                println!("ID is {:?}", header.id);
+                println!("Profile is {:?}", header.profile);
+                println!(
+                    "Sampling frequency is {} Hz (index {})",
+                    header.sampling_frequency(),
+                    header.sampling_frequency_index
+                );
+                println!(
+                    "Channel configuration is {}, {}",
+                    header.channel_configuration,
+                    header.channel_layout()
+                );
+                if header.is_vbr() {
+                    println!("Buffer fullness is 0x7FF (VBR)");
+                } else {
+                    println!("Buffer fullness is {}", header.adts_buffer_fullness);
+                }
+                println!("Raw data blocks: {}", header.raw_data_blocks());
+                Ok(())
+            }
+            Format::Json => write_line(&output::frame_json(
+                cur_pos,
+                progress.time,
+                &header,
+                frame_id.as_deref(),
+                &opts.labels,
+                &time_format(opts),
+                opts.output_version.unwrap_or(output::OUTPUT_VERSION),
+            )),
+            Format::Csv => write_line(&output::frame_csv(
+                cur_pos,
+                progress.time,
+                &header,
+                frame_id.as_deref(),
+                &opts.labels,
+                &time_format(opts),
+            )),
+        });
+        if let Err(err) = written {
+            return output_failed(err, exitcode::OK);
+        }
+        let mut valid = true;
+        if let Some(guess) = header.mpeg_audio {
+            valid = false;
+            progress.mpeg_audio_frames += 1;
+            warnings.warn_frame(
+                "mpeg-audio",
+                cur_pos,
+                frame_id.as_deref(),
+                format!(
+                    "layer is {} and the header looks like {}, \
+                     this is probably not an ADTS stream",
+                    header.layer, guess
+                ),
+            );
+        } else if header.layer != 0 {
+            valid = false;
+            progress.header_warnings += 1;
+            warnings.warn_frame(
+                "layer",
+                cur_pos,
+                frame_id.as_deref(),
+                format!("layer is {}, must be 0 for ADTS", header.layer),
+            );
+        }
+        if header.num_raw_data_blocks != 0 {
+            warnings.warn_frame(
+                "raw-data-blocks",
+                cur_pos,
+                frame_id.as_deref(),
+                format!(
+                    "frame has {} raw data blocks, not all decoders handle more than one",
+                    header.raw_data_blocks()
+                ),
+            );
+        }
+        if let Some(previous) = previous.as_ref() {
+            if let Some(description) = describe_parameter_change(previous, &header) {
+                progress.parameter_changes.push(ParameterChange {
+                    offset: cur_pos,
+                    time: progress.time,
+                    description,
+                });
+            }
+        }
+
+        if let Some(boundaries) = boundaries.as_mut() {
+            let result = boundaries.frame(
+                cur_pos,
+                header.frame_length,
+                progress.time,
+                sample,
+                header.samples(),
+                frame_id.as_deref(),
+            );
+            if let Err(err) = result {
+                eprintln!("error: failed writing boundary export: {}", err);
+                return exitcode::IOERR;
+            }
+        }
+
+        if need_frame {
+            if !opts.frame_ids {
+                let read = profile.measure(Stage::Io, || input.read_frame(&header, &mut frame));
+                if let Err(err) = read {
+                    break frame_ending(&err, cur_pos);
+                }
+            }
+            let frame_ref = FrameRef {
+                index: progress.frames,
+                offset: cur_pos,
+                time: progress.time,
+                duration: header.duration(),
+            };
+            if let Some(digests) = digests.as_mut() {
+                profile.measure(Stage::Hashing, || {
+                    digests.submit(
+                        &frame,
+                        header.header_length(),
+                        check_crc,
+                        (frame_ref, frame_id.clone()),
+                    )
+                });
+                for ((frame_ref, frame_id), digest) in digests.ready() {
+                    apply_digest(
+                        frame_ref,
+                        frame_id.as_deref(),
+                        digest,
+                        &mut loops,
+                        &mut matcher,
+                        &mut progress,
+                        warnings,
+                    );
+                }
+            } else {
+                if loops.is_some() || matcher.is_some() {
+                    let hash = hash.unwrap_or_else(|| {
+                        profile.measure(Stage::Hashing, || fnv1a(&frame[header.header_length()..]))
+                    });
+                    if let Some(detector) = loops.as_mut() {
+                        detector.push(hash, frame_ref);
+                    }
+                    if let Some(matcher) = matcher.as_mut() {
+                        matcher.push(hash, frame_ref);
+                    }
+                }
+                if check_crc {
+                    let check = crc::check_adts_frame(&frame);
+                    valid &=
+                        count_crc(check, cur_pos, frame_id.as_deref(), &mut progress, warnings);
+                }
+            }
+            // With several protected raw data blocks, the first one starts
+            // after their positions, which header_length does not count
+            if opts.check_elements && (header.protection_absent || header.num_raw_data_blocks == 0)
+            {
+                let start = header.header_length();
+                match elements::leading_elements(&frame[start..], cur_pos + start as u64) {
+                    Ok(found) => {
+                        let first = found.iter().copied().find(|element| element.is_channel());
+                        let expected =
+                            elements::expected_first_element(header.channel_configuration);
+                        if let (Some(first), Some(expected)) = (first, expected) {
+                            if first != expected {
+                                valid = false;
+                                progress.element_mismatches += 1;
+                                let names: Vec<&str> =
+                                    found.iter().map(|element| element.name()).collect();
+                                warnings.warn_frame(
+                                    "elements",
+                                    cur_pos,
+                                    frame_id.as_deref(),
+                                    format!(
+                                        "channel configuration {} signals a {} first, the raw \
+                                         data block starts with {}",
+                                        header.channel_configuration,
+                                        expected.name(),
+                                        names.join(", ")
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        valid = false;
+                        progress.element_mismatches += 1;
+                        warnings.warn_frame(
+                            "elements",
+                            cur_pos,
+                            frame_id.as_deref(),
+                            format!("raw data block ends inside its first elements: {}", err),
+                        );
+                    }
+                }
+            }
+            if opts.detect_markers && header.num_raw_data_blocks == 0 {
+                let found = marker::detect(&frame, header.header_length(), cur_pos, progress.time);
+                if let Some(found) = found {
+                    if opts.format == Format::Text && !opts.summary {
+                        println!(
+                            "Latency marker arrived after {}",
+                            time_format(opts).format(found.latency)
+                        );
+                    }
+                    markers.push(found);
+                }
+            }
+            if let Some(injector) = injector.as_mut() {
+                if let Err(err) = injector.frame(progress.time, &header, &frame) {
+                    eprintln!("error: failed writing marked copy: {}", err);
+                    return exitcode::IOERR;
+                }
+            }
+            if let Some(impairer) = impairer.as_mut() {
+                if let Err(err) = impairer.frame(cur_pos, progress.time, header.duration(), &frame)
+                {
+                    eprintln!("error: failed writing impaired copy: {}", err);
+                    return exitcode::IOERR;
+                }
+            }
+            if let Some(redactor) = redactor.as_mut() {
+                if let Err(err) = redactor.frame(cur_pos, &frame, header.header_length()) {
+                    eprintln!("error: failed writing redacted copy: {}", err);
+                    return exitcode::IOERR;
+                }
+            }
+            if drop_invalid && !valid {
+                dropped += 1;
+            } else {
+                if let Some(extractor) = extractor.as_mut() {
+                    if let Err(err) = extractor.frame(&frame, header.header_length()) {
+                        eprintln!("error: failed writing extracted stream: {}", err);
+                        return exitcode::IOERR;
+                    }
+                }
+                if let Some(loas) = loas.as_mut() {
+                    if let Err(err) = loas.frame(&header, &frame) {
+                        eprintln!("error: failed writing LOAS stream: {}", err);
+                        return exitcode::IOERR;
+                    }
+                }
+                if let Some(mp4) = mp4.as_mut() {
+                    if let Err(err) = mp4.frame(&header, &frame) {
+                        eprintln!("error: failed writing MP4 file: {}", err);
+                        return exitcode::IOERR;
+                    }
+                }
+            }
+            if let Some(chunks) = chunks.as_mut() {
+                let hash = hash.unwrap_or_else(|| {
+                    profile.measure(Stage::Hashing, || fnv1a(&frame[header.header_length()..]))
+                });
+                if let Err(err) = chunks.frame(cur_pos, &frame, hash) {
+                    eprintln!("error: failed writing chunk: {}", err);
+                    return exitcode::IOERR;
+                }
+            }
+            if let Some(decoder) = decoder.as_mut() {
+                if let Err(err) = decoder.frame(progress.time, &frame) {
+                    eprintln!("error: failed piping frame to decoder: {}", err);
+                    return exitcode::IOERR;
+                }
+            }
+        } else {
+            if let Err(err) = profile.measure(Stage::Io, || input.skip_frame(&header)) {
+                break frame_ending(&err, cur_pos);
+            }
+        }
+        progress.time += header.duration();
+        progress.frames += 1;
+        if let Some(bitrates) = bitrates.as_mut() {
+            bitrates.push(header.frame_length, header.duration());
+        }
+        if let Some(stats) = stats.as_mut() {
+            stats.push(&header);
+        }
+        if let Some(levels) = levels.as_mut() {
+            levels.push(cur_pos, &header);
+        }
+        sample += u64::from(header.samples());
+        progress.next_offset = cur_pos + u64::from(header.frame_length);
+        previous = Some(header);
+
+        if let (Some(path), Some(length)) = (opts.checkpoint.as_ref(), input.length()) {
+            if progress.next_offset - last_checkpoint >= CHECKPOINT_INTERVAL {
+                // The CRC counts of the frames so far are saved
+                if let Some(digests) = digests.as_mut() {
+                    for ((frame_ref, frame_id), digest) in digests.flush() {
+                        apply_digest(
+                            frame_ref,
+                            frame_id.as_deref(),
+                            digest,
+                            &mut loops,
+                            &mut matcher,
+                            &mut progress,
+                            warnings,
+                        );
+                    }
+                }
+                if let Err(err) = checkpoint::save(path, length, &progress) {
+                    warnings.warn(
+                        "checkpoint",
+                        progress.next_offset,
+                        format!("failed writing checkpoint: {}", err),
+                    );
+                }
+                last_checkpoint = progress.next_offset;
+            }
+        }
+    };
+
+    if let Some(mut digests) = digests {
+        for ((frame_ref, frame_id), digest) in digests.flush() {
+            apply_digest(
+                frame_ref,
+                frame_id.as_deref(),
+                digest,
+                &mut loops,
+                &mut matcher,
+                &mut progress,
+                warnings,
+            );
+        }
+    }
+
+    let code = match ending {
+        Ending::Clean => exitcode::OK,
+        Ending::Truncated { offset } => {
+            warnings.warn(
+                "truncated",
+                offset,
+                "input ends inside this frame".to_string(),
+            );
+            exitcode::OK
+        }
+        Ending::Corrupt { .. } | Ending::ReadError => exitcode::DATAERR,
+    };
+    let mut code = if progress.resyncs.is_empty() {
+        code
+    } else {
+        exitcode::DATAERR
+    };
+    if let (Some(levels), Some(max_level)) = (levels.as_ref(), opts.max_level) {
+        match levels.level() {
+            Ok(level) if level > max_level => {
+                eprintln!(
+                    "error: stream needs AAC Profile level {}, above the maximum of {}",
+                    level, max_level
+                );
+                code = exitcode::DATAERR;
+            }
+            Ok(_) => {}
+            Err(reason) => {
+                eprintln!("error: no AAC Profile level fits the stream: {}", reason);
+                code = exitcode::DATAERR;
+            }
+        }
+    }
+
+    // With resyncs, and for streams, the junk after a corrupt ending was
+    // already collected
+    let stream = matches!(input, Input::Stream { .. });
+    if let (Ending::Corrupt { offset, .. }, true, false) = (ending, opts.no_resync, stream) {
+        match input.skip_junk(offset, opts.sync_confirm) {
+            Ok(found) => junk.push(found),
+            Err(err) => eprintln!("error: failed reading junk data: {}", err),
+        }
+    }
+
+    if let (Some(path), Some(length)) = (opts.checkpoint.as_ref(), input.length()) {
+        if let Err(err) = checkpoint::save(path, length, &progress) {
+            eprintln!("error: failed writing checkpoint: {}", err);
+        }
+    }
+
+    if let Some(decoder) = decoder {
+        match decoder.finish() {
+            Ok(status) if !status.success() => eprintln!("warning: decoder exited with {}", status),
+            Ok(_) => {}
+            Err(err) => eprintln!("error: failed waiting for decoder: {}", err),
+        }
+    }
+    if let Some(boundaries) = boundaries {
+        if let Err(err) = boundaries.finish() {
+            eprintln!("error: failed writing boundary export: {}", err);
+            return exitcode::IOERR;
+        }
+    }
+    if let (Some(redactor), Some(length)) = (redactor, input.length()) {
+        if let Err(err) = redactor.finish(length) {
+            eprintln!("error: failed writing redacted copy: {}", err);
+            return exitcode::IOERR;
+        }
+    }
+    if let Some(extractor) = extractor {
+        if let Err(err) = extractor.finish() {
+            eprintln!("error: failed writing extracted stream: {}", err);
+            return exitcode::IOERR;
+        }
+    }
+    let mut injected = None;
+    if let Some(injector) = injector {
+        match injector.finish() {
+            Ok(count) => injected = Some(count),
+            Err(err) => {
+                eprintln!("error: failed writing marked copy: {}", err);
+                return exitcode::IOERR;
+            }
+        }
+    }
+    let mut impaired = None;
+    if let Some(impairer) = impairer {
+        match impairer.finish() {
+            Ok(counts) => impaired = Some(counts),
+            Err(err) => {
+                eprintln!("error: failed writing impaired copy: {}", err);
+                return exitcode::IOERR;
+            }
+        }
+    }
+    let mut unmuxable = 0;
+    if let Some(loas) = loas {
+        match loas.finish() {
+            Ok(count) => unmuxable = count,
+            Err(err) => {
+                eprintln!("error: failed writing LOAS stream: {}", err);
+                return exitcode::IOERR;
+            }
+        }
+    }
+    let mut unmuxable_mp4 = 0;
+    if let Some(mp4) = mp4 {
+        match mp4.finish() {
+            Ok(count) => unmuxable_mp4 = count,
+            Err(err) => {
+                eprintln!("error: failed writing MP4 file: {}", err);
+                return exitcode::IOERR;
+            }
+        }
+    }
+    let mut chunk_counts = None;
+    if let Some(chunks) = chunks {
+        match chunks.finish() {
+            Ok(counts) => chunk_counts = Some(counts),
+            Err(err) => {
+                eprintln!("error: failed writing chunk: {}", err);
+                return exitcode::IOERR;
+            }
+        }
+    }
+    if opts.format == Format::Text {
+        match stats.as_ref() {
+            Some(stats) => print_summary(stats, &progress, &junk, opts),
+            None => println!(
+                "Read {} frames, total duration {}",
+                progress.frames,
+                time_format(opts).format(progress.time)
+            ),
+        }
+        print_junk(&junk);
+        print_trailers(input.trailers());
+        if dropped > 0 {
+            println!("Dropped {} invalid frames from the output", dropped);
+        }
+        if unmuxable > 0 {
+            println!(
+                "Left {} frames out of the LOAS stream, LATM cannot carry them \
+                 without a channel configuration or with several raw data blocks",
+                unmuxable
+            );
+        }
+        if unmuxable_mp4 > 0 {
+            println!(
+                "Left {} frames out of the MP4 file, without a channel configuration, with \
+                 several raw data blocks or other stream parameters than the first frame",
+                unmuxable_mp4
+            );
+        }
+        if let Some((count, stored)) = chunk_counts {
+            println!("Wrote {} chunks, {} of them new", count, stored);
+        }
+        if let Some(counts) = impaired {
+            println!(
+                "Wrote {} segments to the impaired copy, {} of them reordered and {} duplicated",
+                counts.segments, counts.reordered, counts.duplicated
+            );
+        }
+        if let Some(count) = injected {
+            println!("Injected {} latency markers", count);
+        }
+        if opts.detect_markers {
+            print_markers(&markers, &time_format(opts));
+        }
+        if let Some(detector) = loops.as_mut() {
+            print_loops(detector.finish(), &time_format(opts));
+        }
+        if let Some(matcher) = matcher.as_mut() {
+            print_matches(matcher.finish(), &time_format(opts));
+        }
+        if opts.check_crc {
+            print_crc(&progress.crc);
+        }
+        if let Some(bitrates) = bitrates.as_ref().filter(|_| opts.bitrate) {
+            print_bitrate(bitrates);
+        }
+        if let Some(levels) = levels.as_ref() {
+            print_level(levels);
+        }
+    }
+    if opts.cue.is_some() || opts.chapters.is_some() {
+        let mut events = Vec::new();
+        if let Some(detector) = loops.as_mut() {
+            events.extend(detector.finish().iter().map(|found| Event {
+                start: found.start.time,
+                end: found.end.end_time(),
+                title: format!(
+                    "Loop of content from {}",
+                    time_format(opts).format(found.source.time)
+                ),
+            }));
+        }
+        if let Some(matcher) = matcher.as_mut() {
+            events.extend(matcher.finish().iter().map(|found| Event {
+                start: found.start.time,
+                end: found.end.end_time(),
+                title: found.name.clone(),
+            }));
+        }
+        events.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+        if let Some(path) = opts.cue.as_ref() {
+            let file_name = input_name(opts);
+            let result = fs::File::create(path)
+                .and_then(|out| timeline::write_cue(out, &file_name, progress.time, &events));
+            if let Err(err) = result {
+                eprintln!("error: failed writing CUE sheet: {}", err);
+                return exitcode::CANTCREAT;
+            }
+        }
+        if let Some(path) = opts.chapters.as_ref() {
+            let result =
+                fs::File::create(path).and_then(|out| timeline::write_chapters(out, &events));
+            if let Err(err) = result {
+                eprintln!("error: failed writing chapters: {}", err);
+                return exitcode::CANTCREAT;
+            }
+        }
+    }
+    let findings = Findings {
+        frames: progress.frames,
+        ending,
+        parameter_changes: progress.parameter_changes,
+        resyncs: progress.resyncs,
+        header_warnings: progress.header_warnings,
+        mpeg_audio_frames: progress.mpeg_audio_frames,
+        loops: loops.as_mut().map_or(0, |detector| detector.finish().len()),
+        crc_mismatches: progress.crc.mismatches,
+        element_mismatches: progress.element_mismatches,
+        frame_bytes: bitrates.as_ref().map_or(0, BitrateStats::bytes),
+        bitrate_deviation: bitrates.as_ref().and_then(BitrateStats::deviation),
+    };
+    let health = if opts.health {
+        let weights = opts.health_weights.unwrap_or_default();
+        Some(health::score(&findings, &weights))
+    } else {
+        None
+    };
+    match opts.format {
+        Format::Text => {
+            if opts.verdict {
+                print_verdict(&findings, &time_format(opts));
+            }
+            if let Some(health) = health {
+                println!("Health score: {}/100", health);
+            }
+        }
+        Format::Json => {
+            let verdicts = if opts.verdict {
+                Some(verdict::classify(&findings, &time_format(opts)))
+            } else {
+                None
+            };
+            let summary = Summary {
+                findings: &findings,
+                duration: progress.time,
+                loops: loops.as_mut().map(|detector| detector.finish()),
+                matches: matcher.as_mut().map(|matcher| matcher.finish()),
+                junk: &junk,
+                trailers: input.trailers(),
+                crc: if opts.check_crc {
+                    Some(progress.crc)
+                } else {
+                    None
+                },
+                bitrate: bitrates.as_ref().filter(|_| opts.bitrate),
+                stats: stats.as_ref(),
+                level: levels.as_ref(),
+                markers: if opts.detect_markers {
+                    Some(&markers)
+                } else {
+                    None
+                },
+                verdicts,
+                health,
+                time_format: time_format(opts),
+                output_version: opts.output_version.unwrap_or(output::OUTPUT_VERSION),
+                labels: &opts.labels,
+            };
+            if let Err(err) = write_line(&output::summary_json(&summary)) {
+                return output_failed(err, code);
+            }
+        }
+        Format::Csv => {}
+    }
+
+    code
+}
+
+/// Write a line of JSON or CSV output to stdout
+fn write_line(line: &str) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    writeln!(out, "{}", line)
+}
+
+/// Report a failed write to stdout, returning the exit code of the run
+///
+/// A closed pipe is no error, whoever reads the output wants no more of
+/// it, so the run ends with `code`.
+fn output_failed(err: io::Error, code: exitcode::ExitCode) -> exitcode::ExitCode {
+    if err.kind() == ErrorKind::BrokenPipe {
+        return code;
+    }
+    eprintln!("error: failed writing output: {}", err);
+    exitcode::IOERR
+}
+
+/// How the scan ended when the frame at `offset` could not be read,
+/// reporting errors other than the end of the input
+fn frame_ending(err: &io::Error, offset: u64) -> Ending {
+    if err.kind() == ErrorKind::UnexpectedEof {
+        Ending::Truncated { offset }
+    } else {
+        eprintln!("error: Failed reading frame: {}", err);
+        Ending::ReadError
+    }
+}
+
+fn time_format(opts: &CliArgs) -> TimeFormat {
+    TimeFormat {
+        style: opts.timestamp_format,
+        decimals: opts.decimals,
+    }
+}
+
+/// File name of the input, without its directory
+fn input_name(opts: &CliArgs) -> String {
+    opts.input_path()
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+}
+
+/// Load the checkpoint to resume from, checking it can be used for this run
+fn resume(opts: &CliArgs, file_length: u64) -> Result<Progress, String> {
+    let path = opts
+        .checkpoint
+        .as_ref()
+        .ok_or("--resume requires --checkpoint")?;
+    if opts.detect_loops
+        || !opts.references.is_empty()
+        || opts.redact.is_some()
+        || opts.extract.is_some()
+        || opts.inject_markers.is_some()
+        || opts.impair.is_some()
+        || opts.detect_markers
+        || opts.loas.is_some()
+        || opts.mp4.is_some()
+        || opts.chunks.is_some()
+        || opts.cue.is_some()
+        || opts.chapters.is_some()
+        || opts.boundaries.is_some()
+        || opts.sample.is_some()
+        || opts.bitrate
+        || opts.summary
+        || opts.level
+        || opts.max_level.is_some()
+    {
+        return Err(
+            "loop detection, reference matching, redaction, extraction, remuxing, chunking, \
+             latency markers, impairment, event and boundary export, sampling, bitrate and summary \
+             statistics and level estimates cannot be resumed"
+                .to_string(),
+        );
+    }
+
+    let (checkpoint_length, progress) =
+        checkpoint::load(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    if checkpoint_length != file_length {
+        return Err(format!(
+            "checkpoint is for an input of {} bytes, this one has {}",
+            checkpoint_length, file_length
+        ));
+    }
+    Ok(progress)
+}
+
+/// Spot check about `percent` of the frames at sample positions instead of
+/// walking the whole input
+fn run_sampled(
+    opts: &CliArgs,
+    mut file: &fs::File,
+    start: u64,
+    file_length: u64,
+    percent: f64,
+    profile: &mut Profile,
+) -> exitcode::ExitCode {
+    let first = match peek_header(file, profile) {
+        Ok(header) => header,
+        Err(err) => {
+            eprintln!("error: Failed reading ADTS header: {}", err);
+            return exitcode::DATAERR;
+        }
+    };
+    let estimated_frames = (file_length - start) / u64::from(first.frame_length);
+    let count = ((estimated_frames as f64 * percent / 100.0).ceil() as u64).max(1);
+    let positions = sample::positions(
+        start,
+        file_length,
+        count,
+        opts.sample_periodic,
+        opts.sample_seed,
+    );
+
+    let mut failed = Vec::new();
+    let mut window = Vec::new();
+    for position in positions.iter() {
+        window.clear();
+        let read = profile.measure(Stage::Io, || {
+            file.seek(SeekFrom::Start(*position))?;
+            file.take(sample_window(opts.sync_confirm))
+                .read_to_end(&mut window)
+        });
+        if let Err(err) = read {
+            eprintln!("error: failed reading sample at {}: {}", position, err);
+            return exitcode::IOERR;
+        }
+
+        let found = profile.measure(Stage::SyncSearch, || {
+            find_confirmed_frame(&window, *position, file_length, opts.sync_confirm)
+        });
+        if found.is_none() {
+            failed.push(*position);
+        }
+    }
+
+    let total = positions.len() as u64;
+    let valid = total - failed.len() as u64;
+    println!(
+        "Sampled {} positions: {} valid frames ({:.2}% \u{b1} {:.2}% at 95% confidence)",
+        total,
+        valid,
+        valid as f64 / total as f64 * 100.0,
+        sample::confidence_interval(valid, total) * 100.0
+    );
+    for position in failed.iter() {
+        println!(
+            "No valid frame within {} bytes of offset {}",
+            SAMPLE_WINDOW, position
+        );
+    }
+
+    if failed.is_empty() {
+        exitcode::OK
+    } else {
+        exitcode::DATAERR
+    }
+}
+
+/// Find the first frame in `buffer` whose header is valid and followed by
+/// `confirm - 1` more headers of the same stream, or by the end of the input
+///
+/// `offset` is the position of `buffer` in the input.
+fn find_confirmed_frame(
+    buffer: &[u8],
+    offset: u64,
+    file_length: u64,
+    confirm: usize,
+) -> Option<usize> {
+    let candidates = buffer
+        .len()
+        .saturating_sub(ADTS_HDR_MIN_LEN - 1)
+        .min(SAMPLE_WINDOW);
+    let header_at = |pos: usize| {
+        let bytes = buffer.get(pos..pos + ADTS_HDR_MIN_LEN)?;
+        parse_header(bytes.try_into().unwrap(), offset + pos as u64).ok()
+    };
+
+    (0..candidates).find(|&pos| {
+        if buffer[pos] != 0xFF || buffer[pos + 1] & 0xF0 != 0xF0 {
+            return false;
+        }
+        let first = match header_at(pos) {
+            Some(header) => header,
+            None => return false,
+        };
+        let mut next = pos + usize::from(first.frame_length);
+        for _ in 1..confirm {
+            if offset + next as u64 == file_length {
+                return true;
+            }
+            match header_at(next) {
+                Some(header) if first.same_stream(&header) => {
+                    next += usize::from(header.frame_length)
+                }
+                _ => return false,
+            }
+        }
+        true
+    })
+}
+
+/// Scan a whole disk image for runs of valid frames and extract each
+/// recovered stream to `dir`
+fn run_carve(
+    mut file: &fs::File,
+    file_length: u64,
+    dir: &Path,
+    time_format: &TimeFormat,
+) -> exitcode::ExitCode {
+    if let Err(err) = fs::create_dir_all(dir) {
+        eprintln!("error: failed creating '{}': {}", dir.display(), err);
+        return exitcode::CANTCREAT;
+    }
+
+    let mut runs = Vec::new();
+    let mut window = Vec::new();
+    let mut pos = match file.stream_position() {
+        Ok(pos) => pos,
+        Err(err) => {
+            eprintln!("error: failed reading input: {}", err);
+            return exitcode::IOERR;
+        }
+    };
+    while pos < file_length {
+        window.clear();
+        let read = file
+            .seek(SeekFrom::Start(pos))
+            .and_then(|_| file.take(CARVE_WINDOW).read_to_end(&mut window));
+        if let Err(err) = read {
+            eprintln!("error: failed reading input at {}: {}", pos, err);
+            return exitcode::IOERR;
+        }
+
+        let mut found = None;
+        for i in 0..window.len().saturating_sub(1) {
+            if window[i] != 0xFF || window[i + 1] & 0xF0 != 0xF0 {
+                continue;
+            }
+            match follow_run(file, pos + i as u64) {
+                Ok(Some(run)) => {
+                    found = Some(run);
+                    break;
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    eprintln!("error: failed reading input: {}", err);
+                    return exitcode::IOERR;
+                }
+            }
+        }
+        match found {
+            Some(run) => {
+                pos = run.end;
+                runs.push(run);
+            }
+            // Keep the last byte, it may start a sync
+            None => pos += (window.len() as u64).saturating_sub(1).max(1),
+        }
+    }
+
+    let candidates = carve::group(runs, CARVE_MAX_GAP);
+    if candidates.is_empty() {
+        println!("No streams found");
+        return exitcode::DATAERR;
+    }
+    for (index, candidate) in candidates.iter().enumerate() {
+        let path = dir.join(format!("stream-{:03}.aac", index + 1));
+        let result = fs::File::create(&path).and_then(|out| {
+            let mut out = BufWriter::new(out);
+            for run in candidate.runs.iter() {
+                file.seek(SeekFrom::Start(run.start))?;
+                io::copy(&mut file.take(run.end - run.start), &mut out)?;
+            }
+            out.flush()
+        });
+        if let Err(err) = result {
+            eprintln!("error: failed writing '{}': {}", path.display(), err);
+            return exitcode::IOERR;
+        }
+        println!(
+            "Stream {} at offsets {}-{}: {}, {} frames in {} runs, {}, \
+             confidence {}%, written to '{}'",
+            index + 1,
+            candidate.start(),
+            candidate.end(),
+            candidate.format(),
+            candidate.frames(),
+            candidate.runs.len(),
+            time_format.format(candidate.duration()),
+            candidate.confidence(),
+            path.display()
+        );
+    }
+    exitcode::OK
+}
+
+/// Follow consecutive frames with the same parameters from `offset`,
+/// returning them if there are at least `CARVE_MIN_FRAMES`
+fn follow_run(mut file: &fs::File, offset: u64) -> std::io::Result<Option<Run>> {
+    let mut buffer = [0; ADTS_HDR_MIN_LEN];
+    let mut run = Run {
+        start: offset,
+        end: offset,
+        frames: 0,
+        duration: 0.0,
+        format: String::new(),
+    };
+    loop {
+        file.seek(SeekFrom::Start(run.end))?;
+        match file.read_exact(&mut buffer) {
+            Err(ref err) if err.kind() == ErrorKind::UnexpectedEof => break,
+            result => result?,
+        }
+        let header = match parse_header(&buffer, run.end) {
+            Ok(header) => header,
+            Err(_) => break,
+        };
+        let format = format!(
+            "{:?} {:?}, {} Hz",
+            header.id,
+            header.profile,
+            header.sampling_frequency()
+        );
+        if run.frames == 0 {
+            run.format = format;
+        } else if format != run.format {
+            break;
+        }
+        run.frames += 1;
+        run.duration += header.duration();
+        run.end += u64::from(header.frame_length);
+    }
+
+    if run.frames < CARVE_MIN_FRAMES {
+        return Ok(None);
+    }
+    Ok(Some(run))
+}
+
+/// Report on the first frame that is followed by another valid frame
+fn run_first_frame_report(
+    file: &fs::File,
+    start: u64,
+    file_length: u64,
+    confirm: usize,
+) -> exitcode::ExitCode {
+    let mut window = Vec::new();
+    let read = file.take(sample_window(confirm)).read_to_end(&mut window);
+    if let Err(err) = read {
+        eprintln!("error: failed reading input: {}", err);
+        return exitcode::IOERR;
+    }
+
+    let pos = match find_confirmed_frame(&window, start, file_length, confirm) {
+        Some(pos) => pos,
+        None => {
+            eprintln!(
+                "error: no confirmed frame within {} bytes of offset {}",
+                SAMPLE_WINDOW, start
+            );
+            return exitcode::DATAERR;
+        }
+    };
+    if let Err(err) = report::write_first_frame(io::stdout(), start + pos as u64, &window[pos..]) {
+        eprintln!("error: failed writing report: {}", err);
+        return exitcode::IOERR;
+    }
+    exitcode::OK
+}
+
+/// Detect whether the capture is byte-swapped or bit-shifted, and optionally
+/// write a corrected copy
+fn run_realign(opts: &CliArgs, mut file: &fs::File) -> exitcode::ExitCode {
+    let mut window = Vec::new();
+    let read = file.stream_position().and_then(|start| {
+        file.take(REALIGN_WINDOW).read_to_end(&mut window)?;
+        file.seek(SeekFrom::Start(start))
+    });
+    if let Err(err) = read {
+        eprintln!("error: failed reading input: {}", err);
+        return exitcode::IOERR;
+    }
+
+    let aligned = longest_run(&window);
+    let (transform, frames) = Transform::all()
+        .skip(1)
+        .map(|transform| (transform, longest_run(&transform.apply(&window))))
+        .fold((Transform::IDENTITY, aligned), |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        });
+    if frames < REALIGN_MIN_FRAMES {
+        println!(
+            "No alignment gives {} consecutive valid frames",
+            REALIGN_MIN_FRAMES
+        );
+        return exitcode::DATAERR;
+    }
+    println!(
+        "Capture is {}: {} consecutive valid frames ({} without correction)",
+        transform, frames, aligned
+    );
+
+    if let Some(path) = opts.realign_output.as_ref() {
+        let result = fs::File::create(path).and_then(|out| {
+            realign::write_corrected(BufReader::new(file), BufWriter::new(out), transform)
+        });
+        if let Err(err) = result {
+            eprintln!("error: failed writing corrected copy: {}", err);
+            return exitcode::IOERR;
+        }
+        println!("Wrote corrected copy to '{}'", path.display());
+    }
+    exitcode::OK
+}
+
+/// Length of the longest run of consecutive valid frames in `buffer`
+fn longest_run(buffer: &[u8]) -> usize {
+    let header_at = |pos: usize| {
+        let bytes = buffer.get(pos..pos + ADTS_HDR_MIN_LEN)?;
+        parse_header(bytes.try_into().unwrap(), pos as u64).ok()
+    };
+
+    let mut longest = 0;
+    for pos in 0..buffer.len().saturating_sub(1) {
+        if buffer[pos] != 0xFF || buffer[pos + 1] & 0xF0 != 0xF0 {
+            continue;
+        }
+        let mut frames = 0;
+        let mut next = pos;
+        while let Some(header) = header_at(next) {
+            frames += 1;
+            next += usize::from(header.frame_length);
+        }
+        longest = longest.max(frames);
+    }
+    longest
+}
+
+/// Describe how the stream parameters changed between two frames, if they did
+fn describe_parameter_change(previous: &AdtsHeader, header: &AdtsHeader) -> Option<String> {
+    let mut changes = Vec::new();
+    if previous.id != header.id {
+        changes.push(format!("ID {:?} -> {:?}", previous.id, header.id));
+    }
+    if previous.profile != header.profile {
+        changes.push(format!(
+            "profile {:?} -> {:?}",
+            previous.profile, header.profile
+        ));
+    }
+    if previous.sampling_frequency_index != header.sampling_frequency_index {
+        changes.push(format!(
+            "sampling frequency {} Hz -> {} Hz",
+            previous.sampling_frequency(),
+            header.sampling_frequency()
+        ));
+    }
+    if previous.channel_configuration != header.channel_configuration {
+        changes.push(format!(
+            "channel configuration {} -> {}",
+            previous.channel_configuration, header.channel_configuration
+        ));
+    }
+
+    if changes.is_empty() {
+        None
+    } else {
+        Some(changes.join(", "))
+    }
+}
+
+fn print_verdict(findings: &Findings, time_format: &TimeFormat) {
+    for verdict in verdict::classify(findings, time_format) {
+        println!("Verdict: {}", verdict.classification);
+        for evidence in verdict.evidence {
+            println!("  - {}", evidence);
+        }
+    }
+}
+
+fn print_junk(junk: &[Junk]) {
+    for found in junk {
+        println!(
+            "Junk at offset {} ({} bytes): {}",
+            found.offset,
+            found.length,
+            found.kind.name()
+        );
+    }
+}
+
+fn print_summary(stats: &FrameStats, progress: &Progress, junk: &[Junk], opts: &CliArgs) {
+    println!("Frames: {}", progress.frames);
+    println!(
+        "Bytes: {} in frames, {} of them payload",
+        stats.total_bytes, stats.payload_bytes
+    );
+    println!("Duration: {}", time_format(opts).format(progress.time));
+    if let Some(average) = stats.average_length() {
+        println!(
+            "Frame length: average {:.1}, min {}, max {} bytes",
+            average, stats.min_length, stats.max_length
+        );
+    }
+    let sample_rates: Vec<String> = stats
+        .sample_rates
+        .iter()
+        .map(|rate| format!("{} Hz", rate))
+        .collect();
+    println!("Sample rates: {}", sample_rates.join(", "));
+    let profiles: Vec<String> = stats
+        .profiles
+        .iter()
+        .map(|profile| format!("{:?}", profile))
+        .collect();
+    println!("Profiles: {}", profiles.join(", "));
+    if opts.check_crc {
+        println!("CRC failures: {}", progress.crc.mismatches);
+    } else {
+        println!("CRC failures: not checked");
+    }
+    let junk_bytes: u64 = junk.iter().map(|found| found.length).sum();
+    println!("Junk bytes skipped: {}", junk_bytes);
+}
+
+fn print_bitrate(bitrates: &BitrateStats) {
+    let (average, (min, max)) = match (bitrates.average(), bitrates.min_max()) {
+        (Some(average), Some(min_max)) => (average, min_max),
+        _ => {
+            println!("Bitrate: no frames");
+            return;
+        }
+    };
+    let mode = match bitrates.is_cbr() {
+        Some(true) => "CBR",
+        Some(false) => "VBR",
+        None => "too short to tell CBR from VBR",
+    };
+    println!(
+        "Bitrate: average {:.1} kbit/s, frames between {:.1} and {:.1} kbit/s, {}",
+        average / 1000.0,
+        min / 1000.0,
+        max / 1000.0,
+        mode
+    );
+}
+
+fn print_level(levels: &LevelCheck) {
+    match levels.level() {
+        Ok(level) => println!(
+            "AAC Profile level: {} for {} channels at {} Hz, frames up to {:.1} of {:.1} kbit/s",
+            level,
+            levels.channels(),
+            levels.sample_rate(),
+            levels.peak_bitrate() / 1000.0,
+            level::max_bitrate(levels.channels(), levels.sample_rate()) / 1000.0
+        ),
+        Err(reason) => println!("AAC Profile level: none, {}", reason),
+    }
+}
+
+fn print_adif(header: &AdifHeader, offset: u64) {
+    println!("Found ADIF header at offset {}", offset);
+    if let Some(id) = header.copyright_id.as_ref() {
+        let id: String = id.iter().map(|byte| format!("{:02X}", byte)).collect();
+        println!("Copyright ID is {}", id);
+    }
+    println!(
+        "Original: {}, home: {}",
+        if header.original_copy { "yes" } else { "no" },
+        if header.home { "yes" } else { "no" }
+    );
+    if header.variable_bitrate {
+        println!("Bitrate is variable, at most {} bit/s", header.bitrate);
+    } else {
+        println!("Bitrate is constant, {} bit/s", header.bitrate);
+    }
+    for (index, program) in header.programs.iter().enumerate() {
+        let config = &program.config;
+        println!("Program {}:", index);
+        println!("  Profile is {:?}", config.profile());
+        match SAMPLING_FREQUENCIES.get(usize::from(config.sampling_frequency_index)) {
+            Some(frequency) => println!(
+                "  Sampling frequency is {} Hz (index {})",
+                frequency, config.sampling_frequency_index
+            ),
+            None => println!(
+                "  Sampling frequency index {} is invalid",
+                config.sampling_frequency_index
+            ),
+        }
+        let describe = |elements: &[bool]| -> String {
+            let names: Vec<&str> = elements
+                .iter()
+                .map(|&is_cpe| if is_cpe { "CPE" } else { "SCE" })
+                .collect();
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.join(" ")
+            }
+        };
+        println!(
+            "  Channels: {}, front {}, side {}, back {}, LFE {}",
+            config.channels(),
+            describe(&config.front),
+            describe(&config.side),
+            describe(&config.back),
+            config.lfe
+        );
+        if let Some(fullness) = program.buffer_fullness {
+            println!("  Buffer fullness is {}", fullness);
+        }
+        if !config.comment.is_empty() {
+            println!(
+                "  Comment is '{}'",
+                String::from_utf8_lossy(&config.comment)
+            );
+        }
+    }
+    println!(
+        "Raw data starts at offset {}",
+        offset + header.header_length as u64
+    );
+}
+
+fn print_probes(probes: &[(Acceptance, Vec<FrameRun>)], length: usize, offset: u64) {
+    for (acceptance, runs) in probes {
+        println!(
+            "{}: {} frames in {} runs, covering {:.1}% of {} bytes, confidence {:.2}",
+            acceptance.format.name(),
+            acceptance.frames,
+            runs.len(),
+            acceptance.covered as f64 * 100.0 / length as f64,
+            length,
+            acceptance.confidence
+        );
+        if let Some(parameters) = acceptance.parameters {
+            match parameters.channels {
+                Some(channels) => println!(
+                    "  {} Hz, {} channels in the longest run",
+                    parameters.sample_rate, channels
+                ),
+                None => println!("  {} Hz in the longest run", parameters.sample_rate),
+            }
+        }
+        for run in runs {
+            println!(
+                "  Bytes {} to {}: {} frames",
+                offset + run.start as u64,
+                offset + run.end as u64,
+                run.frames
+            );
+        }
+    }
+}
+
+/// Report the ID3v2 tag at `offset`, with its frames if `data` holds the
+/// tag
+fn print_id3(opts: &CliArgs, tag: &Id3v2Header, data: &[u8], offset: u64) {
+    let frames = if opts.show_id3 {
+        Some(id3::parse_frames(data, tag))
+    } else {
+        None
+    };
+    match opts.format {
+        Format::Text => {}
+        Format::Json => {
+            println!("{}", output::id3_json(tag, frames.as_deref(), offset));
+            return;
+        }
+        Format::Csv => return,
+    }
+
+    println!(
+        "Skipped ID3v2.{} tag at offset {}, {} bytes",
+        tag.major_version,
+        offset,
+        tag.total_length()
+    );
+    for frame in frames.iter().flatten() {
+        let mut line = format!("ID3v2 frame {}", frame.id);
+        if let Some(name) = frame.name() {
+            line.push_str(&format!(" ({})", name));
+        }
+        match frame.text.as_ref() {
+            Some(text) => line.push_str(&format!(": {}", text)),
+            None => line.push_str(&format!(", {} bytes", frame.length)),
+        }
+        println!("{}", line);
+    }
+}
+
+/// Report the tags after the last frame with their contents
+fn print_trailers(trailers: &[Trailer]) {
+    for trailer in trailers {
+        match trailer {
+            Trailer::Id3v1 { offset, tag } => {
+                let version = if tag.track.is_some() { "1.1" } else { "1" };
+                println!(
+                    "Trailing ID3v{} tag at offset {}, {} bytes",
+                    version,
+                    offset,
+                    trailer.length()
+                );
+                let fields = [
+                    ("title", &tag.title),
+                    ("artist", &tag.artist),
+                    ("album", &tag.album),
+                    ("year", &tag.year),
+                    ("comment", &tag.comment),
+                ];
+                for (name, value) in fields.iter().filter(|(_, value)| !value.is_empty()) {
+                    println!("ID3v1 {}: {}", name, value);
+                }
+                if let Some(track) = tag.track {
+                    println!("ID3v1 track: {}", track);
+                }
+                if let Some(genre) = tag.genre {
+                    println!("ID3v1 genre: {}", genre);
+                }
+            }
+            Trailer::Ape {
+                offset,
+                footer,
+                items,
+            } => {
+                println!(
+                    "Trailing APEv{} tag at offset {}, {} bytes, {} items",
+                    footer.version / 1000,
+                    offset,
+                    trailer.length(),
+                    footer.items
+                );
+                for item in items {
+                    match item.text.as_ref() {
+                        Some(text) => println!("APE item {}: {}", item.key, text),
+                        None => println!("APE item {}, {} bytes", item.key, item.length),
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn print_crc(crc: &CrcCounts) {
+    println!(
+        "CRC: {} valid, {} mismatched, {} not verifiable",
+        crc.valid, crc.mismatches, crc.unverifiable
+    );
+}
+
+fn print_loops(loops: &[Loop], time_format: &TimeFormat) {
+    if loops.is_empty() {
+        println!("No loops detected");
+    }
+    for found in loops {
+        let period = found.start.time - found.source.time;
+        println!(
+            "Loop at frame {} (offset {}, {}): repeats content from frame {} (offset {}, {}), \
+             period {} frames ({}), {} frames ({:.1} repetitions) until {}",
+            found.start.index,
+            found.start.offset,
+            time_format.format(found.start.time),
+            found.source.index,
+            found.source.offset,
+            time_format.format(found.source.time),
+            found.period,
+            time_format.format(period),
+            found.repeated_frames(),
+            found.repeated_frames() as f64 / found.period as f64,
+            time_format.format(found.end.end_time()),
+        );
+    }
+}
+
+fn print_markers(markers: &[Marker], time_format: &TimeFormat) {
+    for found in markers {
+        println!(
+            "Latency marker at offset {} ({}): {}",
+            found.offset,
+            time_format.format(found.time),
+            time_format.format(found.latency)
+        );
+    }
+    match marker::latency_range(markers) {
+        Some((min, mean, max)) => println!(
+            "Latency over {} markers: min {}, mean {}, max {}",
+            markers.len(),
+            time_format.format(min),
+            time_format.format(mean),
+            time_format.format(max)
+        ),
+        None => println!("No latency markers found"),
+    }
+}
+
+fn print_matches(matches: &[Match], time_format: &TimeFormat) {
+    if matches.is_empty() {
+        println!("No reference clips found");
+    }
+    for found in matches {
+        println!(
+            "Reference '{}' at frame {} (offset {}, {}) until {}: \
+             {} of {} frames matched starting at reference frame {} ({:.1}%)",
+            found.name,
+            found.start.index,
+            found.start.offset,
+            time_format.format(found.start.time),
+            time_format.format(found.end.end_time()),
+            found.frames,
+            found.reference_frames,
+            found.reference_start,
+            found.frames as f64 / found.reference_frames as f64 * 100.0,
+        );
     }
 }
@@ -0,0 +1,137 @@
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use streamreader::elements;
+use streamreader::{AdtsHeader, ADTS_MAX_FRAME_LEN};
+
+/// Start of the data stream element payload of a latency marker, followed
+/// by the time it was injected in microseconds since the Unix epoch
+const MAGIC: [u8; 4] = *b"SRLM";
+const PAYLOAD_LEN: usize = MAGIC.len() + 8;
+/// id_syn_ele, element_instance_tag, data_byte_align_flag and count
+const DSE_HEADER_LEN: usize = 2;
+/// Tag of the injected data stream elements
+const ELEMENT_INSTANCE_TAG: u8 = 0xF;
+
+/// Writes a copy of the input with a latency marker every `interval`
+/// seconds of audio
+///
+/// The marker is a data stream element put in front of the raw data block,
+/// holding the wall clock time it was written at. Decoders skip it. The
+/// copy is flushed after every frame, so it can be relayed live.
+///
+/// Frames with a CRC or several raw data blocks are copied unchanged, as
+/// the marker would move the data the CRC and block positions refer to. The
+/// buffer fullness is not adjusted for the larger frames.
+pub struct MarkerInjector<W: Write> {
+    out: W,
+    interval: f64,
+    /// Stream time of the next marker
+    next: f64,
+    injected: u64,
+}
+
+impl<W: Write> MarkerInjector<W> {
+    pub fn new(out: W, interval: f64) -> MarkerInjector<W> {
+        MarkerInjector {
+            out,
+            interval,
+            next: 0.0,
+            injected: 0,
+        }
+    }
+
+    /// Write the frame starting at `time` seconds into the stream
+    pub fn frame(&mut self, time: f64, header: &AdtsHeader, frame: &[u8]) -> io::Result<()> {
+        let markable = header.protection_absent
+            && header.num_raw_data_blocks == 0
+            && frame.len() + DSE_HEADER_LEN + PAYLOAD_LEN <= ADTS_MAX_FRAME_LEN;
+        if time < self.next || !markable {
+            self.out.write_all(frame)?;
+            return self.out.flush();
+        }
+
+        let header_length = header.header_length();
+        let length = frame.len() + DSE_HEADER_LEN + PAYLOAD_LEN;
+        let mut marked = Vec::with_capacity(length);
+        marked.extend_from_slice(&frame[..header_length]);
+        set_frame_length(&mut marked, length as u16);
+        marked.push(4 << 5 | ELEMENT_INSTANCE_TAG << 1);
+        marked.push(PAYLOAD_LEN as u8);
+        marked.extend_from_slice(&MAGIC);
+        marked.extend_from_slice(&now_micros().to_be_bytes());
+        marked.extend_from_slice(&frame[header_length..]);
+
+        self.out.write_all(&marked)?;
+        self.out.flush()?;
+        self.injected += 1;
+        self.next = time + self.interval;
+        Ok(())
+    }
+
+    /// Finish the copy, returning the number of markers injected
+    pub fn finish(mut self) -> io::Result<u64> {
+        self.out.flush()?;
+        Ok(self.injected)
+    }
+}
+
+fn set_frame_length(header: &mut [u8], length: u16) {
+    header[3] = (header[3] & 0xFC) | (length >> 11) as u8;
+    header[4] = (length >> 3) as u8;
+    header[5] = (header[5] & 0x1F) | ((length & 0x07) << 5) as u8;
+}
+
+fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_micros() as u64)
+}
+
+/// A latency marker found in a frame
+pub struct Marker {
+    pub offset: u64,
+    /// Stream time of the frame that carried it
+    pub time: f64,
+    /// Seconds between injecting and finding the marker, negative if the
+    /// clocks of both ends are apart by more than the latency
+    pub latency: f64,
+}
+
+/// Look for a latency marker in a frame starting at `offset`, timing it
+/// against the wall clock
+pub fn detect(frame: &[u8], header_length: usize, offset: u64, time: f64) -> Option<Marker> {
+    let raw_data = &frame[header_length..];
+    let streams = elements::leading_data_streams(raw_data, offset + header_length as u64).ok()?;
+    let stream = streams.iter().find(|stream| {
+        stream.element_instance_tag == ELEMENT_INSTANCE_TAG
+            && stream.data.len() == PAYLOAD_LEN
+            && stream.data.starts_with(&MAGIC)
+    })?;
+    let mut stamp = [0; 8];
+    stamp.copy_from_slice(&stream.data[MAGIC.len()..]);
+    let injected = u64::from_be_bytes(stamp);
+
+    Some(Marker {
+        offset,
+        time,
+        latency: (now_micros() as f64 - injected as f64) / 1e6,
+    })
+}
+
+/// Lowest, mean and highest latency of the markers found
+pub fn latency_range(markers: &[Marker]) -> Option<(f64, f64, f64)> {
+    if markers.is_empty() {
+        return None;
+    }
+    let min = markers
+        .iter()
+        .map(|m| m.latency)
+        .fold(f64::INFINITY, f64::min);
+    let max = markers
+        .iter()
+        .map(|m| m.latency)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let mean = markers.iter().map(|m| m.latency).sum::<f64>() / markers.len() as f64;
+    Some((min, mean, max))
+}
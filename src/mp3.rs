@@ -0,0 +1,53 @@
+use streamreader::mpeg_audio;
+
+use crate::walk::{Advertised, Frame};
+
+/// Parse the MPEG audio frame at the start of `data`
+pub fn parse_frame(data: &[u8]) -> Option<Frame> {
+    let header = mpeg_audio::parse_frame_header(data)?;
+    let length = header.frame_length();
+
+    let mut lines = vec![
+        format!("Len is {}", length),
+        format!(
+            "Version is {} Layer {}",
+            header.version,
+            ["I", "II", "III"][usize::from(header.layer - 1)]
+        ),
+        format!("Bitrate is {} kbit/s", header.bitrate),
+        format!("Sampling frequency is {} Hz", header.sample_rate),
+        format!("Channel mode is {}", header.channel_mode_name()),
+        format!(
+            "Padding is {}",
+            if header.padding { "set" } else { "unset" }
+        ),
+        format!(
+            "CRC present: {}",
+            if header.protected { "yes" } else { "no" }
+        ),
+    ];
+    let frame = &data[..length.min(data.len())];
+    let vbr = mpeg_audio::vbr_header(frame, &header);
+    if let Some(vbr) = vbr {
+        let mut line = format!("VBR header is {}", vbr.tag.name());
+        if let Some(frames) = vbr.frames {
+            line.push_str(&format!(", {} frames", frames));
+        }
+        if let Some(bytes) = vbr.bytes {
+            line.push_str(&format!(", {} bytes", bytes));
+        }
+        lines.push(line);
+    }
+
+    Some(Frame {
+        length,
+        duration: header.duration(),
+        lines,
+        warnings: Vec::new(),
+        advertised: vbr.map(|vbr| Advertised {
+            source: vbr.tag.name(),
+            frames: vbr.frames.map(u64::from),
+            bytes: vbr.bytes.map(u64::from),
+        }),
+    })
+}
@@ -0,0 +1,267 @@
+use std::io::{self, Error, ErrorKind, Seek, SeekFrom, Write};
+
+use streamreader::{channel_count, AdtsHeader, AAC_FRAME_SAMPLES};
+
+/// Length of the ftyp box written first
+const FTYP_LEN: u64 = 28;
+/// Length of the mdat box header, which has a 32 bit size
+const MDAT_HEADER_LEN: u64 = 8;
+/// Timescale of the movie header, in units per second
+const MOVIE_TIMESCALE: u32 = 1000;
+
+/// Writes frames into a minimal MP4 file with a single AAC track
+///
+/// The payloads go into the mdat box as they come, the sample tables of the
+/// moov box are written by `finish`. The sample entry is built from the
+/// first frame, later frames of other stream parameters are left out, as
+/// are frames LATM would not carry either: without a channel configuration
+/// or with several raw data blocks.
+pub struct Mp4Writer<W: Write + Seek> {
+    out: W,
+    /// Object type, sampling frequency index and channel configuration
+    config: Option<(u8, u8, u8)>,
+    sample_rate: u32,
+    sizes: Vec<u32>,
+    mdat_len: u64,
+    skipped: u64,
+}
+
+impl<W: Write + Seek> Mp4Writer<W> {
+    pub fn new(mut out: W) -> io::Result<Mp4Writer<W>> {
+        let mut ftyp = Vec::new();
+        ftyp.extend_from_slice(b"M4A ");
+        ftyp.extend_from_slice(&0u32.to_be_bytes());
+        ftyp.extend_from_slice(b"M4A mp42isom");
+        out.write_all(&boxed(b"ftyp", &ftyp))?;
+        // The size is filled in by finish
+        out.write_all(&boxed_header(b"mdat", 0))?;
+        Ok(Mp4Writer {
+            out,
+            config: None,
+            sample_rate: 0,
+            sizes: Vec::new(),
+            mdat_len: 0,
+            skipped: 0,
+        })
+    }
+
+    pub fn frame(&mut self, header: &AdtsHeader, frame: &[u8]) -> io::Result<()> {
+        let config = (
+            header.profile as u8,
+            header.sampling_frequency_index,
+            header.channel_configuration,
+        );
+        if header.channel_configuration == 0
+            || header.num_raw_data_blocks != 0
+            || self.config.is_some_and(|first| first != config)
+        {
+            self.skipped += 1;
+            return Ok(());
+        }
+        if self.config.is_none() {
+            self.config = Some(config);
+            self.sample_rate = header.sampling_frequency();
+        }
+
+        let payload = &frame[header.header_length()..];
+        self.out.write_all(payload)?;
+        self.sizes.push(payload.len() as u32);
+        self.mdat_len += payload.len() as u64;
+        Ok(())
+    }
+
+    /// Write the sample tables, returning the number of frames left out
+    pub fn finish(mut self) -> io::Result<u64> {
+        let mdat_size = MDAT_HEADER_LEN + self.mdat_len;
+        if mdat_size > u64::from(u32::MAX) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "more than 4 GiB of samples",
+            ));
+        }
+        let moov = match self.config {
+            Some(config) => self.moov(config),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "no frame to write into the MP4 file",
+                ))
+            }
+        };
+        self.out.write_all(&moov)?;
+        self.out.seek(SeekFrom::Start(FTYP_LEN))?;
+        self.out
+            .write_all(&boxed_header(b"mdat", mdat_size as u32))?;
+        self.out.flush()?;
+        Ok(self.skipped)
+    }
+
+    fn moov(&self, config: (u8, u8, u8)) -> Vec<u8> {
+        let samples = self.sizes.len() as u32;
+        let duration = u64::from(samples) * u64::from(AAC_FRAME_SAMPLES);
+        let movie_duration =
+            (duration * u64::from(MOVIE_TIMESCALE) / u64::from(self.sample_rate)) as u32;
+
+        let mut mvhd = Vec::new();
+        put_u32s(&mut mvhd, &[0, 0, MOVIE_TIMESCALE, movie_duration]);
+        // Rate 1.0, volume 1.0 and reserved
+        put_u32s(&mut mvhd, &[0x0001_0000, 0x0100_0000, 0, 0]);
+        put_matrix(&mut mvhd);
+        // pre_defined, then next_track_ID
+        put_u32s(&mut mvhd, &[0, 0, 0, 0, 0, 0, 2]);
+
+        let mut tkhd = Vec::new();
+        // Times, track_ID, reserved, duration and reserved
+        put_u32s(&mut tkhd, &[0, 0, 1, 0, movie_duration, 0, 0]);
+        // Layer and alternate group, volume 1.0 and reserved
+        put_u32s(&mut tkhd, &[0, 0x0100_0000]);
+        put_matrix(&mut tkhd);
+        // Width and height
+        put_u32s(&mut tkhd, &[0, 0]);
+
+        let mut mdhd = Vec::new();
+        put_u32s(&mut mdhd, &[0, 0, self.sample_rate, duration as u32]);
+        // Language "und" and pre_defined
+        put_u32s(&mut mdhd, &[0x55C4_0000]);
+
+        let mut hdlr = Vec::new();
+        put_u32s(&mut hdlr, &[0]);
+        hdlr.extend_from_slice(b"soun");
+        put_u32s(&mut hdlr, &[0, 0, 0]);
+        hdlr.extend_from_slice(b"SoundHandler\0");
+
+        let url = full_box(b"url ", 1, &[]);
+        let mut dref = Vec::new();
+        put_u32s(&mut dref, &[1]);
+        dref.extend_from_slice(&url);
+        let dinf = boxed(b"dinf", &full_box(b"dref", 0, &dref));
+
+        let mut stsd = Vec::new();
+        put_u32s(&mut stsd, &[1]);
+        stsd.extend_from_slice(&self.mp4a(config));
+
+        let mut stts = Vec::new();
+        put_u32s(&mut stts, &[1, samples, AAC_FRAME_SAMPLES]);
+        let mut stsc = Vec::new();
+        put_u32s(&mut stsc, &[1, 1, samples, 1]);
+        let mut stsz = Vec::new();
+        put_u32s(&mut stsz, &[0, samples]);
+        put_u32s(&mut stsz, &self.sizes);
+        // All samples are one chunk, right after the mdat header
+        let mut stco = Vec::new();
+        put_u32s(&mut stco, &[1, (FTYP_LEN + MDAT_HEADER_LEN) as u32]);
+
+        let stbl = boxed(
+            b"stbl",
+            &[
+                full_box(b"stsd", 0, &stsd),
+                full_box(b"stts", 0, &stts),
+                full_box(b"stsc", 0, &stsc),
+                full_box(b"stsz", 0, &stsz),
+                full_box(b"stco", 0, &stco),
+            ]
+            .concat(),
+        );
+        let minf = boxed(
+            b"minf",
+            &[full_box(b"smhd", 0, &[0; 4]), dinf, stbl].concat(),
+        );
+        let mdia = boxed(
+            b"mdia",
+            &[
+                full_box(b"mdhd", 0, &mdhd),
+                full_box(b"hdlr", 0, &hdlr),
+                minf,
+            ]
+            .concat(),
+        );
+        let trak = boxed(b"trak", &[full_box(b"tkhd", 7, &tkhd), mdia].concat());
+        boxed(b"moov", &[full_box(b"mvhd", 0, &mvhd), trak].concat())
+    }
+
+    /// Sample entry with the esds box carrying the AudioSpecificConfig
+    fn mp4a(&self, config: (u8, u8, u8)) -> Vec<u8> {
+        let (object_type, sampling_frequency_index, channel_configuration) = config;
+        let max_size = self.sizes.iter().copied().max().unwrap_or(0);
+        let seconds =
+            self.sizes.len() as f64 * f64::from(AAC_FRAME_SAMPLES) / f64::from(self.sample_rate);
+        let average = (self.mdat_len as f64 * 8.0 / seconds) as u32;
+        let frame_seconds = f64::from(AAC_FRAME_SAMPLES) / f64::from(self.sample_rate);
+        let max = (f64::from(max_size) * 8.0 / frame_seconds) as u32;
+
+        // AudioSpecificConfig, with a GASpecificConfig of all zero flags
+        let audio_config = (u16::from(object_type) << 11)
+            | (u16::from(sampling_frequency_index) << 7)
+            | (u16::from(channel_configuration) << 3);
+
+        let mut decoder_config = vec![0x40, 0x15];
+        decoder_config.extend_from_slice(&max_size.to_be_bytes()[1..]);
+        put_u32s(&mut decoder_config, &[max, average]);
+        decoder_config.extend_from_slice(&descriptor(5, &audio_config.to_be_bytes()));
+
+        let mut es = vec![0, 0, 0];
+        es.extend_from_slice(&descriptor(4, &decoder_config));
+        es.extend_from_slice(&descriptor(6, &[0x02]));
+        let esds = full_box(b"esds", 0, &descriptor(3, &es));
+
+        let channels = u16::from(channel_count(channel_configuration).unwrap_or(0));
+        // The 16.16 sample rate field cannot hold rates above 65535 Hz,
+        // decoders take the rate from the AudioSpecificConfig anyway
+        let rate = if self.sample_rate > 0xFFFF {
+            0
+        } else {
+            self.sample_rate << 16
+        };
+        let mut mp4a = vec![0; 6];
+        // data_reference_index
+        mp4a.extend_from_slice(&1u16.to_be_bytes());
+        put_u32s(&mut mp4a, &[0, 0]);
+        mp4a.extend_from_slice(&channels.to_be_bytes());
+        // Sample size, pre_defined and reserved
+        mp4a.extend_from_slice(&[0, 16, 0, 0, 0, 0]);
+        put_u32s(&mut mp4a, &[rate]);
+        mp4a.extend_from_slice(&esds);
+        boxed(b"mp4a", &mp4a)
+    }
+}
+
+fn boxed_header(kind: &[u8; 4], size: u32) -> [u8; 8] {
+    let mut header = [0; 8];
+    header[..4].copy_from_slice(&size.to_be_bytes());
+    header[4..].copy_from_slice(kind);
+    header
+}
+
+fn boxed(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+    let mut data = boxed_header(kind, 8 + content.len() as u32).to_vec();
+    data.extend_from_slice(content);
+    data
+}
+
+/// Box with version 0 and `flags`
+fn full_box(kind: &[u8; 4], flags: u32, content: &[u8]) -> Vec<u8> {
+    let mut data = flags.to_be_bytes().to_vec();
+    data.extend_from_slice(content);
+    boxed(kind, &data)
+}
+
+/// MPEG-4 descriptor with a length of a single byte, ISO 14496-1 8.3.3
+fn descriptor(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut data = vec![tag, content.len() as u8];
+    data.extend_from_slice(content);
+    data
+}
+
+fn put_u32s(data: &mut Vec<u8>, values: &[u32]) {
+    for value in values {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+/// Identity transformation matrix
+fn put_matrix(data: &mut Vec<u8>) {
+    put_u32s(
+        data,
+        &[0x0001_0000, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000],
+    );
+}
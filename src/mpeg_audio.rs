@@ -0,0 +1,289 @@
+use std::fmt;
+
+/// Bitrates in kbit/s by bitrate index for MPEG-1 Layer I, II and III
+const MPEG1_BITRATES: [[u16; 15]; 3] = [
+    [
+        0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+    ],
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+    ],
+    [
+        0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+    ],
+];
+
+/// Bitrates in kbit/s by bitrate index for MPEG-2 Layer I and Layer II/III
+const MPEG2_BITRATES: [[u16; 15]; 2] = [
+    [
+        0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+    ],
+    [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+];
+
+const MPEG1_SAMPLE_RATES: [u32; 3] = [44100, 48000, 32000];
+const MPEG2_SAMPLE_RATES: [u32; 3] = [22050, 24000, 16000];
+
+/// Parameters of an MPEG-1/2 audio header found where an ADTS header was
+/// expected
+///
+/// Both formats share the 0xFFF sync pattern (MPEG-2.5 aside), but ADTS
+/// requires the layer bits to be 0, which is reserved in MPEG audio.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct MpegAudioGuess {
+    pub mpeg1: bool,
+    pub layer: u8,
+    pub bitrate: u16,
+    pub sample_rate: u32,
+}
+
+impl fmt::Display for MpegAudioGuess {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "MPEG-{} Layer {} audio at {} kbit/s, {} Hz",
+            if self.mpeg1 { 1 } else { 2 },
+            ["I", "II", "III"][usize::from(self.layer - 1)],
+            self.bitrate,
+            self.sample_rate
+        )
+    }
+}
+
+/// Check whether a header with a 0xFFF sync reads as plausible MPEG audio
+///
+/// This is `parse_frame_header` without MPEG-2.5, whose 0xFFE sync ADTS
+/// headers never have. Free format and the reserved bitrate, sampling rate
+/// and emphasis values are not considered plausible.
+pub fn guess(header: &[u8]) -> Option<MpegAudioGuess> {
+    let header = parse_frame_header(header)?;
+    if header.version == MpegAudioVersion::Mpeg25 {
+        return None;
+    }
+    Some(MpegAudioGuess {
+        mpeg1: header.version == MpegAudioVersion::Mpeg1,
+        layer: header.layer,
+        bitrate: header.bitrate,
+        sample_rate: header.sample_rate,
+    })
+}
+
+const MPEG25_SAMPLE_RATES: [u32; 3] = [11025, 12000, 8000];
+
+/// MPEG audio version of a frame header
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MpegAudioVersion {
+    Mpeg1,
+    Mpeg2,
+    /// The unofficial extension of MPEG-2 to lower sampling rates
+    Mpeg25,
+}
+
+impl fmt::Display for MpegAudioVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MpegAudioVersion::Mpeg1 => write!(f, "MPEG-1"),
+            MpegAudioVersion::Mpeg2 => write!(f, "MPEG-2"),
+            MpegAudioVersion::Mpeg25 => write!(f, "MPEG-2.5"),
+        }
+    }
+}
+
+/// The fields of an MPEG-1/2/2.5 Layer I/II/III frame header, ISO 11172-3
+/// 2.4.2.3
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct MpegAudioHeader {
+    pub version: MpegAudioVersion,
+    pub layer: u8,
+    /// Whether a CRC follows the header
+    pub protected: bool,
+    /// Bitrate in kbit/s
+    pub bitrate: u16,
+    pub sample_rate: u32,
+    pub padding: bool,
+    /// 0 stereo, 1 joint stereo, 2 dual channel, 3 single channel
+    pub channel_mode: u8,
+}
+
+impl MpegAudioHeader {
+    /// Length of the frame in bytes, including the header
+    pub fn frame_length(&self) -> usize {
+        let bitrate = u32::from(self.bitrate) * 1000;
+        let padding = u32::from(self.padding);
+        let length = match self.layer {
+            1 => (12 * bitrate / self.sample_rate + padding) * 4,
+            3 if self.version != MpegAudioVersion::Mpeg1 => {
+                72 * bitrate / self.sample_rate + padding
+            }
+            _ => 144 * bitrate / self.sample_rate + padding,
+        };
+        length as usize
+    }
+
+    /// Number of samples per channel in the frame
+    pub fn samples(&self) -> u32 {
+        match self.layer {
+            1 => 384,
+            3 if self.version != MpegAudioVersion::Mpeg1 => 576,
+            _ => 1152,
+        }
+    }
+
+    /// Duration of the frame in seconds
+    pub fn duration(&self) -> f64 {
+        f64::from(self.samples()) / f64::from(self.sample_rate)
+    }
+
+    pub fn channel_mode_name(&self) -> &'static str {
+        match self.channel_mode {
+            0 => "stereo",
+            1 => "joint stereo",
+            2 => "dual channel",
+            _ => "mono",
+        }
+    }
+}
+
+/// Parse the MPEG audio frame header at the start of `data`
+///
+/// Free format frames are not supported, their length is only known from
+/// the position of the next header.
+pub fn parse_frame_header(data: &[u8]) -> Option<MpegAudioHeader> {
+    if data.len() < 4 || data[0] != 0xFF || data[1] & 0xE0 != 0xE0 {
+        return None;
+    }
+
+    let version = match (data[1] >> 3) & 0x03 {
+        0 => MpegAudioVersion::Mpeg25,
+        2 => MpegAudioVersion::Mpeg2,
+        3 => MpegAudioVersion::Mpeg1,
+        _ => return None,
+    };
+    let layer = match (data[1] >> 1) & 0x03 {
+        0 => return None,
+        bits => 4 - bits,
+    };
+    let bitrate_index = usize::from(data[2] >> 4);
+    let sample_rate_index = usize::from((data[2] >> 2) & 0x03);
+    if bitrate_index == 0 || bitrate_index == 15 || sample_rate_index == 3 {
+        return None;
+    }
+    // Reserved emphasis
+    if data[3] & 0x03 == 2 {
+        return None;
+    }
+
+    let layer_idx = usize::from(layer - 1);
+    let (bitrate, sample_rate) = match version {
+        MpegAudioVersion::Mpeg1 => (
+            MPEG1_BITRATES[layer_idx][bitrate_index],
+            MPEG1_SAMPLE_RATES[sample_rate_index],
+        ),
+        MpegAudioVersion::Mpeg2 => (
+            MPEG2_BITRATES[layer_idx.min(1)][bitrate_index],
+            MPEG2_SAMPLE_RATES[sample_rate_index],
+        ),
+        MpegAudioVersion::Mpeg25 => (
+            MPEG2_BITRATES[layer_idx.min(1)][bitrate_index],
+            MPEG25_SAMPLE_RATES[sample_rate_index],
+        ),
+    };
+
+    Some(MpegAudioHeader {
+        version,
+        layer,
+        protected: data[1] & 0x01 == 0,
+        bitrate,
+        sample_rate,
+        padding: data[2] & 0x02 != 0,
+        channel_mode: data[3] >> 6,
+    })
+}
+
+/// Which tag a VBR header starts with
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum VbrTag {
+    Xing,
+    /// Written like Xing, but by encoders for constant bitrate streams
+    Info,
+    Vbri,
+}
+
+impl VbrTag {
+    pub fn name(self) -> &'static str {
+        match self {
+            VbrTag::Xing => "Xing",
+            VbrTag::Info => "Info (constant bitrate)",
+            VbrTag::Vbri => "VBRI",
+        }
+    }
+}
+
+/// Stream totals an encoder stored in the first frame of a Layer III
+/// stream, in place of audio data
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct VbrHeader {
+    pub tag: VbrTag,
+    pub frames: Option<u32>,
+    pub bytes: Option<u32>,
+}
+
+/// Find a Xing, Info or VBRI header in the Layer III `frame`
+///
+/// Xing headers follow the side information, VBRI headers always start 32
+/// bytes after the frame header.
+pub fn vbr_header(frame: &[u8], header: &MpegAudioHeader) -> Option<VbrHeader> {
+    if header.layer != 3 {
+        return None;
+    }
+
+    let mono = header.channel_mode == 3;
+    let side_info = match (header.version == MpegAudioVersion::Mpeg1, mono) {
+        (true, false) => 32,
+        (true, true) | (false, false) => 17,
+        (false, true) => 9,
+    };
+    let xing = frame.get(4 + side_info..).unwrap_or(&[]);
+    let tag = if xing.starts_with(b"Xing") {
+        Some(VbrTag::Xing)
+    } else if xing.starts_with(b"Info") {
+        Some(VbrTag::Info)
+    } else {
+        None
+    };
+    if let Some(tag) = tag {
+        let flags = read_u32(xing, 4)?;
+        let mut field = 8;
+        let mut next = |present: bool| {
+            if !present {
+                return None;
+            }
+            let value = read_u32(xing, field);
+            field += 4;
+            value
+        };
+        let frames = next(flags & 0x01 != 0);
+        let bytes = next(flags & 0x02 != 0);
+        return Some(VbrHeader { tag, frames, bytes });
+    }
+
+    let vbri = frame.get(36..).unwrap_or(&[]);
+    if vbri.starts_with(b"VBRI") {
+        // Version, delay and quality come before the totals
+        return Some(VbrHeader {
+            tag: VbrTag::Vbri,
+            bytes: Some(read_u32(vbri, 10)?),
+            frames: Some(read_u32(vbri, 14)?),
+        });
+    }
+    None
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    let bytes = data.get(at..at + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
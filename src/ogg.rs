@@ -0,0 +1,192 @@
+use std::convert::TryInto;
+
+use crate::crc::crc32;
+use crate::flac;
+
+/// Capture pattern at the start of every Ogg page
+pub const OGG_CAPTURE_PATTERN: [u8; 4] = *b"OggS";
+/// Length of the page header before the lacing values
+pub const PAGE_HEADER_LEN: usize = 27;
+
+/// Offset of the CRC-32 field in the page header
+const CRC_OFFSET: usize = 22;
+
+/// The header of an Ogg page, RFC 3533 6
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct OggPage {
+    /// Whether the first packet continues one from the previous page
+    pub continued: bool,
+    /// Whether this is the first page of a logical stream
+    pub beginning_of_stream: bool,
+    /// Whether this is the last page of a logical stream
+    pub end_of_stream: bool,
+    /// Codec defined position after the last packet ending on this page,
+    /// `None` if no packet ends on it
+    pub granule_position: Option<u64>,
+    pub serial: u32,
+    pub sequence: u32,
+    pub crc: u32,
+    /// Number of bytes of each segment
+    pub lacing: Vec<u8>,
+}
+
+impl OggPage {
+    /// Length of the header including the lacing values
+    pub fn header_length(&self) -> usize {
+        PAGE_HEADER_LEN + self.lacing.len()
+    }
+
+    /// Length of the whole page
+    pub fn length(&self) -> usize {
+        self.header_length()
+            + self
+                .lacing
+                .iter()
+                .map(|len| usize::from(*len))
+                .sum::<usize>()
+    }
+
+    /// Number of packets ending on this page
+    pub fn packets(&self) -> usize {
+        self.lacing.iter().filter(|len| **len < 255).count()
+    }
+
+    /// Length of the first packet, or of its part on this page if it
+    /// continues on the next one
+    pub fn first_packet_length(&self) -> usize {
+        let end = self
+            .lacing
+            .iter()
+            .position(|len| *len < 255)
+            .map_or(self.lacing.len(), |pos| pos + 1);
+        self.lacing[..end].iter().map(|len| usize::from(*len)).sum()
+    }
+}
+
+/// Parse the Ogg page header at the start of `data`
+///
+/// Only the header has to be complete, use `crc_valid` on the whole page
+/// to check it.
+pub fn parse_page(data: &[u8]) -> Option<OggPage> {
+    let header = data.get(..PAGE_HEADER_LEN)?;
+    if header[..4] != OGG_CAPTURE_PATTERN || header[4] != 0 || header[5] & 0xF8 != 0 {
+        return None;
+    }
+    let lacing = data.get(PAGE_HEADER_LEN..PAGE_HEADER_LEN + usize::from(header[26]))?;
+    let granule_position = u64::from_le_bytes(header[6..14].try_into().ok()?);
+    Some(OggPage {
+        continued: header[5] & 0x01 != 0,
+        beginning_of_stream: header[5] & 0x02 != 0,
+        end_of_stream: header[5] & 0x04 != 0,
+        granule_position: Some(granule_position).filter(|position| *position != u64::MAX),
+        serial: u32::from_le_bytes(header[14..18].try_into().ok()?),
+        sequence: u32::from_le_bytes(header[18..22].try_into().ok()?),
+        crc: u32::from_le_bytes(header[22..26].try_into().ok()?),
+        lacing: lacing.to_vec(),
+    })
+}
+
+/// Whether the CRC-32 of a whole page matches its header
+///
+/// The CRC is computed with the CRC field set to 0, starting from 0.
+pub fn crc_valid(page: &[u8], header: &OggPage) -> bool {
+    let crc = crc32(0, &page[..CRC_OFFSET]);
+    let crc = crc32(crc, &[0; 4]);
+    crc32(crc, &page[CRC_OFFSET + 4..]) == header.crc
+}
+
+/// Codec of a logical stream, by the first packet of its first page
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum OggCodec {
+    Vorbis,
+    Opus,
+    Flac,
+    Speex,
+    Theora,
+    /// Ogg Skeleton, metadata about the other streams
+    Skeleton,
+    Unknown,
+}
+
+impl OggCodec {
+    pub fn name(self) -> &'static str {
+        match self {
+            OggCodec::Vorbis => "Vorbis",
+            OggCodec::Opus => "Opus",
+            OggCodec::Flac => "FLAC",
+            OggCodec::Speex => "Speex",
+            OggCodec::Theora => "Theora",
+            OggCodec::Skeleton => "Skeleton",
+            OggCodec::Unknown => "unknown",
+        }
+    }
+}
+
+/// What the identification header of a logical stream tells
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct StreamIdentification {
+    pub codec: OggCodec,
+    /// Units of the granule position per second, for audio codecs
+    pub granule_rate: Option<u32>,
+    /// Sampling frequency of the source, for Opus which always decodes to
+    /// 48 kHz this is the one of the input to the encoder
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u8>,
+}
+
+/// Identify the codec from the first packet of a logical stream
+pub fn identify(packet: &[u8]) -> StreamIdentification {
+    let u32_at = |pos: usize| {
+        packet
+            .get(pos..pos + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    let audio = |codec, rate: Option<u32>, channels: Option<u8>| StreamIdentification {
+        codec,
+        granule_rate: rate,
+        sample_rate: rate,
+        channels,
+    };
+
+    if packet.starts_with(b"\x01vorbis") {
+        return audio(OggCodec::Vorbis, u32_at(12), packet.get(11).copied());
+    }
+    if packet.starts_with(b"OpusHead") {
+        return StreamIdentification {
+            codec: OggCodec::Opus,
+            granule_rate: Some(48000),
+            sample_rate: u32_at(12).filter(|rate| *rate > 0),
+            channels: packet.get(9).copied(),
+        };
+    }
+    if packet.starts_with(b"\x7FFLAC") {
+        // Mapping version, header count and the fLaC marker come before
+        // the STREAMINFO metadata block
+        let info = packet.get(17..).and_then(flac::parse_stream_info);
+        return audio(
+            OggCodec::Flac,
+            info.map(|info| info.sample_rate),
+            info.map(|info| info.channels),
+        );
+    }
+    if packet.starts_with(b"Speex   ") {
+        let channels = u32_at(48).map(|channels| channels as u8);
+        return audio(OggCodec::Speex, u32_at(36), channels);
+    }
+    let codec = if packet.starts_with(b"\x80theora") {
+        OggCodec::Theora
+    } else if packet.starts_with(b"fishead\0") {
+        OggCodec::Skeleton
+    } else {
+        OggCodec::Unknown
+    };
+    StreamIdentification {
+        codec,
+        granule_rate: None,
+        sample_rate: None,
+        channels: None,
+    }
+}
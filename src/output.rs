@@ -0,0 +1,576 @@
+use std::borrow::Cow;
+
+use streamreader::adif::AdifHeader;
+use streamreader::id3::{Id3v2Frame, Id3v2Header};
+use streamreader::sniff::{Acceptance, FrameRun};
+use streamreader::ts::{AacStream, MuxStats, NULL_PID};
+use streamreader::AdtsHeader;
+
+use crate::bitrate::{self, BitrateStats};
+use crate::checkpoint::CrcCounts;
+use crate::json::{self, Object};
+use crate::junk::Junk;
+use crate::level::{self, LevelCheck};
+use crate::loops::Loop;
+use crate::marker::{self, Marker};
+use crate::references::Match;
+use crate::stats::FrameStats;
+use crate::timefmt::TimeFormat;
+use crate::trailers::Trailer;
+use crate::verdict::{Ending, Findings, Verdict};
+
+/// How the results of a scan are written to stdout
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    /// Human readable lines
+    Text,
+    /// One JSON object per line for every frame, followed by a summary object
+    Json,
+    /// A header row and one row of comma separated values per frame
+    Csv,
+}
+
+pub fn parse_format(value: &str) -> Result<Format, String> {
+    match value {
+        "text" => Ok(Format::Text),
+        "json" => Ok(Format::Json),
+        "csv" => Ok(Format::Csv),
+        _ => Err(format!("unknown format '{}', use text, json or csv", value)),
+    }
+}
+
+/// Versions of the schemas of --format json and csv this build can write
+///
+/// Within a version, fields are only ever added: JSON objects gain fields
+/// and CSV rows gain columns, so scripts should read JSON fields by name
+/// and find CSV columns by the names of the header row. Renaming or
+/// removing a field, or changing its type or meaning, starts a new version.
+/// A version stays in this list, and can be asked for with
+/// --output-version, until the next major release.
+///
+/// Changelog:
+/// - 1: the frame, summary, adif, probe, id3v2, id3v1, apev1, apev2 and mux
+///   objects, and the CSV columns of `csv_header` with the MPEG version and
+///   the optional frame ID both named id
+/// - 2: the CSV columns are named mpeg_version and frame_id, and JSON times
+///   and durations are always numbers of seconds, with --timestamp-format
+///   iso8601, hms or seconds adding the formatted time as a string in a
+///   field named like the number with _text appended; version 1 writes
+///   them as a number for unix and as the formatted string otherwise
+pub const OUTPUT_VERSIONS: [u32; 2] = [1, 2];
+/// The version written unless --output-version asks for another
+pub const OUTPUT_VERSION: u32 = OUTPUT_VERSIONS[OUTPUT_VERSIONS.len() - 1];
+
+pub fn parse_output_version(value: &str) -> Result<u32, String> {
+    let version = value
+        .parse()
+        .map_err(|_| format!("invalid output version '{}'", value))?;
+    if !OUTPUT_VERSIONS.contains(&version) {
+        let known: Vec<String> = OUTPUT_VERSIONS.iter().map(u32::to_string).collect();
+        return Err(format!(
+            "unknown output version {}, this build writes {}",
+            version,
+            known.join(", ")
+        ));
+    }
+    Ok(version)
+}
+
+/// Identifier of a frame that is the same in every output of a run, and
+/// for every run over the same input
+///
+/// Made of the input name, frame index, offset and a short payload hash.
+pub fn frame_id(input: &str, index: usize, offset: u64, payload_hash: u64) -> String {
+    format!("{}/{}@{}#{:08x}", input, index, offset, payload_hash as u32)
+}
+
+/// JSON object of the labels given with --label, by their keys
+fn labels_json(labels: &[(String, String)]) -> String {
+    labels
+        .iter()
+        .fold(Object::new(), |object, (key, value)| {
+            object.string(key, value)
+        })
+        .finish()
+}
+
+/// JSON fields of a time or duration named `name`, as `version` of the
+/// schema writes them
+fn time_fields(
+    name: &str,
+    seconds: f64,
+    time_format: &TimeFormat,
+    version: u32,
+) -> Vec<(String, String)> {
+    if version < 2 {
+        vec![(name.to_string(), time_format.format_json(seconds))]
+    } else {
+        time_format.json_fields(name, seconds)
+    }
+}
+
+/// JSON object describing the frame at `offset`, starting at `time` seconds
+pub fn frame_json(
+    offset: u64,
+    time: f64,
+    header: &AdtsHeader,
+    id: Option<&str>,
+    labels: &[(String, String)],
+    time_format: &TimeFormat,
+    output_version: u32,
+) -> String {
+    let mut object = Object::new().string("type", "frame");
+    if let Some(id) = id {
+        object = object.string("id", id);
+    }
+    if !labels.is_empty() {
+        object = object.raw("labels", &labels_json(labels));
+    }
+    object
+        .number("offset", offset)
+        .raw_fields(time_fields("time", time, time_format, output_version))
+        .number("frame_length", header.frame_length)
+        .number("bitrate", frame_bitrate(header).round())
+        .string("profile", &format!("{:?}", header.profile))
+        .number("sample_rate", header.sampling_frequency())
+        .number("channel_configuration", header.channel_configuration)
+        .raw(
+            "channels",
+            &header
+                .channels()
+                .map_or("null".to_string(), |n| n.to_string()),
+        )
+        .string("channel_layout", header.channel_layout())
+        .bool("crc_present", !header.protection_absent)
+        .number("buffer_fullness", header.adts_buffer_fullness)
+        .bool("vbr", header.is_vbr())
+        .number("raw_data_blocks", header.raw_data_blocks())
+        .finish()
+}
+
+/// Header row naming the columns written by `frame_csv` in `version` of the
+/// schema, with the frame ID column if `frame_ids` and a label_ column per
+/// label
+pub fn csv_header(version: u32, frame_ids: bool, labels: &[(String, String)]) -> String {
+    let (mpeg_version, frame_id) = if version < 2 {
+        ("id", "id")
+    } else {
+        ("mpeg_version", "frame_id")
+    };
+    let mut header = format!(
+        "offset,time,frame_length,bitrate,{},profile,sample_rate,channel_configuration,channels,\
+         channel_layout,crc_present,header_length,buffer_fullness,vbr,raw_data_blocks",
+        mpeg_version
+    );
+    if frame_ids {
+        header.push(',');
+        header.push_str(frame_id);
+    }
+    for (key, _) in labels {
+        header.push(',');
+        header.push_str(&csv_field(&format!("label_{}", key)));
+    }
+    header
+}
+
+/// `value` as a CSV field, quoted as RFC 4180 asks if it holds a comma,
+/// quote or line break
+pub fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains(&[',', '"', '\r', '\n'][..]) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// CSV row describing the frame at `offset`, starting at `time` seconds,
+/// with the frame ID if given and the values of the labels as the last
+/// columns
+pub fn frame_csv(
+    offset: u64,
+    time: f64,
+    header: &AdtsHeader,
+    id: Option<&str>,
+    labels: &[(String, String)],
+    time_format: &TimeFormat,
+) -> String {
+    let mut row = format!(
+        "{},{},{},{},{:?},{:?},{},{},{},{},{},{},{},{},{}",
+        offset,
+        csv_field(&time_format.format_plain(time)),
+        header.frame_length,
+        frame_bitrate(header).round(),
+        header.id,
+        header.profile,
+        header.sampling_frequency(),
+        header.channel_configuration,
+        header
+            .channels()
+            .map_or(String::new(), |channels| channels.to_string()),
+        csv_field(header.channel_layout()),
+        !header.protection_absent,
+        header.header_length(),
+        header.adts_buffer_fullness,
+        header.is_vbr(),
+        header.raw_data_blocks()
+    );
+    if let Some(id) = id {
+        row.push(',');
+        row.push_str(&csv_field(id));
+    }
+    for (_, value) in labels {
+        row.push(',');
+        row.push_str(&csv_field(value));
+    }
+    row
+}
+
+fn frame_bitrate(header: &AdtsHeader) -> f64 {
+    bitrate::frame_bitrate(header.frame_length, header.duration())
+}
+
+/// Everything that is only known at the end of a scan, next to the findings
+pub struct Summary<'a> {
+    pub findings: &'a Findings,
+    pub duration: f64,
+    pub loops: Option<&'a [Loop]>,
+    pub matches: Option<&'a [Match]>,
+    pub junk: &'a [Junk],
+    pub trailers: &'a [Trailer],
+    pub crc: Option<CrcCounts>,
+    pub bitrate: Option<&'a BitrateStats>,
+    pub stats: Option<&'a FrameStats>,
+    pub level: Option<&'a LevelCheck>,
+    pub markers: Option<&'a [Marker]>,
+    pub verdicts: Option<Vec<Verdict>>,
+    pub health: Option<u8>,
+    pub time_format: TimeFormat,
+    /// One of `OUTPUT_VERSIONS`
+    pub output_version: u32,
+    /// Labels given with --label
+    pub labels: &'a [(String, String)],
+}
+
+/// JSON object summarizing a whole scan
+pub fn summary_json(summary: &Summary) -> String {
+    let findings = summary.findings;
+    let time = |name: &str, seconds: f64| {
+        time_fields(name, seconds, &summary.time_format, summary.output_version)
+    };
+    let ending = match findings.ending {
+        Ending::Clean => Object::new().string("type", "clean"),
+        Ending::Truncated { offset } => Object::new()
+            .string("type", "truncated")
+            .number("offset", offset),
+        Ending::Corrupt { offset, time: at } => Object::new()
+            .string("type", "corrupt")
+            .number("offset", offset)
+            .raw_fields(time("time", at)),
+        Ending::ReadError => Object::new().string("type", "read_error"),
+    };
+    let changes = findings.parameter_changes.iter().map(|change| {
+        Object::new()
+            .number("offset", change.offset)
+            .raw_fields(time("time", change.time))
+            .string("description", &change.description)
+            .finish()
+    });
+
+    let resyncs = findings.resyncs.iter().map(|resync| {
+        Object::new()
+            .number("offset", resync.offset)
+            .raw_fields(time("time", resync.time))
+            .number("skipped", resync.skipped)
+            .finish()
+    });
+
+    let junk = summary.junk.iter().map(|found| {
+        Object::new()
+            .number("offset", found.offset)
+            .number("length", found.length)
+            .string("kind", found.kind.name())
+            .finish()
+    });
+
+    let trailers = summary.trailers.iter().map(trailer_json);
+
+    let mut object = Object::new()
+        .string("type", "summary")
+        .number("output_version", summary.output_version);
+    if !summary.labels.is_empty() {
+        object = object.raw("labels", &labels_json(summary.labels));
+    }
+    object = object
+        .number("frames", findings.frames)
+        .raw_fields(time("duration", summary.duration))
+        .raw("ending", &ending.finish())
+        .raw("parameter_changes", &json::array(changes))
+        .raw("resyncs", &json::array(resyncs))
+        .number("header_warnings", findings.header_warnings)
+        .number("mpeg_audio_frames", findings.mpeg_audio_frames)
+        .number("element_mismatches", findings.element_mismatches)
+        .raw("junk", &json::array(junk))
+        .raw("trailers", &json::array(trailers));
+    if let Some(loops) = summary.loops {
+        let loops = loops.iter().map(|found| {
+            Object::new()
+                .number("offset", found.start.offset)
+                .raw_fields(time("time", found.start.time))
+                .number("source_offset", found.source.offset)
+                .raw_fields(time("source_time", found.source.time))
+                .number("period", found.period)
+                .number("frames", found.repeated_frames())
+                .raw_fields(time("end_time", found.end.end_time()))
+                .finish()
+        });
+        object = object.raw("loops", &json::array(loops));
+    }
+    if let Some(matches) = summary.matches {
+        let matches = matches.iter().map(|found| {
+            Object::new()
+                .string("reference", &found.name)
+                .number("offset", found.start.offset)
+                .raw_fields(time("time", found.start.time))
+                .raw_fields(time("end_time", found.end.end_time()))
+                .number("frames", found.frames)
+                .number("reference_frames", found.reference_frames)
+                .number("reference_start", found.reference_start)
+                .finish()
+        });
+        object = object.raw("matches", &json::array(matches));
+    }
+    if let Some(crc) = summary.crc {
+        let crc = Object::new()
+            .number("valid", crc.valid)
+            .number("mismatches", crc.mismatches)
+            .number("unverifiable", crc.unverifiable)
+            .finish();
+        object = object.raw("crc", &crc);
+    }
+    if let Some(stats) = summary.bitrate {
+        let mut bitrate = Object::new();
+        if let (Some(average), Some((min, max))) = (stats.average(), stats.min_max()) {
+            bitrate = bitrate
+                .number("average", average.round())
+                .number("min", min.round())
+                .number("max", max.round());
+        }
+        bitrate = match stats.is_cbr() {
+            Some(cbr) => bitrate.bool("cbr", cbr),
+            None => bitrate.raw("cbr", "null"),
+        };
+        object = object.raw("bitrate", &bitrate.finish());
+    }
+    if let Some(stats) = summary.stats {
+        let sample_rates = stats.sample_rates.iter().map(|rate| rate.to_string());
+        let profiles = stats
+            .profiles
+            .iter()
+            .map(|profile| json::string(&format!("{:?}", profile)));
+        let mut object_stats = Object::new()
+            .number("total_bytes", stats.total_bytes)
+            .number("payload_bytes", stats.payload_bytes);
+        if let Some(average) = stats.average_length() {
+            object_stats = object_stats
+                .number("average_frame_length", average)
+                .number("min_frame_length", stats.min_length)
+                .number("max_frame_length", stats.max_length);
+        }
+        let junk_bytes: u64 = summary.junk.iter().map(|found| found.length).sum();
+        let object_stats = object_stats
+            .raw("sample_rates", &json::array(sample_rates))
+            .raw("profiles", &json::array(profiles))
+            .number("junk_bytes", junk_bytes)
+            .finish();
+        object = object.raw("stats", &object_stats);
+    }
+    if let Some(levels) = summary.level {
+        let level = match levels.level() {
+            Ok(level) => Object::new()
+                .number("level", level)
+                .number("channels", levels.channels())
+                .number("sample_rate", levels.sample_rate())
+                .number("peak_bitrate", levels.peak_bitrate().round())
+                .number(
+                    "max_bitrate",
+                    level::max_bitrate(levels.channels(), levels.sample_rate()).round(),
+                ),
+            Err(reason) => Object::new().raw("level", "null").string("reason", &reason),
+        };
+        object = object.raw("level", &level.finish());
+    }
+    if let Some(markers) = summary.markers {
+        let found = markers.iter().map(|found| {
+            Object::new()
+                .number("offset", found.offset)
+                .raw_fields(time("time", found.time))
+                .raw_fields(time("latency", found.latency))
+                .finish()
+        });
+        let mut latency = Object::new().raw("markers", &json::array(found));
+        if let Some((min, mean, max)) = marker::latency_range(markers) {
+            latency = latency
+                .raw_fields(time("min", min))
+                .raw_fields(time("mean", mean))
+                .raw_fields(time("max", max));
+        }
+        object = object.raw("latency", &latency.finish());
+    }
+    if let Some(verdicts) = summary.verdicts.as_ref() {
+        let verdicts = verdicts.iter().map(|verdict| {
+            let evidence = verdict.evidence.iter().map(|line| json::string(line));
+            Object::new()
+                .string("classification", &verdict.classification)
+                .raw("evidence", &json::array(evidence))
+                .finish()
+        });
+        object = object.raw("verdicts", &json::array(verdicts));
+    }
+    if let Some(health) = summary.health {
+        object = object.number("health", health);
+    }
+    object.finish()
+}
+
+/// JSON object describing the header of ADIF input
+pub fn adif_json(header: &AdifHeader, offset: u64) -> String {
+    let programs = header.programs.iter().map(|program| {
+        let config = &program.config;
+        let mut object = Object::new()
+            .string("profile", &format!("{:?}", config.profile()))
+            .number("sampling_frequency_index", config.sampling_frequency_index)
+            .number("channels", config.channels())
+            .number("lfe", config.lfe)
+            .string("comment", &String::from_utf8_lossy(&config.comment));
+        if let Some(fullness) = program.buffer_fullness {
+            object = object.number("buffer_fullness", fullness);
+        }
+        object.finish()
+    });
+    Object::new()
+        .string("type", "adif")
+        .number("offset", offset)
+        .bool("variable_bitrate", header.variable_bitrate)
+        .number("bitrate", header.bitrate)
+        .raw("programs", &json::array(programs))
+        .number("raw_data_offset", offset + header.header_length as u64)
+        .finish()
+}
+
+/// JSON object listing the framings found by a probe and their runs of
+/// frames, with byte ranges in the input
+pub fn probe_json(probes: &[(Acceptance, Vec<FrameRun>)], offset: u64) -> String {
+    let formats = probes.iter().map(|(acceptance, runs)| {
+        let runs = runs.iter().map(|run| {
+            Object::new()
+                .number("start", offset + run.start as u64)
+                .number("end", offset + run.end as u64)
+                .number("frames", run.frames)
+                .finish()
+        });
+        let mut object = Object::new()
+            .string("format", acceptance.format.name())
+            .number("frames", acceptance.frames)
+            .number("covered", acceptance.covered)
+            .raw("confidence", &format!("{:.4}", acceptance.confidence));
+        if let Some(parameters) = acceptance.parameters {
+            object = object.number("sample_rate", parameters.sample_rate);
+            if let Some(channels) = parameters.channels {
+                object = object.number("channels", channels);
+            }
+        }
+        object.raw("runs", &json::array(runs)).finish()
+    });
+    Object::new()
+        .string("type", "probe")
+        .raw("formats", &json::array(formats))
+        .finish()
+}
+
+/// JSON object describing an ID3v2 tag, with its frames if they were read
+pub fn id3_json(tag: &Id3v2Header, frames: Option<&[Id3v2Frame]>, offset: u64) -> String {
+    let mut object = Object::new()
+        .string("type", "id3v2")
+        .number("offset", offset)
+        .string(
+            "version",
+            &format!("2.{}.{}", tag.major_version, tag.revision),
+        )
+        .number("length", tag.total_length());
+    if let Some(frames) = frames {
+        let frames = frames.iter().map(|frame| {
+            let mut object = Object::new()
+                .string("id", &frame.id)
+                .number("length", frame.length);
+            if let Some(text) = frame.text.as_ref() {
+                object = object.string("text", text);
+            }
+            object.finish()
+        });
+        object = object.raw("frames", &json::array(frames));
+    }
+    object.finish()
+}
+
+/// JSON object with the mux rate of a transport stream and the share of
+/// each PID
+pub fn mux_json(mux: &MuxStats, stream: &AacStream) -> String {
+    let pids = mux.packets.iter().map(|(pid, packets)| {
+        let mut object = Object::new()
+            .number("pid", *pid)
+            .number("packets", *packets)
+            .number("share", mux.share(*pid));
+        if let Some(bitrate) = mux.bitrate(*pid) {
+            object = object.number("bitrate", bitrate.round());
+        }
+        object.finish()
+    });
+    let mut object = Object::new()
+        .string("type", "mux")
+        .number("pcr_pid", stream.pcr_pid);
+    if let Some(rate) = mux.mux_rate() {
+        object = object.number("mux_rate", rate.round());
+    }
+    object
+        .number("padding_share", mux.share(NULL_PID))
+        .number("audio_share", mux.share(stream.pid))
+        .raw("pids", &json::array(pids))
+        .finish()
+}
+
+/// JSON object of a tag after the last frame
+fn trailer_json(trailer: &Trailer) -> String {
+    let object = Object::new()
+        .string("type", trailer.name())
+        .number("offset", trailer.offset())
+        .number("length", trailer.length());
+    match trailer {
+        Trailer::Id3v1 { tag, .. } => {
+            let mut object = object
+                .string("title", &tag.title)
+                .string("artist", &tag.artist)
+                .string("album", &tag.album)
+                .string("year", &tag.year)
+                .string("comment", &tag.comment);
+            if let Some(track) = tag.track {
+                object = object.number("track", track);
+            }
+            if let Some(genre) = tag.genre {
+                object = object.number("genre", genre);
+            }
+            object.finish()
+        }
+        Trailer::Ape { items, .. } => {
+            let items = items.iter().map(|item| {
+                let mut object = Object::new()
+                    .string("key", &item.key)
+                    .number("length", item.length);
+                if let Some(text) = item.text.as_ref() {
+                    object = object.string("text", text);
+                }
+                object.finish()
+            });
+            object.raw("items", &json::array(items)).finish()
+        }
+    }
+}
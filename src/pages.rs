@@ -0,0 +1,161 @@
+use streamreader::ogg::{self, StreamIdentification};
+
+use crate::walk::Frame;
+
+/// What is known about a logical stream from its pages so far
+struct LogicalStream {
+    serial: u32,
+    /// `None` if the input starts after its first page
+    identification: Option<StreamIdentification>,
+    /// Sequence number of the last page
+    sequence: u32,
+    /// Description of a sequence gap before the last page
+    gap: Option<String>,
+    /// Last granule position before and after the last page
+    granule_before: Option<u64>,
+    granule_after: Option<u64>,
+    ended: bool,
+}
+
+/// Parser for Ogg pages, tracking every logical stream
+///
+/// Pages of several logical streams can be interleaved, and streams can be
+/// chained one after the other. The duration is counted from the granule
+/// positions of one audio stream at a time, the first one begun while no
+/// other is timed.
+///
+/// Parsing the same page again, as `walk` does to confirm a sync, gives the
+/// same result.
+pub fn parser() -> impl FnMut(&[u8]) -> Option<Frame> {
+    let mut streams: Vec<LogicalStream> = Vec::new();
+    // Serial of the stream the duration is counted from
+    let mut timing: Option<u32> = None;
+    move |data| {
+        let page = ogg::parse_page(data)?;
+        let length = page.length();
+        let mut lines = vec![
+            format!("Len is {}", length),
+            format!("Stream serial is 0x{:08X}", page.serial),
+        ];
+        let mut warnings = Vec::new();
+
+        let index = match streams.iter().position(|s| s.serial == page.serial) {
+            Some(index) => index,
+            None => {
+                streams.push(LogicalStream {
+                    serial: page.serial,
+                    identification: None,
+                    sequence: page.sequence,
+                    gap: None,
+                    granule_before: None,
+                    granule_after: page.granule_position,
+                    ended: false,
+                });
+                streams.len() - 1
+            }
+        };
+        let stream = &mut streams[index];
+        if stream.sequence != page.sequence {
+            let expected = stream.sequence.wrapping_add(1);
+            stream.gap = match page.sequence {
+                sequence if sequence == expected => None,
+                sequence if sequence > expected => {
+                    Some(format!("{} pages missing before it", sequence - expected))
+                }
+                _ => Some(format!("out of order, {} was expected", expected)),
+            };
+            stream.sequence = page.sequence;
+            stream.granule_before = stream.granule_after;
+            stream.granule_after = page.granule_position.or(stream.granule_after);
+        }
+        match stream.gap.as_ref() {
+            Some(gap) => {
+                lines.push(format!("Page sequence is {}, {}", page.sequence, gap));
+                warnings.push((
+                    "sequence",
+                    format!(
+                        "page {} of stream 0x{:08X}: {}",
+                        page.sequence, page.serial, gap
+                    ),
+                ));
+            }
+            None => lines.push(format!("Page sequence is {}", page.sequence)),
+        }
+
+        let mut flags = Vec::new();
+        if page.beginning_of_stream {
+            flags.push("beginning of stream");
+        }
+        if page.continued {
+            flags.push("continued packet");
+        }
+        if page.end_of_stream {
+            flags.push("end of stream");
+        }
+        if !flags.is_empty() {
+            lines.push(format!("Flags: {}", flags.join(", ")));
+        }
+
+        if page.beginning_of_stream {
+            let start = page.header_length();
+            let end = (start + page.first_packet_length()).min(data.len());
+            let identification = ogg::identify(data.get(start..end).unwrap_or(&[]));
+            let mut codec = format!("Codec is {}", identification.codec.name());
+            if let Some(rate) = identification.sample_rate {
+                codec.push_str(&format!(", {} Hz", rate));
+            }
+            if let Some(channels) = identification.channels {
+                codec.push_str(&format!(", {} channels", channels));
+            }
+            lines.push(codec);
+            stream.identification = Some(identification);
+            stream.ended = false;
+
+            let timed = timing.and_then(|serial| streams.iter().find(|s| s.serial == serial));
+            if identification.granule_rate.is_some() && timed.is_none_or(|s| s.ended) {
+                timing = Some(page.serial);
+            }
+        }
+        let stream = &mut streams[index];
+        if page.end_of_stream {
+            stream.ended = true;
+        }
+
+        match page.granule_position {
+            Some(position) => lines.push(format!("Granule position is {}", position)),
+            None => lines.push("Granule position is unset, no packet ends here".to_string()),
+        }
+        lines.push(format!("Packets ending here: {}", page.packets()));
+        if let Some(page_data) = data.get(..length) {
+            if ogg::crc_valid(page_data, &page) {
+                lines.push("CRC-32 is valid".to_string());
+            } else {
+                lines.push("CRC-32 is mismatch".to_string());
+                warnings.push((
+                    "crc",
+                    format!(
+                        "CRC-32 mismatch in page {} of stream 0x{:08X}",
+                        page.sequence, page.serial
+                    ),
+                ));
+            }
+        }
+
+        let rate = stream
+            .identification
+            .and_then(|identification| identification.granule_rate);
+        let duration = match (rate, stream.granule_before, stream.granule_after) {
+            (Some(rate), Some(before), Some(after)) if timing == Some(page.serial) => {
+                after.saturating_sub(before) as f64 / f64::from(rate)
+            }
+            _ => 0.0,
+        };
+        Some(Frame {
+            length,
+            duration,
+            lines,
+            warnings,
+            advertised: None,
+        })
+    }
+}
@@ -0,0 +1,115 @@
+use std::io::Read;
+
+use streamreader::ts::{self, AudioDescription, TsDemuxer, PTS_FREQUENCY};
+use streamreader::{AdtsError, AdtsFrames, MPEGAudioObjectType};
+
+/// The stream parameters that have to fit between main audio and its
+/// description
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Parameters {
+    pub profile: MPEGAudioObjectType,
+    pub sample_rate: u32,
+    pub channel_configuration: u8,
+}
+
+/// One demuxed stream of an audio description pair
+pub struct PairedStream {
+    pub pid: u16,
+    /// Parameters of the first frame
+    pub parameters: Option<Parameters>,
+    pub frames: u64,
+    /// Seconds of audio
+    pub duration: f64,
+    /// First presentation time stamp, in 90 kHz ticks
+    pub timestamp: Option<u64>,
+    /// The broken frame the stream was read up to
+    pub error: Option<AdtsError>,
+}
+
+impl PairedStream {
+    /// Read the ADTS frames of the stream up to the end or the first broken
+    /// frame
+    pub fn read<R: Read>(mut demuxer: TsDemuxer<R>) -> PairedStream {
+        let mut stream = PairedStream {
+            pid: demuxer.stream().pid,
+            parameters: None,
+            frames: 0,
+            duration: 0.0,
+            timestamp: None,
+            error: None,
+        };
+        for frame in AdtsFrames::new(&mut demuxer) {
+            let header = match frame {
+                Ok(frame) => frame.header,
+                Err(err) => {
+                    stream.error = Some(err);
+                    break;
+                }
+            };
+            stream.parameters.get_or_insert(Parameters {
+                profile: header.profile,
+                sample_rate: header.sampling_frequency(),
+                channel_configuration: header.channel_configuration,
+            });
+            stream.frames += 1;
+            stream.duration += header.duration();
+        }
+        stream.timestamp = demuxer.first_timestamp();
+        stream
+    }
+}
+
+/// Reasons the description cannot be played with the main audio as its
+/// mix needs
+pub fn mismatches(
+    main: &Parameters,
+    description: &Parameters,
+    mix: AudioDescription,
+) -> Vec<String> {
+    let mut found = Vec::new();
+    if main.sample_rate != description.sample_rate {
+        found.push(format!(
+            "sample rates differ, {} Hz and {} Hz",
+            main.sample_rate, description.sample_rate
+        ));
+    }
+    match mix {
+        // Receivers switch over to it, so it has to fit the same output
+        AudioDescription::BroadcastMix => {
+            if main.channel_configuration != description.channel_configuration {
+                found.push(format!(
+                    "the broadcast mix has channel configuration {}, the main audio {}",
+                    description.channel_configuration, main.channel_configuration
+                ));
+            }
+            if main.profile != description.profile {
+                found.push(format!(
+                    "the broadcast mix is {:?}, the main audio {:?}",
+                    description.profile, main.profile
+                ));
+            }
+        }
+        AudioDescription::ReceiverMix => {
+            let channels = |parameters: &Parameters| {
+                streamreader::channel_count(parameters.channel_configuration)
+            };
+            if let (Some(main), Some(description)) = (channels(main), channels(description)) {
+                if description > main {
+                    found.push(format!(
+                        "the receiver mix has {} channels, more than the {} of the main audio",
+                        description, main
+                    ));
+                }
+            }
+        }
+        _ => {}
+    }
+    found
+}
+
+/// Seconds the description starts after the main audio, by their first
+/// time stamps
+pub fn start_drift(main: &PairedStream, description: &PairedStream) -> Option<f64> {
+    let ticks = ts::timestamp_difference(main.timestamp?, description.timestamp?);
+    Some(ticks as f64 / PTS_FREQUENCY as f64)
+}
@@ -0,0 +1,119 @@
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+
+use crate::header::{parse_header, AdtsError, AdtsHeader, ADTS_HDR_MAX_LEN, ADTS_HDR_MIN_LEN};
+
+fn find_startcode(buf: [u8; ADTS_HDR_MAX_LEN]) -> Option<usize> {
+    buf.windows(2)
+        .position(|b| (b[0] == 0xFF) && ((b[1] & 0xF0) == 0xF0))
+}
+
+/// Streaming ADTS parser reading frame by frame from any seekable source
+///
+/// The parser keeps no state besides the position of its reader, so the
+/// reader can also be used directly between calls. Passing `&fs::File`
+/// allows that while the parser exists.
+pub struct AdtsParser<R> {
+    reader: R,
+}
+
+impl<R: Read + Seek> AdtsParser<R> {
+    pub fn new(reader: R) -> AdtsParser<R> {
+        AdtsParser { reader }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Seek to the next ADTS syncword, returning its offset
+    pub fn seek_startcode(&mut self) -> io::Result<u64> {
+        let mut buffer = [0; ADTS_HDR_MAX_LEN];
+
+        loop {
+            self.reader.read_exact(&mut buffer)?;
+            let startcode_pos = match find_startcode(buffer) {
+                Some(n) => n,
+                None => {
+                    // Keep the last byte, it may start a syncword
+                    self.reader.seek(SeekFrom::Current(-1))?;
+                    continue;
+                }
+            };
+
+            // Seek back to header start
+            let offset = -(ADTS_HDR_MAX_LEN as i64 - startcode_pos as i64);
+            return self.reader.seek(SeekFrom::Current(offset));
+        }
+    }
+
+    /// Seek to the next valid header that is followed by `confirm - 1` more
+    /// headers of the same stream, or by the end of the input, returning its
+    /// offset
+    pub fn seek_sync(&mut self, confirm: usize) -> io::Result<u64> {
+        loop {
+            let start = self.seek_startcode()?;
+            let confirmed = self.confirmed(start, confirm)?;
+            let next = if confirmed { start } else { start + 1 };
+            self.reader.seek(SeekFrom::Start(next))?;
+            if confirmed {
+                return Ok(start);
+            }
+        }
+    }
+
+    fn confirmed(&mut self, start: u64, confirm: usize) -> io::Result<bool> {
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        let mut first: Option<AdtsHeader> = None;
+        let mut offset = start;
+
+        for _ in 0..confirm {
+            if offset == end {
+                return Ok(true);
+            }
+            self.reader.seek(SeekFrom::Start(offset))?;
+            let header = match self.peek_header() {
+                Ok(header) => header,
+                Err(AdtsError::Io(err)) if err.kind() != ErrorKind::UnexpectedEof => {
+                    return Err(err)
+                }
+                Err(_) => return Ok(false),
+            };
+            if first
+                .as_ref()
+                .is_some_and(|first| !first.same_stream(&header))
+            {
+                return Ok(false);
+            }
+            offset += u64::from(header.frame_length);
+            first.get_or_insert(header);
+        }
+        Ok(true)
+    }
+
+    /// Parse the header at the current position without consuming it
+    pub fn peek_header(&mut self) -> Result<AdtsHeader, AdtsError> {
+        let mut buffer = [0; ADTS_HDR_MIN_LEN];
+
+        let offset = self.reader.stream_position()?;
+        self.reader.read_exact(&mut buffer)?;
+        let header = parse_header(&buffer, offset)?;
+        self.reader
+            .seek(SeekFrom::Current(-(ADTS_HDR_MIN_LEN as i64)))?;
+
+        Ok(header)
+    }
+
+    /// Read the whole frame starting at the current position into `frame`
+    pub fn read_frame(&mut self, header: &AdtsHeader, frame: &mut Vec<u8>) -> io::Result<()> {
+        frame.resize(usize::from(header.frame_length), 0);
+        self.reader.read_exact(frame)
+    }
+}
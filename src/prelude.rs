@@ -0,0 +1,7 @@
+//! The types most users of the crate need, for `use streamreader::prelude::*`
+
+pub use crate::sniff::{sniff, Acceptance, SniffFormat};
+pub use crate::{
+    parse_header, AdtsError, AdtsFrame, AdtsFrames, AdtsHeader, AdtsParser, AdtsReader,
+    MPEGAudioObjectType, MPEGVersion,
+};
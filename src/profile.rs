@@ -0,0 +1,90 @@
+use std::time::{Duration, Instant};
+
+/// Stages of a scan run that are accounted for separately
+#[derive(Debug, Clone, Copy)]
+pub enum Stage {
+    Io,
+    SyncSearch,
+    HeaderDecode,
+    Hashing,
+    Output,
+}
+
+const STAGES: [Stage; 5] = [
+    Stage::Io,
+    Stage::SyncSearch,
+    Stage::HeaderDecode,
+    Stage::Hashing,
+    Stage::Output,
+];
+
+impl Stage {
+    fn name(self) -> &'static str {
+        match self {
+            Stage::Io => "I/O",
+            Stage::SyncSearch => "sync search",
+            Stage::HeaderDecode => "header decode",
+            Stage::Hashing => "hashing",
+            Stage::Output => "output formatting",
+        }
+    }
+}
+
+/// Accumulates wall-clock time spent per stage over a whole run
+pub struct Profile {
+    enabled: bool,
+    started: Instant,
+    spent: [Duration; STAGES.len()],
+}
+
+impl Profile {
+    pub fn new(enabled: bool) -> Profile {
+        Profile {
+            enabled,
+            started: Instant::now(),
+            spent: [Duration::default(); STAGES.len()],
+        }
+    }
+
+    /// Run `f`, attributing the time it takes to `stage`
+    pub fn measure<T, F: FnOnce() -> T>(&mut self, stage: Stage, f: F) -> T {
+        if !self.enabled {
+            return f();
+        }
+
+        let start = Instant::now();
+        let result = f();
+        self.spent[stage as usize] += start.elapsed();
+        result
+    }
+
+    /// Print the per-stage breakdown to stderr, if profiling is enabled
+    pub fn report(&self) {
+        if !self.enabled {
+            return;
+        }
+
+        let total = self.started.elapsed();
+        let accounted: Duration = self.spent.iter().sum();
+        let other = total.checked_sub(accounted).unwrap_or_default();
+
+        eprintln!("Self-profile ({:.3} ms total):", millis(total));
+        for stage in STAGES.iter() {
+            print_row(stage.name(), self.spent[*stage as usize], total);
+        }
+        print_row("other", other, total);
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn print_row(name: &str, spent: Duration, total: Duration) {
+    let share = if total.as_nanos() == 0 {
+        0.0
+    } else {
+        spent.as_secs_f64() / total.as_secs_f64() * 100.0
+    };
+    eprintln!("  {:<18} {:>12.3} ms {:>6.1}%", name, millis(spent), share);
+}
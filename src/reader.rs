@@ -0,0 +1,290 @@
+use std::io::{self, ErrorKind, Read};
+
+use crate::header::{parse_header, AdtsError, AdtsHeader, ADTS_HDR_MIN_LEN};
+
+/// Streaming ADTS parser for sources that cannot seek, like pipes
+///
+/// Only the bytes of the current header are buffered, and the frames looked
+/// at while confirming a sync; payloads are read straight into the caller's
+/// buffer or discarded.
+pub struct AdtsReader<R> {
+    reader: R,
+    /// Offset of the first byte in `lookahead`
+    position: u64,
+    /// Bytes read from the source but not consumed yet
+    lookahead: Vec<u8>,
+    /// Set once the source returned end of file
+    eof: bool,
+    /// Latest bytes consumed by the last sync search, with `keep_skipped`
+    skipped: Vec<u8>,
+    /// How many of the bytes consumed by a sync search to keep at least
+    skipped_limit: usize,
+}
+
+impl<R: Read> AdtsReader<R> {
+    pub fn new(reader: R) -> AdtsReader<R> {
+        AdtsReader {
+            reader,
+            position: 0,
+            lookahead: Vec::with_capacity(ADTS_HDR_MIN_LEN),
+            eof: false,
+            skipped: Vec::new(),
+            skipped_limit: 0,
+        }
+    }
+
+    /// Keep at least the last `limit` bytes each sync search consumes, so
+    /// data skipped up to the end of the input can still be looked at
+    pub fn keep_skipped(&mut self, limit: usize) {
+        self.skipped_limit = limit;
+    }
+
+    /// The latest bytes the last call to `seek_startcode` or `seek_sync`
+    /// consumed, as many as `keep_skipped` asked for or more
+    pub fn skipped(&self) -> &[u8] {
+        &self.skipped
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Offset of the next byte that was not consumed yet
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Offset of the end of the input, once it was reached
+    pub fn end(&self) -> Option<u64> {
+        if self.eof {
+            Some(self.position + self.lookahead.len() as u64)
+        } else {
+            None
+        }
+    }
+
+    /// Buffer at least `len` bytes, failing if the input ends before
+    fn fill(&mut self, len: usize) -> io::Result<()> {
+        let mut chunk = [0; ADTS_HDR_MIN_LEN];
+        while self.lookahead.len() < len {
+            let wanted = (len - self.lookahead.len()).min(chunk.len());
+            match self.reader.read(&mut chunk[..wanted]) {
+                Ok(0) => {
+                    self.eof = true;
+                    return Err(io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "failed to fill whole buffer",
+                    ));
+                }
+                Ok(read) => self.lookahead.extend_from_slice(&chunk[..read]),
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Up to `len` bytes from the current position without consuming them,
+    /// fewer only if the input ends before
+    pub fn peek(&mut self, len: usize) -> io::Result<&[u8]> {
+        match self.fill(len) {
+            Ok(()) => {}
+            Err(ref err) if err.kind() == ErrorKind::UnexpectedEof => {}
+            Err(err) => return Err(err),
+        }
+        Ok(&self.lookahead[..len.min(self.lookahead.len())])
+    }
+
+    /// Consume `len` bytes without keeping them
+    pub fn skip(&mut self, len: u64) -> io::Result<()> {
+        let buffered = (self.lookahead.len() as u64).min(len);
+        self.lookahead.drain(..buffered as usize);
+        self.position += buffered;
+
+        let rest = len - buffered;
+        let skipped = io::copy(&mut (&mut self.reader).take(rest), &mut io::sink())?;
+        self.position += skipped;
+        if skipped < rest {
+            self.eof = true;
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "input ended while skipping",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Consume bytes up to the next ADTS syncword, returning its offset
+    pub fn seek_startcode(&mut self) -> io::Result<u64> {
+        self.skipped.clear();
+        self.find_startcode()
+    }
+
+    fn find_startcode(&mut self) -> io::Result<u64> {
+        loop {
+            self.fill(2)?;
+            if self.lookahead[0] == 0xFF && self.lookahead[1] & 0xF0 == 0xF0 {
+                return Ok(self.position);
+            }
+            self.drop_byte();
+        }
+    }
+
+    /// Consume the first buffered byte, keeping it if asked to
+    fn drop_byte(&mut self) {
+        let byte = self.lookahead.remove(0);
+        self.position += 1;
+        if self.skipped_limit > 0 {
+            if self.skipped.len() >= 2 * self.skipped_limit {
+                self.skipped.drain(..self.skipped_limit);
+            }
+            self.skipped.push(byte);
+        }
+    }
+
+    /// Consume bytes up to the next valid header that is followed by
+    /// `confirm - 1` more headers of the same stream, or by the end of the
+    /// input, returning its offset
+    pub fn seek_sync(&mut self, confirm: usize) -> io::Result<u64> {
+        self.skipped.clear();
+        loop {
+            let start = self.find_startcode()?;
+            if self.confirmed(confirm)? {
+                return Ok(start);
+            }
+            self.drop_byte();
+        }
+    }
+
+    fn confirmed(&mut self, confirm: usize) -> io::Result<bool> {
+        let mut first: Option<AdtsHeader> = None;
+        let mut start = 0;
+
+        for _ in 0..confirm {
+            match self.fill(start + ADTS_HDR_MIN_LEN) {
+                Ok(()) => {}
+                Err(ref err) if err.kind() == ErrorKind::UnexpectedEof => {
+                    return Ok(first.is_some() && self.lookahead.len() == start)
+                }
+                Err(err) => return Err(err),
+            }
+            let mut buffer = [0; ADTS_HDR_MIN_LEN];
+            buffer.copy_from_slice(&self.lookahead[start..start + ADTS_HDR_MIN_LEN]);
+            let header = match parse_header(&buffer, self.position + start as u64) {
+                Ok(header) => header,
+                Err(_) => return Ok(false),
+            };
+            if first
+                .as_ref()
+                .is_some_and(|first| !first.same_stream(&header))
+            {
+                return Ok(false);
+            }
+            start += usize::from(header.frame_length);
+            first.get_or_insert(header);
+        }
+        Ok(true)
+    }
+
+    /// Parse the header at the current position without consuming it
+    pub fn peek_header(&mut self) -> Result<AdtsHeader, AdtsError> {
+        self.fill(ADTS_HDR_MIN_LEN)?;
+        let mut buffer = [0; ADTS_HDR_MIN_LEN];
+        buffer.copy_from_slice(&self.lookahead[..ADTS_HDR_MIN_LEN]);
+        parse_header(&buffer, self.position)
+    }
+
+    /// Consume the whole frame starting at the current position into `frame`
+    pub fn read_frame(&mut self, header: &AdtsHeader, frame: &mut Vec<u8>) -> io::Result<()> {
+        let len = usize::from(header.frame_length);
+        frame.resize(len, 0);
+        let buffered = self.lookahead.len().min(len);
+        frame[..buffered].copy_from_slice(&self.lookahead[..buffered]);
+        self.lookahead.drain(..buffered);
+        self.position += buffered as u64;
+
+        match self.reader.read_exact(&mut frame[buffered..]) {
+            Ok(()) => {
+                self.position += (len - buffered) as u64;
+                Ok(())
+            }
+            Err(err) => {
+                if err.kind() == ErrorKind::UnexpectedEof {
+                    self.eof = true;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Consume the whole frame starting at the current position
+    pub fn skip_frame(&mut self, header: &AdtsHeader) -> io::Result<()> {
+        self.skip(u64::from(header.frame_length))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::tests::adts_frame;
+
+    /// A stream of `count` frames of `length` bytes after `junk`
+    fn stream(junk: &[u8], count: usize, length: u16) -> Vec<u8> {
+        let mut data = junk.to_vec();
+        for _ in 0..count {
+            data.extend(adts_frame(length, false));
+        }
+        data
+    }
+
+    #[test]
+    fn resyncs_past_a_false_syncword() {
+        // A syncword followed by a header that is not one of the stream
+        let mut junk = adts_frame(40, false);
+        junk.truncate(20);
+        let data = stream(&junk, 3, 100);
+        let mut reader = AdtsReader::new(&data[..]);
+        reader.keep_skipped(64);
+        assert_eq!(reader.seek_startcode().unwrap(), 0);
+        assert_eq!(reader.seek_sync(3).unwrap(), 20);
+        assert_eq!(reader.skipped(), &junk[..]);
+
+        let header = reader.peek_header().unwrap();
+        assert_eq!(header.frame_length, 100);
+        reader.skip_frame(&header).unwrap();
+        assert_eq!(reader.position(), 120);
+    }
+
+    #[test]
+    fn the_end_of_the_input_confirms_a_sync() {
+        let data = stream(&[0x00, 0x00], 1, 100);
+        let mut reader = AdtsReader::new(&data[..]);
+        assert_eq!(reader.seek_sync(3).unwrap(), 2);
+
+        let mut frame = Vec::new();
+        let header = reader.peek_header().unwrap();
+        reader.read_frame(&header, &mut frame).unwrap();
+        assert_eq!(frame, &data[2..]);
+        assert!(reader.peek(1).unwrap().is_empty());
+        assert_eq!(reader.end(), Some(102));
+    }
+
+    #[test]
+    fn sync_search_fails_at_the_end() {
+        let mut data = stream(&[], 1, 100);
+        data.truncate(50);
+        let mut reader = AdtsReader::new(&data[..]);
+        let err = reader.seek_sync(3).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn skipped_bytes_are_limited() {
+        let data = stream(&[0x00; 1000], 2, 100);
+        let mut reader = AdtsReader::new(&data[..]);
+        reader.keep_skipped(100);
+        assert_eq!(reader.seek_startcode().unwrap(), 1000);
+        let skipped = reader.skipped();
+        assert!(skipped.len() >= 100 && skipped.len() <= 200);
+    }
+}
@@ -0,0 +1,117 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// A misalignment of a capture that can be undone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    /// The bytes of each 16 bit word are swapped
+    pub byte_swap: bool,
+    /// The stream starts this many bits into the capture (0–7)
+    pub bit_shift: u8,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        byte_swap: false,
+        bit_shift: 0,
+    };
+
+    /// All transforms that are tried, starting with the identity
+    pub fn all() -> impl Iterator<Item = Transform> {
+        [false, true].iter().flat_map(|&byte_swap| {
+            (0..8).map(move |bit_shift| Transform {
+                byte_swap,
+                bit_shift,
+            })
+        })
+    }
+
+    /// Undo the misalignment of `data`
+    ///
+    /// `data` must start on a 16 bit word of the capture. The last byte is
+    /// dropped when shifting, as only part of it belongs to the stream.
+    pub fn apply(self, data: &[u8]) -> Vec<u8> {
+        let mut data = data.to_vec();
+        if self.byte_swap {
+            swap_bytes(&mut data);
+        }
+        if self.bit_shift == 0 {
+            return data;
+        }
+        shift_bits(&data, self.bit_shift)
+    }
+}
+
+fn swap_bytes(data: &mut [u8]) {
+    for word in data.chunks_exact_mut(2) {
+        word.swap(0, 1);
+    }
+}
+
+/// Drop the first `bit_shift` bits of `data`, and the last partial byte
+fn shift_bits(data: &[u8], bit_shift: u8) -> Vec<u8> {
+    data.windows(2)
+        .map(|pair| (pair[0] << bit_shift) | (pair[1] >> (8 - bit_shift)))
+        .collect()
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.byte_swap, self.bit_shift) {
+            (false, 0) => write!(f, "aligned"),
+            (true, 0) => write!(f, "byte-swapped"),
+            (false, shift) => write!(f, "shifted by {} bits", shift),
+            (true, shift) => write!(f, "byte-swapped and shifted by {} bits", shift),
+        }
+    }
+}
+
+/// Bytes processed at once by `write_corrected`, must be even
+const CHUNK: usize = 64 * 1024;
+
+/// Write a copy of `input` with `transform` undone
+pub fn write_corrected<R: Read, W: Write>(
+    mut input: R,
+    mut out: W,
+    transform: Transform,
+) -> io::Result<()> {
+    let mut buffer = vec![0; CHUNK];
+    // Last byte of the previous chunk, which shifting needs for the first
+    // output byte of the next one
+    let mut carry = None;
+    loop {
+        let read = read_full(&mut input, &mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        let chunk = &mut buffer[..read];
+        if transform.byte_swap {
+            swap_bytes(chunk);
+        }
+        if transform.bit_shift == 0 {
+            out.write_all(chunk)?;
+            continue;
+        }
+
+        let mut data = Vec::with_capacity(read + 1);
+        data.extend(carry);
+        data.extend_from_slice(chunk);
+        out.write_all(&shift_bits(&data, transform.bit_shift))?;
+        carry = data.last().copied();
+    }
+    out.flush()
+}
+
+/// Fill `buffer` as far as the input allows, returning the bytes read
+fn read_full<R: Read>(input: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match input.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
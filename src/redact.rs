@@ -0,0 +1,55 @@
+use std::io::{self, Write};
+
+use streamreader::crc::adts_crc;
+
+/// Writes a copy of the input with all content replaced by zeros
+///
+/// Frame headers are kept, so the copy has the same structure, length and
+/// offsets as the input. Everything else, including payloads and data
+/// between frames, is zeroed.
+pub struct Redactor<W: Write> {
+    out: W,
+    written: u64,
+}
+
+impl<W: Write> Redactor<W> {
+    pub fn new(out: W) -> Redactor<W> {
+        Redactor { out, written: 0 }
+    }
+
+    /// Write a frame starting at `offset`, keeping its `header_length`
+    /// header bytes
+    ///
+    /// Protected frames get a CRC matching the zeroed payload.
+    pub fn frame(&mut self, offset: u64, frame: &[u8], header_length: usize) -> io::Result<()> {
+        self.pad_to(offset)?;
+
+        let mut redacted = vec![0; frame.len()];
+        redacted[..header_length].copy_from_slice(&frame[..header_length]);
+        if header_length > 7 {
+            let crc = adts_crc(&redacted[..7], &redacted[header_length..]);
+            redacted[7..9].copy_from_slice(&crc.to_be_bytes());
+        }
+
+        self.out.write_all(&redacted)?;
+        self.written += redacted.len() as u64;
+        Ok(())
+    }
+
+    /// Fill with zeros up to `offset`
+    pub fn pad_to(&mut self, offset: u64) -> io::Result<()> {
+        const ZEROS: [u8; 4096] = [0; 4096];
+
+        while self.written < offset {
+            let len = (offset - self.written).min(ZEROS.len() as u64) as usize;
+            self.out.write_all(&ZEROS[..len])?;
+            self.written += len as u64;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self, length: u64) -> io::Result<()> {
+        self.pad_to(length)?;
+        self.out.flush()
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use crate::loops::FrameRef;
+
+/// Only report a match once this share of the reference clip was found
+const MIN_COVERAGE: f64 = 0.5;
+
+/// A reference clip, indexed by the payload hashes of its frames
+pub struct Reference {
+    pub name: String,
+    pub hashes: Vec<u64>,
+}
+
+/// An occurrence of a reference clip in the scanned stream
+#[derive(Debug)]
+pub struct Match {
+    /// Name of the matched reference clip
+    pub name: String,
+    /// Number of frames in the reference clip
+    pub reference_frames: usize,
+    /// Index of the first matched frame within the reference
+    pub reference_start: usize,
+    pub frames: usize,
+    pub start: FrameRef,
+    pub end: FrameRef,
+}
+
+struct Active {
+    reference: usize,
+    reference_start: usize,
+    next: usize,
+    start: FrameRef,
+    end: FrameRef,
+}
+
+/// Finds runs of frames matching a contiguous part of a reference clip
+///
+/// Matching may start at any frame whose payload is unique within its
+/// reference, so clips cut short at the beginning are still found.
+pub struct ReferenceMatcher {
+    references: Vec<Reference>,
+    /// Payload hash to (reference, frame index) for frames unique within
+    /// their reference
+    index: HashMap<u64, Vec<(usize, usize)>>,
+    active: Vec<Active>,
+    matches: Vec<Match>,
+}
+
+impl ReferenceMatcher {
+    pub fn new(references: Vec<Reference>) -> ReferenceMatcher {
+        let mut index: HashMap<u64, Vec<(usize, usize)>> = HashMap::new();
+        for (ref_idx, reference) in references.iter().enumerate() {
+            let mut counts: HashMap<u64, usize> = HashMap::new();
+            for hash in reference.hashes.iter() {
+                *counts.entry(*hash).or_insert(0) += 1;
+            }
+            for (pos, hash) in reference.hashes.iter().enumerate() {
+                if counts[hash] == 1 {
+                    index.entry(*hash).or_default().push((ref_idx, pos));
+                }
+            }
+        }
+
+        ReferenceMatcher {
+            references,
+            index,
+            active: Vec::new(),
+            matches: Vec::new(),
+        }
+    }
+
+    /// Feed the payload hash of the next frame
+    pub fn push(&mut self, hash: u64, frame: FrameRef) {
+        let mut idx = 0;
+        while idx < self.active.len() {
+            let active = &mut self.active[idx];
+            let hashes = &self.references[active.reference].hashes;
+            if active.next < hashes.len() && hashes[active.next] == hash {
+                active.next += 1;
+                active.end = frame;
+                idx += 1;
+            } else {
+                let ended = self.active.swap_remove(idx);
+                self.end_match(ended);
+            }
+        }
+
+        if let Some(positions) = self.index.get(&hash) {
+            for &(reference, pos) in positions {
+                if self.active.iter().any(|a| a.reference == reference) {
+                    continue;
+                }
+                self.active.push(Active {
+                    reference,
+                    reference_start: pos,
+                    next: pos + 1,
+                    start: frame,
+                    end: frame,
+                });
+            }
+        }
+    }
+
+    /// Finish matching, returning all matches in stream order
+    pub fn finish(&mut self) -> &[Match] {
+        for ended in std::mem::take(&mut self.active) {
+            self.end_match(ended);
+        }
+        self.matches.sort_by_key(|m| m.start.index);
+        &self.matches
+    }
+
+    fn end_match(&mut self, ended: Active) {
+        let frames = ended.next - ended.reference_start;
+        let reference = &self.references[ended.reference];
+        let total = reference.hashes.len();
+        if frames as f64 >= total as f64 * MIN_COVERAGE {
+            self.matches.push(Match {
+                name: reference.name.clone(),
+                reference_frames: total,
+                reference_start: ended.reference_start,
+                frames,
+                start: ended.start,
+                end: ended.end,
+            });
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use std::io::{self, Write};
+
+use streamreader::bitcursor::{BitCursor, BitError};
+use streamreader::{channel_layout, AAC_FRAME_SAMPLES, ADTS_HDR_MAX_LEN, SAMPLING_FREQUENCIES};
+
+/// A header field as stored in the stream, with what it means
+struct Field {
+    name: &'static str,
+    first_bit: u64,
+    width: u8,
+    value: u64,
+    meaning: String,
+}
+
+fn profile_name(profile: u64) -> &'static str {
+    match profile {
+        0 => "AAC Main",
+        1 => "AAC LC",
+        2 => "AAC SSR",
+        _ => "AAC LTP",
+    }
+}
+
+/// Decode every field of the header in `bytes`
+fn decode_fields(bytes: &[u8], offset: u64) -> Result<Vec<Field>, BitError> {
+    let mut reader = BitCursor::new(bytes, offset);
+    let mut fields = Vec::new();
+    let mut field = |reader: &mut BitCursor,
+                     name: &'static str,
+                     width: u8,
+                     meaning: &dyn Fn(u64) -> String|
+     -> Result<u64, BitError> {
+        let first_bit = reader.position() - offset * 8;
+        let value = reader.peek(width)?;
+        reader.skip(width)?;
+        fields.push(Field {
+            name,
+            first_bit,
+            width,
+            value,
+            meaning: meaning(value),
+        });
+        Ok(value)
+    };
+    let flag = |set: &'static str, unset: &'static str| {
+        move |value: u64| if value == 1 { set } else { unset }.to_string()
+    };
+    let none = |_: u64| String::new();
+
+    field(&mut reader, "syncword", 12, &none)?;
+    field(&mut reader, "ID", 1, &flag("MPEG-2", "MPEG-4"))?;
+    field(&mut reader, "layer", 2, &|value| {
+        if value == 0 { "" } else { "must be 0" }.to_string()
+    })?;
+    let protection_absent = field(
+        &mut reader,
+        "protection_absent",
+        1,
+        &flag("no CRC", "CRC follows the header"),
+    )?;
+    field(&mut reader, "profile_ObjectType", 2, &|value| {
+        format!(
+            "{}, MPEG-4 audio object type {}",
+            profile_name(value),
+            value + 1
+        )
+    })?;
+    field(
+        &mut reader,
+        "sampling_frequency_index",
+        4,
+        &|value| match SAMPLING_FREQUENCIES.get(value as usize) {
+            Some(hz) => format!("{} Hz", hz),
+            None if value == 15 => "explicit frequency escape, not allowed in ADTS".to_string(),
+            None => "reserved".to_string(),
+        },
+    )?;
+    field(&mut reader, "private_bit", 1, &none)?;
+    field(&mut reader, "channel_configuration", 3, &|value| {
+        channel_layout(value as u8).to_string()
+    })?;
+    field(&mut reader, "original_copy", 1, &flag("original", "copy"))?;
+    field(&mut reader, "home", 1, &none)?;
+    field(&mut reader, "copyright_identification_bit", 1, &none)?;
+    field(
+        &mut reader,
+        "copyright_identification_start",
+        1,
+        &flag("start of copyright ID", ""),
+    )?;
+    field(&mut reader, "aac_frame_length", 13, &|value| {
+        format!("{} bytes including the header", value)
+    })?;
+    field(&mut reader, "adts_buffer_fullness", 11, &|value| {
+        if value == 0x7FF {
+            "variable bitrate".to_string()
+        } else {
+            format!("{} 32 bit words", value)
+        }
+    })?;
+    field(
+        &mut reader,
+        "number_of_raw_data_blocks_in_frame",
+        2,
+        &|value| format!("{} raw data block(s)", value + 1),
+    )?;
+    if protection_absent == 0 {
+        field(&mut reader, "crc_check", 16, &none)?;
+    }
+    Ok(fields)
+}
+
+/// Write everything that can be derived from the header of the first frame
+///
+/// `bytes` holds the header starting at `offset`, including the CRC if the
+/// frame is protected.
+pub fn write_first_frame<W: Write>(mut out: W, offset: u64, bytes: &[u8]) -> io::Result<()> {
+    let bytes = &bytes[..bytes.len().min(ADTS_HDR_MAX_LEN)];
+    let fields = decode_fields(bytes, offset)
+        .map_err(|err| io::Error::new(io::ErrorKind::UnexpectedEof, err.to_string()))?;
+    let value = |name: &str| {
+        fields
+            .iter()
+            .find(|field| field.name == name)
+            .map_or(0, |field| field.value)
+    };
+
+    writeln!(out, "First frame at offset {}", offset)?;
+    writeln!(out, "Header fields:")?;
+    for field in fields.iter() {
+        let line = format!(
+            "  {:<36} bits {:>2}\u{2013}{:<2} = {:<6} 0x{:<4X} {}",
+            field.name,
+            field.first_bit,
+            field.first_bit + u64::from(field.width) - 1,
+            field.value,
+            field.value,
+            field.meaning
+        );
+        writeln!(out, "{}", line.trim_end())?;
+    }
+
+    let profile = value("profile_ObjectType");
+    let object_type = profile + 1;
+    let frequency_index = value("sampling_frequency_index");
+    let channels = value("channel_configuration");
+    let blocks = value("number_of_raw_data_blocks_in_frame") + 1;
+    let frame_length = value("aac_frame_length");
+    let sampling_frequency = SAMPLING_FREQUENCIES
+        .get(frequency_index as usize)
+        .copied()
+        .unwrap_or(0);
+
+    // AudioSpecificConfig with a GASpecificConfig for 1024 sample frames,
+    // no core coder and no extension
+    let config = (object_type << 11) | (frequency_index << 7) | (channels << 3);
+    writeln!(out, "Derived:")?;
+    writeln!(
+        out,
+        "  AudioSpecificConfig: {:02X} {:02X}",
+        config >> 8,
+        config & 0xFF
+    )?;
+    if sampling_frequency > 0 {
+        let duration = f64::from(AAC_FRAME_SAMPLES) * blocks as f64 / f64::from(sampling_frequency);
+        writeln!(
+            out,
+            "  Frame duration: {:.3} ms, bitrate of this frame: {:.1} kbit/s",
+            duration * 1000.0,
+            frame_length as f64 * 8.0 / duration / 1000.0
+        )?;
+    }
+
+    writeln!(out, "Decoder requirements:")?;
+    writeln!(
+        out,
+        "  {} decoder (audio object type {}), {} Hz, {}",
+        profile_name(profile),
+        object_type,
+        sampling_frequency,
+        channel_layout(channels as u8)
+    )?;
+    if profile == 1 && sampling_frequency <= 24000 {
+        writeln!(
+            out,
+            "  May be HE-AAC: SBR would double the output rate to {} Hz",
+            sampling_frequency * 2
+        )?;
+    }
+    if profile == 1 && channels == 1 {
+        writeln!(
+            out,
+            "  May be HE-AACv2: parametric stereo would decode mono to stereo"
+        )?;
+    }
+    if channels == 0 {
+        writeln!(
+            out,
+            "  The decoder must read the channel layout from a program_config_element"
+        )?;
+    }
+    if blocks > 1 {
+        writeln!(
+            out,
+            "  The decoder must handle {} raw data blocks per ADTS frame",
+            blocks
+        )?;
+    }
+
+    writeln!(out, "Recommended settings:")?;
+    writeln!(out, "  Play: ffplay -f aac <file>")?;
+    writeln!(
+        out,
+        "  Remux without ADTS: ffmpeg -i <file> -c copy -bsf:a aac_adtstoasc out.m4a"
+    )?;
+    writeln!(
+        out,
+        "  Raw AAC in a container needs the AudioSpecificConfig above as codec private data"
+    )?;
+    out.flush()
+}
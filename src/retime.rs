@@ -0,0 +1,123 @@
+use std::io::Read;
+
+use streamreader::ts::{self, TsDemuxer, PTS_FREQUENCY};
+use streamreader::{AdtsError, AdtsFrames};
+
+/// How far the time stamp of a PES packet is off the regenerated timeline
+pub struct Deviation {
+    /// Offset in the elementary stream of the first frame starting in the
+    /// PES packet, which the time stamp is for
+    pub offset: u64,
+    /// Seconds from the first time stamp on the regenerated timeline
+    pub time: f64,
+    /// Seconds the time stamp is later than the regenerated one
+    pub deviation: f64,
+}
+
+/// The time stamps of a demuxed stream against clean ones regenerated
+/// from its frame durations, starting at its first time stamp
+pub struct Retiming {
+    pub frames: u64,
+    /// Seconds of audio
+    pub duration: f64,
+    /// One for each PES packet with a time stamp and a frame starting in it
+    pub deviations: Vec<Deviation>,
+    /// The broken frame the stream was read up to
+    pub error: Option<AdtsError>,
+}
+
+impl Retiming {
+    /// Read the ADTS frames of the stream up to the end or the first broken
+    /// frame, and compare them with its time stamps
+    pub fn read<R: Read>(mut demuxer: TsDemuxer<R>) -> Retiming {
+        demuxer.keep_timestamps();
+        let mut retiming = Retiming {
+            frames: 0,
+            duration: 0.0,
+            deviations: Vec::new(),
+            error: None,
+        };
+        // Offset and regenerated start time of each frame
+        let mut starts = Vec::new();
+        for frame in AdtsFrames::new(&mut demuxer) {
+            let frame = match frame {
+                Ok(frame) => frame,
+                Err(err) => {
+                    retiming.error = Some(err);
+                    break;
+                }
+            };
+            starts.push((frame.offset, retiming.duration));
+            retiming.frames += 1;
+            retiming.duration += frame.header.duration();
+        }
+
+        let timestamps = demuxer.timestamps();
+        let mut frames = starts.iter().peekable();
+        // Time stamp and regenerated time everything is measured from, and
+        // the last time stamp unwrapped
+        let mut origin: Option<(i64, f64)> = None;
+        let mut last: Option<(u64, i64)> = None;
+        for (index, timestamp) in timestamps.iter().enumerate() {
+            let ticks = match last {
+                Some((pts, ticks)) => ticks + ts::timestamp_difference(pts, timestamp.pts),
+                None => 0,
+            };
+            last = Some((timestamp.pts, ticks));
+
+            while frames
+                .peek()
+                .is_some_and(|(offset, _)| *offset < timestamp.stream_offset)
+            {
+                frames.next();
+            }
+            let next_packet = timestamps.get(index + 1).map(|next| next.stream_offset);
+            let (offset, time) = match frames.peek() {
+                Some(&&(offset, time)) if next_packet.is_none_or(|next| offset < next) => {
+                    (offset, time)
+                }
+                _ => continue,
+            };
+            let (origin_ticks, origin_time) = *origin.get_or_insert((ticks, time));
+            let elapsed = (ticks - origin_ticks) as f64 / PTS_FREQUENCY as f64;
+            retiming.deviations.push(Deviation {
+                offset,
+                time: time - origin_time,
+                deviation: elapsed - (time - origin_time),
+            });
+        }
+        retiming
+    }
+
+    /// The first of the deviations furthest from the regenerated timeline
+    pub fn largest(&self) -> Option<&Deviation> {
+        self.deviations
+            .iter()
+            .fold(None, |largest, deviation| match largest {
+                Some(largest) if largest.deviation.abs() >= deviation.deviation.abs() => {
+                    Some(largest)
+                }
+                _ => Some(deviation),
+            })
+    }
+
+    /// Average distance of the time stamps from the regenerated timeline
+    pub fn mean(&self) -> Option<f64> {
+        if self.deviations.is_empty() {
+            return None;
+        }
+        let total: f64 = self.deviations.iter().map(|d| d.deviation.abs()).sum();
+        Some(total / self.deviations.len() as f64)
+    }
+
+    /// The places where the time stamps jump off the regenerated timeline
+    /// by more than `tolerance` seconds against the one before, with the
+    /// size of the jump
+    pub fn jumps(&self, tolerance: f64) -> Vec<(&Deviation, f64)> {
+        self.deviations
+            .windows(2)
+            .map(|pair| (&pair[1], pair[1].deviation - pair[0].deviation))
+            .filter(|(_, jump)| jump.abs() > tolerance)
+            .collect()
+    }
+}
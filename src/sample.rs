@@ -0,0 +1,56 @@
+/// A small xorshift64* generator, so sample positions and impairments can
+/// be reproduced from a seed
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // xorshift must not start from zero
+        Rng((seed ^ 0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniformly distributed in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Choose `count` sample positions in `start..end`, in ascending order
+///
+/// Positions are spread evenly if `periodic` is set, otherwise they are
+/// drawn uniformly at random from `seed`.
+pub fn positions(start: u64, end: u64, count: u64, periodic: bool, seed: u64) -> Vec<u64> {
+    let span = end.saturating_sub(start);
+    if span == 0 {
+        return Vec::new();
+    }
+
+    let mut positions: Vec<u64> = if periodic {
+        (0..count)
+            .map(|i| start + (u128::from(span) * u128::from(i) / u128::from(count)) as u64)
+            .collect()
+    } else {
+        let mut rng = Rng::new(seed);
+        (0..count).map(|_| start + rng.next() % span).collect()
+    };
+    positions.sort_unstable();
+    positions
+}
+
+/// Half width of the 95% confidence interval for a proportion `valid / total`
+///
+/// Uses the normal approximation, which is good enough for the sample sizes
+/// spot checks are done with.
+pub fn confidence_interval(valid: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    let p = valid as f64 / total as f64;
+    1.96 * (p * (1.0 - p) / total as f64).sqrt()
+}
@@ -0,0 +1,346 @@
+//! Quick check how well a buffer of unknown content fits each supported
+//! framing, without parsing more than the frame headers
+
+use std::convert::TryInto;
+
+use crate::ac3;
+use crate::dts;
+use crate::header::{self, ADTS_HDR_MIN_LEN};
+use crate::latm;
+use crate::mpeg_audio;
+use crate::ogg;
+
+/// Framings `accept` can rate a buffer against
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum SniffFormat {
+    Adts,
+    /// MPEG-1/2/2.5 Layer I, II and III
+    MpegAudio,
+    /// AC-3 and E-AC-3
+    Ac3,
+    /// DTS core frames and DTS-HD extension substreams
+    Dts,
+    /// LOAS AudioSyncStream frames carrying LATM
+    Loas,
+    /// Ogg pages, of any codec
+    Ogg,
+}
+
+impl SniffFormat {
+    pub const ALL: &'static [SniffFormat] = &[
+        SniffFormat::Adts,
+        SniffFormat::MpegAudio,
+        SniffFormat::Ac3,
+        SniffFormat::Dts,
+        SniffFormat::Loas,
+        SniffFormat::Ogg,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SniffFormat::Adts => "ADTS",
+            SniffFormat::MpegAudio => "MPEG audio",
+            SniffFormat::Ac3 => "AC-3",
+            SniffFormat::Dts => "DTS",
+            SniffFormat::Loas => "LOAS",
+            SniffFormat::Ogg => "Ogg",
+        }
+    }
+}
+
+/// Parameters of the first frame of the longest run
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct StreamParameters {
+    pub sample_rate: u32,
+    /// Number of channels including LFE, `None` if the header does not tell
+    pub channels: Option<u8>,
+}
+
+/// How well a buffer fits one framing
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct Acceptance {
+    pub format: SniffFormat,
+    /// Number of frames in runs of frames following each other
+    pub frames: usize,
+    /// Number of frames in the longest run
+    pub longest_run: usize,
+    /// Bytes of the buffer covered by the runs
+    pub covered: usize,
+    pub parameters: Option<StreamParameters>,
+    /// 0 to 1, how likely the buffer is in this framing
+    pub confidence: f64,
+}
+
+/// Frames of one framing following each other in a buffer
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct FrameRun {
+    /// Position of the first frame in the buffer
+    pub start: usize,
+    /// Position after the last frame, at most the end of the buffer
+    pub end: usize,
+    pub frames: usize,
+    /// Parameters of the first frame that has them
+    pub parameters: Option<StreamParameters>,
+}
+
+/// A frame header found at some position
+struct Found {
+    length: usize,
+    parameters: Option<StreamParameters>,
+}
+
+/// Find all runs of `format` frames in `data`
+///
+/// A single header is easily matched by chance, so only frames that are
+/// followed directly by another frame, or by the end of the buffer, count.
+/// The buffer may start and end in the middle of a frame, and the search
+/// goes on after a run ends, so a buffer holding several streams one after
+/// the other has a run for each.
+pub fn runs(data: &[u8], format: SniffFormat) -> Vec<FrameRun> {
+    let parse = match format {
+        SniffFormat::Adts => parse_adts,
+        SniffFormat::MpegAudio => parse_mpeg_audio,
+        SniffFormat::Ac3 => parse_ac3,
+        SniffFormat::Dts => parse_dts,
+        SniffFormat::Loas => parse_loas,
+        SniffFormat::Ogg => parse_ogg,
+    };
+
+    let mut runs = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let mut run = FrameRun {
+            start,
+            end: start,
+            frames: 0,
+            parameters: None,
+        };
+        while let Some(found) = parse(&data[run.end..]) {
+            if found.length == 0 {
+                break;
+            }
+            run.parameters = run.parameters.or(found.parameters);
+            run.frames += 1;
+            run.end = (run.end + found.length).min(data.len());
+            if run.end == data.len() {
+                break;
+            }
+        }
+
+        if run.frames > 1 || (run.frames == 1 && run.end == data.len()) {
+            start = run.end;
+            runs.push(run);
+        } else {
+            start += 1;
+        }
+    }
+    runs
+}
+
+/// Rate how well `data` fits `format`
+///
+/// The confidence is the share of the buffer covered by the runs `runs`
+/// finds, lowered for short runs.
+pub fn accept(data: &[u8], format: SniffFormat) -> Acceptance {
+    rate(format, data.len(), &runs(data, format))
+}
+
+/// Rate a buffer of `length` bytes from the runs of `format` frames found
+/// in it
+pub fn rate(format: SniffFormat, length: usize, runs: &[FrameRun]) -> Acceptance {
+    let mut acceptance = Acceptance {
+        format,
+        frames: 0,
+        longest_run: 0,
+        covered: 0,
+        parameters: None,
+        confidence: 0.0,
+    };
+    for run in runs {
+        acceptance.frames += run.frames;
+        acceptance.covered += run.end - run.start;
+        if run.frames > acceptance.longest_run {
+            acceptance.longest_run = run.frames;
+            acceptance.parameters = run.parameters;
+        }
+    }
+
+    if length > 0 {
+        let coverage = acceptance.covered as f64 / length as f64;
+        let chance = 0.5_f64.powi(acceptance.longest_run.min(64) as i32);
+        acceptance.confidence = coverage * (1.0 - chance);
+    }
+    acceptance
+}
+
+/// Rate `data` against every supported framing, best fit first
+pub fn sniff(data: &[u8]) -> Vec<Acceptance> {
+    let mut acceptances: Vec<_> = SniffFormat::ALL
+        .iter()
+        .map(|format| accept(data, *format))
+        .collect();
+    acceptances.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap());
+    acceptances
+}
+
+fn parse_adts(data: &[u8]) -> Option<Found> {
+    let buffer: &[u8; ADTS_HDR_MIN_LEN] = data.get(..ADTS_HDR_MIN_LEN)?.try_into().ok()?;
+    let header = header::parse_header(buffer, 0).ok()?;
+    if header.layer != 0 || usize::from(header.frame_length) < header.header_length() {
+        return None;
+    }
+    let channels = match header.channel_configuration {
+        0 => None,
+        7 => Some(8),
+        count => Some(count),
+    };
+    Some(Found {
+        length: usize::from(header.frame_length),
+        parameters: Some(StreamParameters {
+            sample_rate: header.sampling_frequency(),
+            channels,
+        }),
+    })
+}
+
+fn parse_mpeg_audio(data: &[u8]) -> Option<Found> {
+    let header = mpeg_audio::parse_frame_header(data)?;
+    Some(Found {
+        length: header.frame_length(),
+        parameters: Some(StreamParameters {
+            sample_rate: header.sample_rate,
+            channels: Some(if header.channel_mode == 3 { 1 } else { 2 }),
+        }),
+    })
+}
+
+fn parse_ac3(data: &[u8]) -> Option<Found> {
+    let header = ac3::parse_header(data)?;
+    let channels = [2, 1, 2, 3, 3, 4, 4, 5][usize::from(header.acmod)] + u8::from(header.lfeon);
+    Some(Found {
+        length: header.frame_length,
+        parameters: Some(StreamParameters {
+            sample_rate: header.sample_rate,
+            channels: Some(channels),
+        }),
+    })
+}
+
+fn parse_dts(data: &[u8]) -> Option<Found> {
+    if let Some(substream) = dts::parse_substream_header(data) {
+        // Substreams only have their parameters in the asset descriptors
+        return Some(Found {
+            length: substream.frame_length,
+            parameters: None,
+        });
+    }
+    let header = dts::parse_header(data)?;
+    let channels = [1, 2, 2, 2, 2, 3, 3, 4, 4, 5, 6, 6, 6, 7, 8, 8]
+        .get(usize::from(header.amode))
+        .map(|count| count + u8::from(header.lfe));
+    Some(Found {
+        length: header.frame_length,
+        parameters: Some(StreamParameters {
+            sample_rate: header.sample_rate,
+            channels,
+        }),
+    })
+}
+
+fn parse_loas(data: &[u8]) -> Option<Found> {
+    let frame = latm::parse_frame(data)?;
+    // Elements without a StreamMuxConfig use the one of an earlier element
+    let parameters = frame.config.map(|config| {
+        let asc = config.audio_specific_config;
+        StreamParameters {
+            sample_rate: asc.sbr_frequency.unwrap_or(asc.sampling_frequency),
+            channels: match asc.channel_configuration {
+                0 => None,
+                7 => Some(8),
+                count => Some(count),
+            },
+        }
+    });
+    Some(Found {
+        length: frame.length,
+        parameters,
+    })
+}
+
+fn parse_ogg(data: &[u8]) -> Option<Found> {
+    let page = ogg::parse_page(data)?;
+    // Only the first page of a stream tells its parameters
+    let parameters = if page.beginning_of_stream {
+        let start = page.header_length();
+        let end = (start + page.first_packet_length()).min(data.len());
+        let identification = ogg::identify(data.get(start..end).unwrap_or(&[]));
+        identification
+            .sample_rate
+            .map(|sample_rate| StreamParameters {
+                sample_rate,
+                channels: identification.channels,
+            })
+    } else {
+        None
+    };
+    Some(Found {
+        length: page.length(),
+        parameters,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::tests::adts_frame;
+
+    #[test]
+    fn finds_adts_runs_around_junk() {
+        let mut data = vec![0x55; 30];
+        for _ in 0..4 {
+            data.extend(adts_frame(100, false));
+        }
+        data.extend_from_slice(&[0x55; 30]);
+        data.extend(adts_frame(100, false));
+        data.extend(adts_frame(100, false));
+
+        let found = runs(&data, SniffFormat::Adts);
+        let spans: Vec<(usize, usize, usize)> = found
+            .iter()
+            .map(|run| (run.start, run.end, run.frames))
+            .collect();
+        assert_eq!(spans, [(30, 430, 4), (460, 660, 2)]);
+        assert_eq!(
+            found[0].parameters,
+            Some(StreamParameters {
+                sample_rate: 44100,
+                channels: Some(2),
+            })
+        );
+    }
+
+    #[test]
+    fn a_lone_header_is_no_run() {
+        let mut data = adts_frame(100, false);
+        data.extend_from_slice(&[0x55; 100]);
+        assert!(runs(&data, SniffFormat::Adts).is_empty());
+
+        // Unless the buffer ends inside its frame
+        assert_eq!(runs(&data[..60], SniffFormat::Adts).len(), 1);
+    }
+
+    #[test]
+    fn rates_adts_first() {
+        let data: Vec<u8> = (0..20).flat_map(|_| adts_frame(100, false)).collect();
+        let acceptances = sniff(&data);
+        assert_eq!(acceptances[0].format, SniffFormat::Adts);
+        assert_eq!(acceptances[0].frames, 20);
+        assert_eq!(acceptances[0].covered, data.len());
+        assert!(acceptances[0].confidence > 0.99);
+        assert!(acceptances[1].confidence < 0.5);
+    }
+}
@@ -0,0 +1,52 @@
+use streamreader::{AdtsHeader, MPEGAudioObjectType};
+
+/// Aggregate statistics over all frames of a run
+pub struct FrameStats {
+    pub frames: usize,
+    /// Bytes of raw data, without headers
+    pub payload_bytes: u64,
+    pub total_bytes: u64,
+    pub min_length: u16,
+    pub max_length: u16,
+    /// Sampling frequencies in Hz, in the order they were first seen
+    pub sample_rates: Vec<u32>,
+    /// Profiles in the order they were first seen
+    pub profiles: Vec<MPEGAudioObjectType>,
+}
+
+impl FrameStats {
+    pub fn new() -> FrameStats {
+        FrameStats {
+            frames: 0,
+            payload_bytes: 0,
+            total_bytes: 0,
+            min_length: u16::MAX,
+            max_length: 0,
+            sample_rates: Vec::new(),
+            profiles: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, header: &AdtsHeader) {
+        self.frames += 1;
+        self.total_bytes += u64::from(header.frame_length);
+        self.payload_bytes += (usize::from(header.frame_length) - header.header_length()) as u64;
+        self.min_length = self.min_length.min(header.frame_length);
+        self.max_length = self.max_length.max(header.frame_length);
+        if !self.sample_rates.contains(&header.sampling_frequency()) {
+            self.sample_rates.push(header.sampling_frequency());
+        }
+        if !self.profiles.contains(&header.profile) {
+            self.profiles.push(header.profile);
+        }
+    }
+
+    /// Average frame length in bytes, including headers
+    pub fn average_length(&self) -> Option<f64> {
+        if self.frames == 0 {
+            None
+        } else {
+            Some(self.total_bytes as f64 / self.frames as f64)
+        }
+    }
+}
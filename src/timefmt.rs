@@ -0,0 +1,164 @@
+use crate::json;
+
+/// How times and durations are written in the output
+#[derive(Debug, Clone, Copy)]
+pub enum Style {
+    /// Seconds with a unit, "62.500 s"
+    Seconds,
+    /// Plain decimal seconds, "62.500"
+    Unix,
+    /// ISO 8601 duration, "PT1M2.500S"
+    Iso8601,
+    /// Clock time, "00:01:02.500"
+    Hms,
+}
+
+pub fn parse_style(value: &str) -> Result<Style, String> {
+    match value {
+        "unix" => Ok(Style::Unix),
+        "iso8601" => Ok(Style::Iso8601),
+        "hms" => Ok(Style::Hms),
+        _ => Err(format!(
+            "unknown timestamp format '{}' (expected unix, iso8601 or hms)",
+            value
+        )),
+    }
+}
+
+/// Largest number of decimals, so the scaled value still fits a u64
+const MAX_DECIMALS: usize = 9;
+
+pub fn parse_decimals(value: &str) -> Result<usize, String> {
+    match value.parse() {
+        Ok(decimals) if decimals <= MAX_DECIMALS => Ok(decimals),
+        _ => Err(format!(
+            "invalid number of decimals '{}' (expected 0 to {})",
+            value, MAX_DECIMALS
+        )),
+    }
+}
+
+/// Time formatting chosen on the command line
+///
+/// Anything not chosen falls back to the default of the place the time is
+/// printed at, so the output without options stays as it always was.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeFormat {
+    pub style: Option<Style>,
+    pub decimals: Option<usize>,
+}
+
+impl TimeFormat {
+    /// Format a precise time or duration, by default as "62.500 s"
+    pub fn format(&self, seconds: f64) -> String {
+        render(
+            self.style.unwrap_or(Style::Seconds),
+            self.decimals.unwrap_or(3),
+            seconds,
+        )
+    }
+
+    /// Format a difference of times like `format`, always with its sign,
+    /// by default as "+62.500 s"
+    pub fn format_signed(&self, seconds: f64) -> String {
+        let magnitude = self.format(seconds.abs());
+        // Nothing left to be negative after rounding
+        let sign = if seconds < 0.0 && magnitude != self.format(0.0) {
+            '-'
+        } else {
+            '+'
+        };
+        format!("{}{}", sign, magnitude)
+    }
+
+    /// Format a time or duration for machine readable output, by default
+    /// as plain seconds with microseconds, "62.500000"
+    pub fn format_plain(&self, seconds: f64) -> String {
+        render(
+            self.style.unwrap_or(Style::Unix),
+            self.decimals.unwrap_or(6),
+            seconds,
+        )
+    }
+
+    /// Format a time or duration as a JSON value like `format_plain`, a
+    /// number for plain seconds and a string in any other style
+    ///
+    /// The type depends on the style, so the --format json schema only uses
+    /// it for output version 1, `json_fields` after.
+    pub fn format_json(&self, seconds: f64) -> String {
+        match self.style.unwrap_or(Style::Unix) {
+            _ if !seconds.is_finite() => "null".to_string(),
+            Style::Unix => self.format_plain(seconds),
+            _ => json::string(&self.format_plain(seconds)),
+        }
+    }
+
+    /// JSON fields of a time or duration named `name`: always plain seconds
+    /// as a number, and in the chosen style as a string in `name_text`
+    /// too, unless that style is plain seconds
+    pub fn json_fields(&self, name: &str, seconds: f64) -> Vec<(String, String)> {
+        if !seconds.is_finite() {
+            return vec![(name.to_string(), "null".to_string())];
+        }
+        let decimals = self.decimals.unwrap_or(6);
+        let mut fields = vec![(name.to_string(), render(Style::Unix, decimals, seconds))];
+        match self.style.unwrap_or(Style::Unix) {
+            Style::Unix => {}
+            style => fields.push((
+                format!("{}_text", name),
+                json::string(&render(style, decimals, seconds)),
+            )),
+        }
+        fields
+    }
+
+    /// Format a coarse time, by default as "00:01:02"
+    pub fn format_clock(&self, seconds: f64) -> String {
+        render(
+            self.style.unwrap_or(Style::Hms),
+            self.decimals.unwrap_or(0),
+            seconds,
+        )
+    }
+}
+
+/// Write `seconds` in `style`
+///
+/// Clock and ISO 8601 times without decimals are truncated like a clock
+/// rather than rounded, with decimals they are rounded to the last digit.
+fn render(style: Style, decimals: usize, seconds: f64) -> String {
+    match style {
+        Style::Seconds => format!("{:.*} s", decimals, seconds),
+        Style::Unix => format!("{:.*}", decimals, seconds),
+        Style::Iso8601 | Style::Hms => {
+            let scale = 10u64.pow(decimals as u32);
+            let scaled = seconds.max(0.0) * scale as f64;
+            let units = if decimals == 0 {
+                scaled as u64
+            } else {
+                scaled.round() as u64
+            };
+            let whole = units / scale;
+            let fraction = if decimals == 0 {
+                String::new()
+            } else {
+                format!(".{:0width$}", units % scale, width = decimals)
+            };
+            let (hours, minutes, seconds) = (whole / 3600, whole / 60 % 60, whole % 60);
+
+            if let Style::Hms = style {
+                return format!("{:02}:{:02}:{:02}{}", hours, minutes, seconds, fraction);
+            }
+            let mut out = String::from("PT");
+            if hours > 0 {
+                out.push_str(&format!("{}H", hours));
+            }
+            if hours > 0 || minutes > 0 {
+                out.push_str(&format!("{}M", minutes));
+            }
+            out.push_str(&format!("{}{}S", seconds, fraction));
+            out
+        }
+    }
+}
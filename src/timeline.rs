@@ -0,0 +1,118 @@
+use std::io::{self, Write};
+
+/// A named stretch of the stream, in seconds from the start of the scan
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub start: f64,
+    pub end: f64,
+    pub title: String,
+}
+
+/// Title of the tracks filling the gaps between events in a CUE sheet
+const GAP_TITLE: &str = "Program";
+
+/// Write the events, sorted by start, as a CUE sheet for the audio file
+/// `file_name` lasting `duration` seconds
+///
+/// CUE tracks cover the whole file without gaps, so stretches between
+/// events become tracks of their own. Overlapping events are cut at the
+/// start of the next one, events starting together share a track.
+pub fn write_cue<W: Write>(
+    mut out: W,
+    file_name: &str,
+    duration: f64,
+    events: &[Event],
+) -> io::Result<()> {
+    writeln!(out, "FILE \"{}\" WAVE", escape_cue(file_name))?;
+
+    let mut tracks: Vec<(f64, String)> = Vec::new();
+    let mut position = 0.0;
+    for event in events {
+        if event.start > position {
+            tracks.push((position, GAP_TITLE.to_string()));
+        }
+        match tracks.last_mut() {
+            Some(last) if cue_time(last.0) == cue_time(event.start) => {
+                last.1 = format!("{} / {}", last.1, event.title);
+            }
+            _ => tracks.push((event.start, event.title.clone())),
+        }
+        position = event.end;
+    }
+    if tracks.is_empty() || duration > position {
+        tracks.push((position, GAP_TITLE.to_string()));
+    }
+
+    for (number, (start, title)) in tracks.iter().enumerate() {
+        writeln!(out, "  TRACK {:02} AUDIO", number + 1)?;
+        writeln!(out, "    TITLE \"{}\"", escape_cue(title))?;
+        writeln!(out, "    INDEX 01 {}", cue_time(*start))?;
+    }
+    Ok(())
+}
+
+/// Write the events as a Matroska chapter XML file
+pub fn write_chapters<W: Write>(mut out: W, events: &[Event]) -> io::Result<()> {
+    writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(out, "<!DOCTYPE Chapters SYSTEM \"matroskachapters.dtd\">")?;
+    writeln!(out, "<Chapters>")?;
+    writeln!(out, "  <EditionEntry>")?;
+    for event in events {
+        writeln!(out, "    <ChapterAtom>")?;
+        writeln!(
+            out,
+            "      <ChapterTimeStart>{}</ChapterTimeStart>",
+            chapter_time(event.start)
+        )?;
+        writeln!(
+            out,
+            "      <ChapterTimeEnd>{}</ChapterTimeEnd>",
+            chapter_time(event.end)
+        )?;
+        writeln!(out, "      <ChapterDisplay>")?;
+        writeln!(
+            out,
+            "        <ChapterString>{}</ChapterString>",
+            escape_xml(&event.title)
+        )?;
+        writeln!(out, "        <ChapterLanguage>und</ChapterLanguage>")?;
+        writeln!(out, "      </ChapterDisplay>")?;
+        writeln!(out, "    </ChapterAtom>")?;
+    }
+    writeln!(out, "  </EditionEntry>")?;
+    writeln!(out, "</Chapters>")
+}
+
+/// Format seconds as CUE mm:ss:ff, with 75 frames per second
+fn cue_time(seconds: f64) -> String {
+    let frames = (seconds * 75.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        frames / 75 / 60,
+        frames / 75 % 60,
+        frames % 75
+    )
+}
+
+/// Format seconds as hh:mm:ss.nnnnnnnnn
+fn chapter_time(seconds: f64) -> String {
+    let nanos = (seconds * 1e9).round() as u64;
+    let secs = nanos / 1_000_000_000;
+    format!(
+        "{:02}:{:02}:{:02}.{:09}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        nanos % 1_000_000_000
+    )
+}
+
+fn escape_cue(text: &str) -> String {
+    text.replace('"', "'")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
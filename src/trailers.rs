@@ -0,0 +1,109 @@
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use streamreader::ape::{self, ApeFooter, ApeItem, APE_FOOTER_LEN};
+use streamreader::id3::{self, Id3v1Tag, ID3V1_LEN};
+
+/// A tag after the last frame of a file
+pub enum Trailer {
+    Id3v1 {
+        offset: u64,
+        tag: Id3v1Tag,
+    },
+    Ape {
+        offset: u64,
+        footer: ApeFooter,
+        items: Vec<ApeItem>,
+    },
+}
+
+impl Trailer {
+    pub fn offset(&self) -> u64 {
+        match self {
+            Trailer::Id3v1 { offset, .. } | Trailer::Ape { offset, .. } => *offset,
+        }
+    }
+
+    pub fn length(&self) -> u64 {
+        match self {
+            Trailer::Id3v1 { .. } => ID3V1_LEN as u64,
+            Trailer::Ape { footer, .. } => footer.total_length() as u64,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Trailer::Id3v1 { .. } => "id3v1",
+            Trailer::Ape { footer, .. } if footer.version < 2000 => "apev1",
+            Trailer::Ape { .. } => "apev2",
+        }
+    }
+}
+
+/// Find the tags at the end of a file of `length` bytes whose frames start
+/// at `start`, in the order they are in the file
+///
+/// An APE tag comes before an ID3v1 tag if a file has both. The frames end
+/// at the offset of the first one. Leaves the file at an unspecified
+/// position.
+pub fn find(mut file: &fs::File, start: u64, length: u64) -> io::Result<Vec<Trailer>> {
+    find_with(start, length, |offset, data| {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(data)
+    })
+}
+
+/// Find the tags at the end of a stream of `length` bytes in `tail`, the
+/// last bytes of the stream, in the order they are in the stream
+///
+/// Only tags that are completely in `tail` are found.
+pub fn find_in(tail: &[u8], length: u64) -> Vec<Trailer> {
+    let start = length - tail.len() as u64;
+    let tags = find_with(start, length, |offset, data| {
+        let at = (offset - start) as usize;
+        data.copy_from_slice(&tail[at..at + data.len()]);
+        Ok(())
+    });
+    tags.expect("reading from memory failed")
+}
+
+/// Find the tags of an input of `length` bytes whose frames start at
+/// `start`, getting its bytes from `read_at`, which fills the buffer with
+/// the data at an offset
+fn find_with<F>(start: u64, length: u64, mut read_at: F) -> io::Result<Vec<Trailer>>
+where
+    F: FnMut(u64, &mut [u8]) -> io::Result<()>,
+{
+    let mut trailers = Vec::new();
+    let mut end = length;
+
+    if end >= start + ID3V1_LEN as u64 {
+        let mut data = [0; ID3V1_LEN];
+        read_at(end - ID3V1_LEN as u64, &mut data)?;
+        if let Some(tag) = id3::parse_v1(&data) {
+            end -= ID3V1_LEN as u64;
+            trailers.push(Trailer::Id3v1 { offset: end, tag });
+        }
+    }
+
+    if end >= start + APE_FOOTER_LEN as u64 {
+        let mut data = [0; APE_FOOTER_LEN];
+        read_at(end - APE_FOOTER_LEN as u64, &mut data)?;
+        let footer =
+            ape::parse_footer(&data).filter(|footer| footer.total_length() as u64 <= end - start);
+        if let Some(footer) = footer {
+            let mut tag = vec![0; footer.size];
+            read_at(end - footer.size as u64, &mut tag)?;
+            end -= footer.total_length() as u64;
+            trailers.insert(
+                0,
+                Trailer::Ape {
+                    offset: end,
+                    items: ape::parse_items(&tag, &footer),
+                    footer,
+                },
+            );
+        }
+    }
+    Ok(trailers)
+}
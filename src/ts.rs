@@ -0,0 +1,1091 @@
+//! Demuxing the AAC elementary stream of an MPEG transport stream,
+//! ISO 13818-1
+
+use std::io::{self, ErrorKind, Read};
+
+use crate::crc::crc32;
+
+pub const TS_PACKET_LEN: usize = 188;
+pub const TS_SYNC_BYTE: u8 = 0x47;
+/// PID of the program association table
+const PAT_PID: u16 = 0;
+/// PID of the null packets that pad a multiplex, their continuity counter
+/// is undefined
+pub const NULL_PID: u16 = 0x1FFF;
+/// Sync bytes one packet apart needed to trust a packet start
+const SYNC_CONFIRM: usize = 3;
+/// Frequency of the program clock reference
+pub const PCR_FREQUENCY: u64 = 27_000_000;
+/// The program clock reference wraps around at this value
+const PCR_WRAP: u64 = 300 << 33;
+/// Frequency of presentation time stamps
+pub const PTS_FREQUENCY: u64 = 90_000;
+/// Presentation time stamps wrap around at this value
+const PTS_WRAP: u64 = 1 << 33;
+
+/// A transport stream packet
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct TsPacket<'a> {
+    pub pid: u16,
+    /// Set by the demodulator for packets with uncorrectable errors
+    pub transport_error: bool,
+    /// Whether a PES packet or PSI section starts in this packet
+    pub payload_unit_start: bool,
+    pub continuity_counter: u8,
+    /// Set in the adaptation field where the continuity counter may jump
+    pub discontinuity_indicator: bool,
+    /// Program clock reference of the adaptation field, in ticks of
+    /// `PCR_FREQUENCY`
+    pub pcr: Option<u64>,
+    /// The bytes after the adaptation field, empty if there are none
+    pub payload: &'a [u8],
+}
+
+/// Parse the transport stream packet at the start of `data`
+pub fn parse_packet(data: &[u8]) -> Option<TsPacket<'_>> {
+    let packet = data.get(..TS_PACKET_LEN)?;
+    if packet[0] != TS_SYNC_BYTE {
+        return None;
+    }
+    let adaptation_field_control = packet[3] >> 4 & 0x3;
+    let adaptation_field = if adaptation_field_control & 0x2 != 0 {
+        packet.get(5..5 + usize::from(packet[4]))?
+    } else {
+        &[]
+    };
+    let payload = match adaptation_field_control {
+        1 => &packet[4..],
+        2 => &[][..],
+        3 => packet.get(5 + usize::from(packet[4])..)?,
+        _ => return None,
+    };
+    Some(TsPacket {
+        pid: u16::from(packet[1] & 0x1F) << 8 | u16::from(packet[2]),
+        transport_error: packet[1] & 0x80 != 0,
+        payload_unit_start: packet[1] & 0x40 != 0,
+        continuity_counter: packet[3] & 0xF,
+        discontinuity_indicator: adaptation_field
+            .first()
+            .is_some_and(|flags| flags & 0x80 != 0),
+        pcr: parse_pcr(adaptation_field),
+        payload,
+    })
+}
+
+/// A program of the program association table
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct Program {
+    pub program_number: u16,
+    /// PID of the packets carrying the program map table
+    pub pmt_pid: u16,
+}
+
+/// Parse a program_association_section, checking its CRC-32
+///
+/// The network information table entry is left out.
+pub fn parse_pat(section: &[u8]) -> Option<Vec<Program>> {
+    let body = section_body(section, 0x00)?;
+    let programs = body[5..]
+        .chunks_exact(4)
+        .map(|entry| Program {
+            program_number: u16::from(entry[0]) << 8 | u16::from(entry[1]),
+            pmt_pid: u16::from(entry[2] & 0x1F) << 8 | u16::from(entry[3]),
+        })
+        .filter(|program| program.program_number != 0)
+        .collect();
+    Some(programs)
+}
+
+/// How AAC is framed in an elementary stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum AacFraming {
+    Adts,
+    /// LOAS AudioSyncStream frames carrying LATM
+    Latm,
+}
+
+impl AacFraming {
+    pub fn name(self) -> &'static str {
+        match self {
+            AacFraming::Adts => "ADTS",
+            AacFraming::Latm => "LOAS/LATM",
+        }
+    }
+}
+
+/// The program clock reference of an adaptation field, if its flag is set
+fn parse_pcr(adaptation_field: &[u8]) -> Option<u64> {
+    let field = adaptation_field
+        .get(..7)
+        .filter(|field| field[0] & 0x10 != 0)?;
+    let base = u64::from(field[1]) << 25
+        | u64::from(field[2]) << 17
+        | u64::from(field[3]) << 9
+        | u64::from(field[4]) << 1
+        | u64::from(field[5] >> 7);
+    let extension = u64::from(field[5] & 0x01) << 8 | u64::from(field[6]);
+    Some(base * 300 + extension)
+}
+
+/// Tag of the DVB AAC descriptor, EN 300 468 annex H
+pub const AAC_DESCRIPTOR: u8 = 0x7C;
+/// Tag of the ISO 639 language descriptor, with the audio type
+pub const ISO_639_LANGUAGE_DESCRIPTOR: u8 = 0x0A;
+/// Tag of the DVB extension descriptor, followed by the extension tag
+pub const EXTENSION_DESCRIPTOR: u8 = 0x7F;
+/// Extension tag of the DVB supplementary audio descriptor
+const SUPPLEMENTARY_AUDIO_DESCRIPTOR: u8 = 0x06;
+
+/// How an audio description stream for the visually impaired is played
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum AudioDescription {
+    /// The program audio mixed with the description, played instead of
+    /// the main audio
+    BroadcastMix,
+    /// The description alone, mixed into the main audio by the receiver
+    ReceiverMix,
+    /// Only marked by the audio type of its language descriptor, which does
+    /// not tell how it is mixed
+    Unspecified,
+}
+
+impl AudioDescription {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioDescription::BroadcastMix => "broadcast mix",
+            AudioDescription::ReceiverMix => "receiver mix",
+            AudioDescription::Unspecified => "unspecified mix",
+        }
+    }
+}
+
+/// An elementary stream of a program map table
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ElementaryStream {
+    pub stream_type: u8,
+    pub pid: u16,
+    /// Tags and contents of the descriptors of the stream
+    pub descriptors: Vec<(u8, Vec<u8>)>,
+}
+
+impl ElementaryStream {
+    /// Framing of the stream if it is AAC, by its stream type
+    ///
+    /// DVB can also carry ADTS as private data, stream type 0x06, marked by
+    /// an AAC descriptor.
+    pub fn aac_framing(&self) -> Option<AacFraming> {
+        match self.stream_type {
+            0x0F => Some(AacFraming::Adts),
+            0x11 => Some(AacFraming::Latm),
+            0x06 if self.descriptor(AAC_DESCRIPTOR).is_some() => Some(AacFraming::Adts),
+            _ => None,
+        }
+    }
+
+    /// Whether the stream is an audio description, by its supplementary
+    /// audio descriptor or else the audio type of its language descriptor
+    pub fn audio_description(&self) -> Option<AudioDescription> {
+        let supplementary = self
+            .descriptors
+            .iter()
+            .filter(|(tag, _)| *tag == EXTENSION_DESCRIPTOR)
+            .find_map(|(_, data)| match data.as_slice() {
+                [SUPPLEMENTARY_AUDIO_DESCRIPTOR, flags, ..] => Some(*flags),
+                _ => None,
+            });
+        if let Some(flags) = supplementary {
+            // Editorial classification 1 is the description for the
+            // visually impaired
+            return match (flags >> 7, flags >> 2 & 0x1F) {
+                (_, classification) if classification != 1 => None,
+                (1, _) => Some(AudioDescription::BroadcastMix),
+                _ => Some(AudioDescription::ReceiverMix),
+            };
+        }
+        // Audio type 3 is visual impaired commentary
+        let languages = self.descriptor(ISO_639_LANGUAGE_DESCRIPTOR)?;
+        if languages.chunks_exact(4).any(|language| language[3] == 3) {
+            return Some(AudioDescription::Unspecified);
+        }
+        None
+    }
+
+    /// The contents of the first descriptor with `tag`
+    pub fn descriptor(&self, tag: u8) -> Option<&[u8]> {
+        self.descriptors
+            .iter()
+            .find(|(found, _)| *found == tag)
+            .map(|(_, data)| data.as_slice())
+    }
+}
+
+/// Parse the descriptors in `data`, leaving out one that does not fit
+fn parse_descriptors(mut data: &[u8]) -> Vec<(u8, Vec<u8>)> {
+    let mut descriptors = Vec::new();
+    while let [tag, length, rest @ ..] = data {
+        let length = usize::from(*length);
+        match rest.get(..length) {
+            Some(contents) => descriptors.push((*tag, contents.to_vec())),
+            None => break,
+        }
+        data = &rest[length..];
+    }
+    descriptors
+}
+
+/// The fields of a TS_program_map_section
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Pmt {
+    pub program_number: u16,
+    pub pcr_pid: u16,
+    pub streams: Vec<ElementaryStream>,
+}
+
+/// Parse a TS_program_map_section, checking its CRC-32
+pub fn parse_pmt(section: &[u8]) -> Option<Pmt> {
+    let body = section_body(section, 0x02)?;
+    if body.len() < 9 {
+        return None;
+    }
+    let program_info_length = usize::from(body[7] & 0x0F) << 8 | usize::from(body[8]);
+    let mut streams = Vec::new();
+    let mut pos = 9 + program_info_length;
+    while pos + 5 <= body.len() {
+        let entry = &body[pos..];
+        let info_length = usize::from(entry[3] & 0x0F) << 8 | usize::from(entry[4]);
+        let info = &entry[5..(5 + info_length).min(entry.len())];
+        streams.push(ElementaryStream {
+            stream_type: entry[0],
+            pid: u16::from(entry[1] & 0x1F) << 8 | u16::from(entry[2]),
+            descriptors: parse_descriptors(info),
+        });
+        pos += 5 + info_length;
+    }
+    Some(Pmt {
+        program_number: u16::from(body[0]) << 8 | u16::from(body[1]),
+        pcr_pid: u16::from(body[5] & 0x1F) << 8 | u16::from(body[6]),
+        streams,
+    })
+}
+
+/// The section after its first 3 bytes and without its CRC-32, if it is a
+/// complete section of `table_id` with a valid CRC
+fn section_body(section: &[u8], table_id: u8) -> Option<&[u8]> {
+    let section = section.get(..section_length(section)?)?;
+    if section[0] != table_id || section.len() < 12 || crc32(0xFFFF_FFFF, section) != 0 {
+        return None;
+    }
+    Some(&section[3..section.len() - 4])
+}
+
+/// Length of the whole section including its first 3 bytes
+fn section_length(section: &[u8]) -> Option<usize> {
+    let header = section.get(..3)?;
+    Some(3 + (usize::from(header[1] & 0x0F) << 8 | usize::from(header[2])))
+}
+
+/// The payload of the PES packet at the start of `data`, after its header
+pub fn pes_payload(data: &[u8]) -> Option<&[u8]> {
+    if data.len() < 6 || data[..3] != [0, 0, 1] {
+        return None;
+    }
+    // Streams without the optional PES header, ISO 13818-1 table 2-21
+    if matches!(
+        data[3],
+        0xBC | 0xBE | 0xBF | 0xF0 | 0xF1 | 0xF2 | 0xF8 | 0xFF
+    ) {
+        return Some(&data[6..]);
+    }
+    data.get(9 + usize::from(*data.get(8)?)..)
+}
+
+/// Length of the PES packet at the start of `data` including its first 6
+/// bytes, `None` if it is left open
+fn pes_length(data: &[u8]) -> Option<usize> {
+    let length = usize::from(*data.get(4)?) << 8 | usize::from(*data.get(5)?);
+    if length == 0 {
+        return None;
+    }
+    Some(6 + length)
+}
+
+/// The presentation time stamp of the PES packet at the start of `data`
+fn pes_timestamp(data: &[u8]) -> Option<u64> {
+    pes_payload(data)?;
+    let header = data.get(..14)?;
+    if header[7] & 0x80 == 0 {
+        return None;
+    }
+    Some(
+        u64::from(header[9] >> 1 & 0x07) << 30
+            | u64::from(header[10]) << 22
+            | u64::from(header[11] >> 1) << 15
+            | u64::from(header[12]) << 7
+            | u64::from(header[13] >> 1),
+    )
+}
+
+/// Ticks from presentation time stamp `from` to `to`, the shorter way
+/// around their wrap, so a later one may be smaller
+pub fn timestamp_difference(from: u64, to: u64) -> i64 {
+    let ticks = to.wrapping_sub(from) & (PTS_WRAP - 1);
+    if ticks >= PTS_WRAP / 2 {
+        ticks as i64 - PTS_WRAP as i64
+    } else {
+        ticks as i64
+    }
+}
+
+/// The presentation time stamp of a PES packet of the demuxed stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct PesTimestamp {
+    /// Position in the demuxed elementary stream where the payload of the
+    /// PES packet starts
+    pub stream_offset: u64,
+    /// In 90 kHz ticks
+    pub pts: u64,
+}
+
+/// The AAC stream a `TsDemuxer` extracts
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct AacStream {
+    pub program_number: u16,
+    pub pid: u16,
+    pub framing: AacFraming,
+    /// PID of the packets with the clock of the program
+    pub pcr_pid: u16,
+}
+
+/// Packet counts and timing of a transport stream
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct MuxStats {
+    /// Packets by PID, in the order the PIDs first appear
+    pub packets: Vec<(u16, u64)>,
+    /// Packets from the first to the last clock reference of the program,
+    /// leaving out the intervals that end at a discontinuity
+    pub pcr_packets: u64,
+    /// Ticks of `PCR_FREQUENCY` of the intervals of `pcr_packets`
+    pub pcr_ticks: u64,
+}
+
+impl MuxStats {
+    pub fn total_packets(&self) -> u64 {
+        self.packets.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Packets with `pid`
+    pub fn packets_of(&self, pid: u16) -> u64 {
+        self.packets
+            .iter()
+            .find(|(found, _)| *found == pid)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    /// Bits per second of the whole multiplex, by the clock of the program
+    pub fn mux_rate(&self) -> Option<f64> {
+        if self.pcr_ticks == 0 {
+            return None;
+        }
+        let bits = (self.pcr_packets * TS_PACKET_LEN as u64 * 8) as f64;
+        Some(bits * PCR_FREQUENCY as f64 / self.pcr_ticks as f64)
+    }
+
+    /// Share of the packets with `pid` in the multiplex, from 0 to 1
+    pub fn share(&self, pid: u16) -> f64 {
+        match self.total_packets() {
+            0 => 0.0,
+            total => self.packets_of(pid) as f64 / total as f64,
+        }
+    }
+
+    /// Bits per second of `pid`, its share of the mux rate
+    pub fn bitrate(&self, pid: u16) -> Option<f64> {
+        Some(self.mux_rate()? * self.share(pid))
+    }
+
+    fn count(&mut self, pid: u16) {
+        match self.packets.iter_mut().find(|(found, _)| *found == pid) {
+            Some((_, count)) => *count += 1,
+            None => self.packets.push((pid, 1)),
+        }
+    }
+}
+
+/// What a continuity counter jump did to the demuxed stream
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum GapOutcome {
+    /// The PES packet around the gap has all the bytes its header gives,
+    /// only the counter jumped
+    Recovered,
+    /// Data was lost, with the number of bytes missing from the PES packet
+    /// around the gap if known. It has more bytes than its header gives if
+    /// the start of the next one was lost.
+    Lost(Option<u64>),
+    /// Not known, for PES packets without a length, gaps between PES
+    /// packets and gaps in other streams
+    Unknown,
+}
+
+/// A jump of the continuity counter of a PID, where packets were lost
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct Discontinuity {
+    pub pid: u16,
+    /// Offset of the first packet after the gap in the transport stream
+    pub offset: u64,
+    /// Position in the demuxed elementary stream at the gap
+    pub stream_offset: u64,
+    /// Number of packets lost, only known modulo 16
+    pub lost: u8,
+    /// Seconds from the first time stamp of the demuxed stream to the one of
+    /// the PES packet the gap is in, only for gaps in the demuxed stream
+    pub time: Option<f64>,
+    pub outcome: GapOutcome,
+}
+
+/// Reads the AAC elementary stream of one program out of a transport
+/// stream
+///
+/// The payloads of the stream's PES packets are read back to back, from
+/// the first PES packet after its program map table. Packets with the
+/// transport error flag and repeated packets are left out, so lost or
+/// corrupt packets show in the elementary stream as broken frames. Jumps
+/// of the continuity counter of all PIDs are collected in
+/// `discontinuities`, once the stream is found.
+pub struct TsDemuxer<R> {
+    reader: R,
+    /// Bytes read from the source but not split into packets yet
+    buffer: Vec<u8>,
+    packet: [u8; TS_PACKET_LEN],
+    /// Whether the last packet started at a sync byte
+    synced: bool,
+    /// Offset of the next byte of the source after `buffer`
+    position: u64,
+    /// Offset of the current packet
+    packet_offset: u64,
+    mux: MuxStats,
+    /// Clock reference of the program last seen, with the number of
+    /// packets before it
+    last_pcr: Option<(u64, u64)>,
+    eof: bool,
+    stream: AacStream,
+    /// The program map table of the stream
+    program: Pmt,
+    /// Set at the first PES packet of the stream
+    started: bool,
+    /// Last continuity counter by PID
+    continuity_counters: Vec<(u16, u8)>,
+    discontinuities: Vec<Discontinuity>,
+    /// Gaps in the current PES packet of the stream, to be checked against
+    /// its length when it ends
+    pending: Vec<usize>,
+    /// Length and received bytes of the current PES packet of the stream
+    pes_length: Option<usize>,
+    pes_received: usize,
+    pes_timestamp: Option<u64>,
+    first_timestamp: Option<u64>,
+    /// Time stamps of the PES packets of the stream, with `keep_timestamps`
+    timestamps: Option<Vec<PesTimestamp>>,
+    /// Elementary stream bytes demuxed before the current packet
+    stream_position: u64,
+    /// Elementary stream bytes of the current packet not read yet
+    payload: Vec<u8>,
+    consumed: usize,
+}
+
+impl<R: Read> TsDemuxer<R> {
+    /// Read the program association and map tables up to the AAC stream
+    /// with `pid`, or the first AAC stream if `None`
+    ///
+    /// Fails with `ErrorKind::InvalidData` if the input has no such
+    /// stream.
+    pub fn new(reader: R, pid: Option<u16>) -> io::Result<TsDemuxer<R>> {
+        let mut demuxer = TsDemuxer {
+            reader,
+            buffer: Vec::with_capacity(TS_PACKET_LEN * SYNC_CONFIRM),
+            packet: [0; TS_PACKET_LEN],
+            synced: false,
+            position: 0,
+            packet_offset: 0,
+            mux: MuxStats::default(),
+            last_pcr: None,
+            eof: false,
+            stream: AacStream {
+                program_number: 0,
+                pid: 0,
+                framing: AacFraming::Adts,
+                pcr_pid: NULL_PID,
+            },
+            program: Pmt {
+                program_number: 0,
+                pcr_pid: NULL_PID,
+                streams: Vec::new(),
+            },
+            started: false,
+            continuity_counters: Vec::new(),
+            discontinuities: Vec::new(),
+            pending: Vec::new(),
+            pes_length: None,
+            pes_received: 0,
+            pes_timestamp: None,
+            first_timestamp: None,
+            timestamps: None,
+            stream_position: 0,
+            payload: Vec::new(),
+            consumed: 0,
+        };
+        demuxer.stream = demuxer.find_stream(pid)?;
+        Ok(demuxer)
+    }
+
+    pub fn stream(&self) -> AacStream {
+        self.stream
+    }
+
+    /// The program map table of the program of the stream
+    pub fn program(&self) -> &Pmt {
+        &self.program
+    }
+
+    /// The first presentation time stamp of the stream read so far, in 90 kHz
+    /// ticks
+    pub fn first_timestamp(&self) -> Option<u64> {
+        self.first_timestamp
+    }
+
+    /// Keep the time stamp of every PES packet of the stream read from now
+    /// on, for `timestamps`
+    pub fn keep_timestamps(&mut self) {
+        self.timestamps.get_or_insert_with(Vec::new);
+    }
+
+    /// The time stamps of the PES packets read since `keep_timestamps`, in
+    /// stream order
+    pub fn timestamps(&self) -> &[PesTimestamp] {
+        self.timestamps.as_deref().unwrap_or(&[])
+    }
+
+    /// Packet counts and timing of the packets read so far
+    pub fn mux_stats(&self) -> &MuxStats {
+        &self.mux
+    }
+
+    /// The continuity counter jumps read so far
+    ///
+    /// The outcome of a gap is known once its PES packet ended.
+    pub fn discontinuities(&self) -> &[Discontinuity] {
+        &self.discontinuities
+    }
+
+    fn find_stream(&mut self, pid: Option<u16>) -> io::Result<AacStream> {
+        let mut pmt_pids = Vec::new();
+        // Sections being put together from several packets, by PID
+        let mut sections: Vec<(u16, Vec<u8>)> = Vec::new();
+        while self.next_packet()? {
+            let packet = self.packet;
+            let packet = match parse_packet(&packet) {
+                Some(packet) if !packet.transport_error => packet,
+                _ => continue,
+            };
+            if packet.pid != PAT_PID && !pmt_pids.contains(&packet.pid) {
+                continue;
+            }
+            let section = match collect_section(&mut sections, &packet) {
+                Some(section) => section,
+                None => continue,
+            };
+
+            if packet.pid == PAT_PID {
+                if let Some(programs) = parse_pat(&section) {
+                    pmt_pids = programs.iter().map(|program| program.pmt_pid).collect();
+                }
+                continue;
+            }
+            let pmt = match parse_pmt(&section) {
+                Some(pmt) => pmt,
+                None => continue,
+            };
+            for stream in pmt.streams.iter() {
+                if pid.is_some_and(|pid| pid != stream.pid) {
+                    continue;
+                }
+                match stream.aac_framing() {
+                    Some(framing) => {
+                        let stream = AacStream {
+                            program_number: pmt.program_number,
+                            pid: stream.pid,
+                            framing,
+                            pcr_pid: pmt.pcr_pid,
+                        };
+                        self.program = pmt;
+                        return Ok(stream);
+                    }
+                    None if pid.is_some() => {
+                        return Err(io::Error::new(
+                            ErrorKind::InvalidData,
+                            format!(
+                                "PID {} is not AAC, its stream type is 0x{:02X}",
+                                stream.pid, stream.stream_type
+                            ),
+                        ))
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        let message = match pid {
+            Some(pid) => format!("no stream with PID {} in the program map tables", pid),
+            None => "no AAC stream in the program map tables".to_string(),
+        };
+        Err(io::Error::new(ErrorKind::InvalidData, message))
+    }
+
+    /// Buffer at least `len` bytes, fewer only at the end of the input
+    fn fill(&mut self, len: usize) -> io::Result<()> {
+        let mut chunk = [0; TS_PACKET_LEN];
+        while !self.eof && self.buffer.len() < len {
+            let wanted = (len - self.buffer.len()).min(chunk.len());
+            match self.reader.read(&mut chunk[..wanted]) {
+                Ok(0) => self.eof = true,
+                Ok(read) => {
+                    self.buffer.extend_from_slice(&chunk[..read]);
+                    self.position += read as u64;
+                }
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the buffer has sync bytes one packet apart from its start
+    fn sync_confirmed(&self) -> bool {
+        (0..SYNC_CONFIRM)
+            .map(|packet| packet * TS_PACKET_LEN)
+            .take_while(|pos| *pos < self.buffer.len())
+            .all(|pos| self.buffer[pos] == TS_SYNC_BYTE)
+    }
+
+    /// Read the next packet into `packet`, returning false at the end of
+    /// the input
+    ///
+    /// After a packet without a sync byte, the next one has to be confirmed
+    /// by the packets following it.
+    fn next_packet(&mut self) -> io::Result<bool> {
+        loop {
+            self.fill(TS_PACKET_LEN * SYNC_CONFIRM)?;
+            if self.buffer.len() < TS_PACKET_LEN {
+                return Ok(false);
+            }
+            if (self.synced && self.buffer[0] == TS_SYNC_BYTE) || self.sync_confirmed() {
+                self.synced = true;
+                self.packet_offset = self.position - self.buffer.len() as u64;
+                self.packet.copy_from_slice(&self.buffer[..TS_PACKET_LEN]);
+                self.buffer.drain(..TS_PACKET_LEN);
+                self.count_packet();
+                return Ok(true);
+            }
+            self.synced = false;
+            let skip = self.buffer[1..]
+                .iter()
+                .position(|byte| *byte == TS_SYNC_BYTE)
+                .map_or(self.buffer.len(), |pos| pos + 1);
+            self.buffer.drain(..skip);
+        }
+    }
+
+    /// Add the current packet to the mux statistics
+    fn count_packet(&mut self) {
+        let packet = self.packet;
+        let packet = match parse_packet(&packet) {
+            Some(packet) => packet,
+            None => return,
+        };
+        self.mux.count(packet.pid);
+        let pcr = match packet.pcr {
+            Some(pcr) if packet.pid == self.stream.pcr_pid => pcr,
+            _ => return,
+        };
+        let packets = self.mux.total_packets();
+        if let Some((last, last_packets)) = self.last_pcr {
+            if !packet.discontinuity_indicator {
+                self.mux.pcr_packets += packets - last_packets;
+                self.mux.pcr_ticks += (pcr + PCR_WRAP - last) % PCR_WRAP;
+            }
+        }
+        self.last_pcr = Some((pcr, packets));
+    }
+
+    /// Take the elementary stream bytes of the current packet, if it is one
+    /// of the stream's
+    fn demux_packet(&mut self) {
+        let packet = self.packet;
+        let packet = match parse_packet(&packet) {
+            Some(packet) if !packet.transport_error && !packet.payload.is_empty() => packet,
+            _ => return,
+        };
+        // A packet may be sent twice in a row, with the same counter
+        let lost = match self.continuity(&packet) {
+            Some(lost) => lost,
+            None => return,
+        };
+        if packet.pid != self.stream.pid {
+            if lost != 0 {
+                self.gap(&packet, lost, None, GapOutcome::Unknown);
+            }
+            return;
+        }
+
+        if packet.payload_unit_start {
+            let missing = self.finish_pes();
+            if lost != 0 && self.started {
+                let outcome = match missing {
+                    Some(Some(missing)) if missing > 0 => GapOutcome::Lost(Some(missing as u64)),
+                    Some(None) => GapOutcome::Lost(None),
+                    _ => GapOutcome::Unknown,
+                };
+                self.gap(&packet, lost, self.time(), outcome);
+            }
+            self.started = true;
+            self.pes_length = pes_length(packet.payload);
+            self.pes_timestamp = pes_timestamp(packet.payload);
+            if self.first_timestamp.is_none() {
+                self.first_timestamp = self.pes_timestamp;
+            }
+            if let (Some(timestamps), Some(pts)) = (self.timestamps.as_mut(), self.pes_timestamp) {
+                timestamps.push(PesTimestamp {
+                    stream_offset: self.stream_position,
+                    pts,
+                });
+            }
+        } else if lost != 0 && self.started {
+            self.pending.push(self.discontinuities.len());
+            self.gap(&packet, lost, self.time(), GapOutcome::Unknown);
+        }
+        self.pes_received += packet.payload.len();
+
+        let data = if packet.payload_unit_start {
+            pes_payload(packet.payload).unwrap_or(&[])
+        } else if self.started {
+            packet.payload
+        } else {
+            &[]
+        };
+        self.payload.clear();
+        self.payload.extend_from_slice(data);
+        self.consumed = 0;
+        self.stream_position += data.len() as u64;
+    }
+
+    /// Check the continuity counter of `packet` against the last one of
+    /// its PID, returning the number of lost packets or `None` for a
+    /// repeated packet
+    fn continuity(&mut self, packet: &TsPacket) -> Option<u8> {
+        if packet.pid == NULL_PID {
+            return Some(0);
+        }
+        let counter = packet.continuity_counter;
+        let index = self
+            .continuity_counters
+            .iter()
+            .position(|(pid, _)| *pid == packet.pid);
+        let index = match index {
+            Some(index) => index,
+            None => {
+                self.continuity_counters.push((packet.pid, counter));
+                return Some(0);
+            }
+        };
+        let last = std::mem::replace(&mut self.continuity_counters[index].1, counter);
+        if last == counter {
+            return None;
+        }
+        if packet.discontinuity_indicator {
+            return Some(0);
+        }
+        Some(counter.wrapping_sub(last).wrapping_sub(1) & 0xF)
+    }
+
+    fn gap(&mut self, packet: &TsPacket, lost: u8, time: Option<f64>, outcome: GapOutcome) {
+        self.discontinuities.push(Discontinuity {
+            pid: packet.pid,
+            offset: self.packet_offset,
+            stream_offset: self.stream_position,
+            lost,
+            time,
+            outcome,
+        });
+    }
+
+    /// Seconds from the first time stamp of the stream to the one of the
+    /// current PES packet
+    fn time(&self) -> Option<f64> {
+        let ticks = self.pes_timestamp?.wrapping_sub(self.first_timestamp?) & ((1 << 33) - 1);
+        Some(ticks as f64 / 90_000.0)
+    }
+
+    /// End the current PES packet of the stream, settling the outcome of
+    /// its gaps, and return how many of its bytes are missing
+    ///
+    /// Returns `None` for a packet without a length and `Some(None)` for one
+    /// with more bytes than its length.
+    fn finish_pes(&mut self) -> Option<Option<usize>> {
+        let received = std::mem::replace(&mut self.pes_received, 0);
+        let missing = self
+            .pes_length
+            .take()
+            .map(|length| length.checked_sub(received));
+        for index in self.pending.drain(..) {
+            self.discontinuities[index].outcome = match missing {
+                Some(Some(0)) => GapOutcome::Recovered,
+                Some(missing) => GapOutcome::Lost(missing.map(|missing| missing as u64)),
+                None => GapOutcome::Unknown,
+            };
+        }
+        missing
+    }
+}
+
+/// Add the packet's payload to the section of its PID, returning the
+/// section once it is complete
+///
+/// Only the first section starting in a packet is kept, the tables read
+/// here have a single section each.
+fn collect_section(sections: &mut Vec<(u16, Vec<u8>)>, packet: &TsPacket) -> Option<Vec<u8>> {
+    let index = match sections.iter().position(|(pid, _)| *pid == packet.pid) {
+        Some(index) => index,
+        None => {
+            sections.push((packet.pid, Vec::new()));
+            sections.len() - 1
+        }
+    };
+    let section = &mut sections[index].1;
+    if packet.payload_unit_start {
+        let pointer = usize::from(*packet.payload.first()?);
+        section.clear();
+        section.extend_from_slice(packet.payload.get(1 + pointer..)?);
+    } else if !section.is_empty() {
+        section.extend_from_slice(packet.payload);
+    }
+
+    let length = section_length(section)?;
+    if section.len() < length {
+        return None;
+    }
+    let complete = section[..length].to_vec();
+    section.clear();
+    Some(complete)
+}
+
+impl<R: Read> Read for TsDemuxer<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.consumed == self.payload.len() {
+            if !self.next_packet()? {
+                self.finish_pes();
+                return Ok(0);
+            }
+            self.payload.clear();
+            self.consumed = 0;
+            self.demux_packet();
+        }
+        let len = buf.len().min(self.payload.len() - self.consumed);
+        buf[..len].copy_from_slice(&self.payload[self.consumed..self.consumed + len]);
+        self.consumed += len;
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::tests::adts_frame;
+    use crate::AdtsFrames;
+
+    const PMT_PID: u16 = 0x100;
+    const AUDIO_PID: u16 = 0x101;
+
+    /// A section of `table_id` with the long header of version 0 and a
+    /// valid CRC-32 around `fields`
+    fn section(table_id: u8, fields: &[u8]) -> Vec<u8> {
+        let length = 2 + 3 + fields.len() + 4;
+        let mut section = vec![table_id, 0xB0 | (length >> 8) as u8, length as u8];
+        section.extend_from_slice(&[0x00, 0x01, 0xC1, 0x00, 0x00]);
+        section.extend_from_slice(fields);
+        let crc = crc32(0xFFFF_FFFF, &section);
+        section.extend_from_slice(&crc.to_be_bytes());
+        section
+    }
+
+    fn pat() -> Vec<u8> {
+        section(
+            0x00,
+            &[0x00, 0x01, 0xE0 | (PMT_PID >> 8) as u8, PMT_PID as u8],
+        )
+    }
+
+    /// The map of program 1, whose number takes the place of the
+    /// transport stream ID of `section`, with one ADTS stream that also
+    /// carries the PCR
+    fn pmt() -> Vec<u8> {
+        let pid = [0xE0 | (AUDIO_PID >> 8) as u8, AUDIO_PID as u8];
+        section(
+            0x02,
+            &[pid[0], pid[1], 0xF0, 0x00, 0x0F, pid[0], pid[1], 0xF0, 0x00],
+        )
+    }
+
+    /// A packet of `pid` carrying `payload`, stuffed with an adaptation
+    /// field to the full length
+    fn packet(pid: u16, start: bool, counter: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![
+            TS_SYNC_BYTE,
+            if start { 0x40 } else { 0x00 } | (pid >> 8) as u8,
+            pid as u8,
+        ];
+        let stuffing = TS_PACKET_LEN - 4 - payload.len();
+        if stuffing == 0 {
+            packet.push(0x10 | counter);
+        } else {
+            packet.push(0x30 | counter);
+            packet.push(stuffing as u8 - 1);
+            if stuffing > 1 {
+                packet.push(0x00);
+                packet.resize(TS_PACKET_LEN - payload.len(), 0xFF);
+            }
+        }
+        packet.extend_from_slice(payload);
+        packet
+    }
+
+    /// A PES packet of an audio stream with the time stamp `pts`
+    fn pes(pts: u64, data: &[u8]) -> Vec<u8> {
+        let length = 3 + 5 + data.len();
+        let mut pes = vec![0x00, 0x00, 0x01, 0xC0, (length >> 8) as u8, length as u8];
+        pes.extend_from_slice(&[
+            0x80,
+            0x80,
+            0x05,
+            0x21 | (pts >> 29) as u8 & 0x0E,
+            (pts >> 22) as u8,
+            0x01 | (pts >> 14) as u8 & 0xFE,
+            (pts >> 7) as u8,
+            0x01 | (pts << 1) as u8 & 0xFE,
+        ]);
+        pes.extend_from_slice(data);
+        pes
+    }
+
+    /// A transport stream with one ADTS stream whose PES packets each
+    /// carry the frames of one entry of `packets` with its time stamp
+    fn transport_stream(packets: &[(u64, Vec<u8>)]) -> Vec<u8> {
+        let table = |table: Vec<u8>| [&[0][..], &table].concat();
+        let mut ts = packet(PAT_PID, true, 0, &table(pat()));
+        ts.extend(packet(PMT_PID, true, 0, &table(pmt())));
+        let mut counter = 0;
+        for (pts, data) in packets {
+            let pes = pes(*pts, data);
+            for (index, chunk) in pes.chunks(TS_PACKET_LEN - 4).enumerate() {
+                ts.extend(packet(AUDIO_PID, index == 0, counter, chunk));
+                counter = (counter + 1) & 0x0F;
+            }
+        }
+        ts
+    }
+
+    #[test]
+    fn parses_packet_with_pcr() {
+        let mut data = packet(AUDIO_PID, true, 5, &[0xAB; 176]);
+        // PCR flag and a base of 2 with an extension of 3
+        data[5..12].copy_from_slice(&[0x10, 0x00, 0x00, 0x00, 0x01, 0x00, 0x03]);
+        let packet = parse_packet(&data).unwrap();
+        assert_eq!(packet.pid, AUDIO_PID);
+        assert!(packet.payload_unit_start);
+        assert!(!packet.transport_error);
+        assert_eq!(packet.continuity_counter, 5);
+        assert_eq!(packet.pcr, Some(2 * 300 + 3));
+        assert_eq!(packet.payload, &[0xAB; 176][..]);
+
+        assert!(parse_packet(&data[..TS_PACKET_LEN - 1]).is_none());
+        data[0] = 0x48;
+        assert!(parse_packet(&data).is_none());
+    }
+
+    #[test]
+    fn tables_need_a_valid_crc() {
+        let programs = parse_pat(&pat()).unwrap();
+        assert_eq!(
+            programs,
+            vec![Program {
+                program_number: 1,
+                pmt_pid: PMT_PID,
+            }]
+        );
+        let pmt = parse_pmt(&pmt()).unwrap();
+        assert_eq!(pmt.pcr_pid, AUDIO_PID);
+        assert_eq!(pmt.streams.len(), 1);
+        assert_eq!(pmt.streams[0].aac_framing(), Some(AacFraming::Adts));
+
+        let mut broken = pat();
+        broken[9] ^= 0x01;
+        assert!(parse_pat(&broken).is_none());
+        assert!(parse_pmt(&pat()).is_none());
+    }
+
+    #[test]
+    fn reads_pes_time_stamps() {
+        let pes = pes(0x1_2345_6789, &[0xFF, 0xF1]);
+        assert_eq!(pes_timestamp(&pes), Some(0x1_2345_6789));
+        assert_eq!(pes_payload(&pes), Some(&[0xFF, 0xF1][..]));
+        assert_eq!(pes_length(&pes), Some(pes.len()));
+    }
+
+    #[test]
+    fn timestamp_difference_takes_the_wrap() {
+        assert_eq!(timestamp_difference(1000, 4600), 3600);
+        assert_eq!(timestamp_difference(4600, 1000), -3600);
+        assert_eq!(timestamp_difference(PTS_WRAP - 1800, 1800), 3600);
+        assert_eq!(timestamp_difference(1800, PTS_WRAP - 1800), -3600);
+    }
+
+    #[test]
+    fn demuxes_adts_frames() {
+        let frames = [adts_frame(200, false), adts_frame(150, false)].concat();
+        let ts = transport_stream(&[(900, frames.clone()), (900 + 4180, frames)]);
+
+        let mut demuxer = TsDemuxer::new(&ts[..], None).unwrap();
+        demuxer.keep_timestamps();
+        assert_eq!(demuxer.stream().pid, AUDIO_PID);
+        assert_eq!(demuxer.stream().program_number, 1);
+
+        let lengths: Vec<u16> = AdtsFrames::new(&mut demuxer)
+            .map(|frame| frame.unwrap().header.frame_length)
+            .collect();
+        assert_eq!(lengths, [200, 150, 200, 150]);
+        assert_eq!(demuxer.first_timestamp(), Some(900));
+        let timestamps: Vec<(u64, u64)> = demuxer
+            .timestamps()
+            .iter()
+            .map(|timestamp| (timestamp.stream_offset, timestamp.pts))
+            .collect();
+        assert_eq!(timestamps, [(0, 900), (350, 900 + 4180)]);
+        assert!(demuxer.discontinuities().is_empty());
+    }
+
+    #[test]
+    fn needs_an_aac_stream() {
+        let ts = transport_stream(&[(900, adts_frame(200, false))]);
+        let err = TsDemuxer::new(&ts[..], Some(0x200)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
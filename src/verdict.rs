@@ -0,0 +1,219 @@
+use crate::timefmt::TimeFormat;
+
+/// How the walk over the input ended
+#[derive(Debug, Clone, Copy)]
+pub enum Ending {
+    /// The input ended exactly after the last frame
+    Clean,
+    /// The input ended inside the frame or header starting at `offset`
+    Truncated { offset: u64 },
+    /// No valid header was found where the next frame should start
+    Corrupt { offset: u64, time: f64 },
+    /// Reading the input failed
+    ReadError,
+}
+
+/// A change of stream parameters between two consecutive frames
+#[derive(Debug)]
+pub struct ParameterChange {
+    pub offset: u64,
+    pub time: f64,
+    pub description: String,
+}
+
+/// Corrupt data skipped to find the next frame
+#[derive(Debug)]
+pub struct Resync {
+    /// Offset where a header was expected
+    pub offset: u64,
+    pub time: f64,
+    /// Number of bytes skipped up to the next frame
+    pub skipped: u64,
+}
+
+/// Everything collected during a run that the verdict is based on
+pub struct Findings {
+    pub frames: usize,
+    pub ending: Ending,
+    pub parameter_changes: Vec<ParameterChange>,
+    pub resyncs: Vec<Resync>,
+    /// Number of frames with header fields an encoder should never write
+    pub header_warnings: u64,
+    /// Number of frames whose header reads as MPEG audio rather than ADTS
+    pub mpeg_audio_frames: usize,
+    pub loops: usize,
+    /// Number of frames whose CRC did not match their contents
+    pub crc_mismatches: u64,
+    /// Number of frames whose raw data contradicts their channel
+    /// configuration
+    pub element_mismatches: u64,
+    /// Bytes of the frames, to weigh the bytes skipped by resyncs against
+    pub frame_bytes: u64,
+    /// Largest deviation of the bitrate over a second from the average, as
+    /// a share of the average, `None` if the stream is too short
+    pub bitrate_deviation: Option<f64>,
+}
+
+/// One classification of the stream with the evidence supporting it
+pub struct Verdict {
+    pub classification: String,
+    pub evidence: Vec<String>,
+}
+
+/// Only this many parameter changes or resyncs are listed as evidence
+const MAX_CHANGE_EVIDENCE: usize = 3;
+
+/// Classify the stream from the findings of a run
+///
+/// Returns a single "healthy" verdict if nothing suspicious was found.
+pub fn classify(findings: &Findings, time_format: &TimeFormat) -> Vec<Verdict> {
+    let mut verdicts = Vec::new();
+
+    if findings.frames == 0 {
+        verdicts.push(Verdict {
+            classification: "no ADTS stream found".to_string(),
+            evidence: vec!["not a single valid frame was read".to_string()],
+        });
+        return verdicts;
+    }
+
+    match findings.ending {
+        Ending::Clean => {}
+        Ending::Truncated { offset } => verdicts.push(Verdict {
+            classification: "truncated recording".to_string(),
+            evidence: vec![format!(
+                "input ends inside the frame starting at offset {}",
+                offset
+            )],
+        }),
+        Ending::Corrupt { offset, time } => verdicts.push(Verdict {
+            classification: format!(
+                "corrupt or lost data around {}",
+                time_format.format_clock(time)
+            ),
+            evidence: vec![format!(
+                "no valid header at offset {} after {} good frames",
+                offset, findings.frames
+            )],
+        }),
+        Ending::ReadError => verdicts.push(Verdict {
+            classification: "unreadable input".to_string(),
+            evidence: vec!["reading the input failed before its end".to_string()],
+        }),
+    }
+
+    if let Some(first) = findings.resyncs.first() {
+        let mut evidence: Vec<String> = findings
+            .resyncs
+            .iter()
+            .take(MAX_CHANGE_EVIDENCE)
+            .map(|resync| {
+                format!(
+                    "{} bytes skipped at offset {} ({})",
+                    resync.skipped,
+                    resync.offset,
+                    time_format.format_clock(resync.time)
+                )
+            })
+            .collect();
+        if findings.resyncs.len() > MAX_CHANGE_EVIDENCE {
+            evidence.push(format!(
+                "and {} more corrupt regions",
+                findings.resyncs.len() - MAX_CHANGE_EVIDENCE
+            ));
+        }
+        verdicts.push(Verdict {
+            classification: format!(
+                "corrupt or lost data around {}",
+                time_format.format_clock(first.time)
+            ),
+            evidence,
+        });
+    }
+
+    if !findings.parameter_changes.is_empty() {
+        let mut evidence: Vec<String> = findings
+            .parameter_changes
+            .iter()
+            .take(MAX_CHANGE_EVIDENCE)
+            .map(|change| {
+                format!(
+                    "{} at offset {} ({})",
+                    change.description,
+                    change.offset,
+                    time_format.format_clock(change.time)
+                )
+            })
+            .collect();
+        if findings.parameter_changes.len() > MAX_CHANGE_EVIDENCE {
+            evidence.push(format!(
+                "and {} more parameter changes",
+                findings.parameter_changes.len() - MAX_CHANGE_EVIDENCE
+            ));
+        }
+        verdicts.push(Verdict {
+            classification: "spliced from two sources".to_string(),
+            evidence,
+        });
+    }
+
+    if findings.mpeg_audio_frames > 0 {
+        verdicts.push(Verdict {
+            classification: "MPEG audio misdetected as ADTS".to_string(),
+            evidence: vec![format!(
+                "{} of {} frames have MPEG audio layer bits and plausible MPEG audio \
+                 bitrate and sampling rate fields",
+                findings.mpeg_audio_frames, findings.frames
+            )],
+        });
+    }
+
+    if findings.crc_mismatches > 0 {
+        verdicts.push(Verdict {
+            classification: "corrupted frames".to_string(),
+            evidence: vec![format!(
+                "{} of {} frames fail their CRC check",
+                findings.crc_mismatches, findings.frames
+            )],
+        });
+    }
+
+    if findings.element_mismatches > 0 {
+        verdicts.push(Verdict {
+            classification: "payload contradicts the channel configuration".to_string(),
+            evidence: vec![format!(
+                "{} of {} frames start with other syntactic elements than their channel \
+                 configuration signals",
+                findings.element_mismatches, findings.frames
+            )],
+        });
+    }
+
+    if findings.header_warnings > 0 {
+        verdicts.push(Verdict {
+            classification: "encoder header bug".to_string(),
+            evidence: vec![format!(
+                "{} of {} frames have invalid header fields",
+                findings.header_warnings, findings.frames
+            )],
+        });
+    }
+
+    if findings.loops > 0 {
+        verdicts.push(Verdict {
+            classification: "looping content".to_string(),
+            evidence: vec![format!("{} repeating sequences detected", findings.loops)],
+        });
+    }
+
+    if verdicts.is_empty() {
+        verdicts.push(Verdict {
+            classification: "healthy".to_string(),
+            evidence: vec![format!(
+                "{} frames read up to the end of the input",
+                findings.frames
+            )],
+        });
+    }
+    verdicts
+}
@@ -0,0 +1,360 @@
+use std::io::{self, Read};
+
+use crate::dca;
+use crate::dolby;
+use crate::junk::{self, Junk};
+use crate::loas;
+use crate::mp3;
+use crate::pages;
+use crate::xiph;
+
+/// Frames longer than this are never looked for, it is enough for every
+/// supported format
+const WINDOW_LEN: usize = 1 << 20;
+
+/// How far measured totals may be off the advertised ones, relative to them
+const TOTALS_TOLERANCE: f64 = 0.01;
+
+/// Parses the frame at the start of the data, if there is one
+///
+/// Parsers may keep state from earlier frames, like the LOAS
+/// StreamMuxConfig that later frames refer back to.
+pub type ParseFrame = Box<dyn FnMut(&[u8]) -> Option<Frame>>;
+
+/// Framing of the input, ADTS unless given
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputFormat {
+    Adts,
+    Mp3,
+    /// AC-3 and E-AC-3
+    Ac3,
+    /// DTS core frames and DTS-HD extension substreams
+    Dts,
+    Flac,
+    /// AAC in the LOAS sync layer with LATM multiplexing
+    Loas,
+    /// MPEG transport stream carrying AAC in ADTS or LOAS
+    Ts,
+    Ogg,
+}
+
+impl InputFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            InputFormat::Adts => "ADTS",
+            InputFormat::Mp3 => "MPEG audio",
+            InputFormat::Ac3 => "AC-3",
+            InputFormat::Dts => "DTS",
+            InputFormat::Flac => "FLAC",
+            InputFormat::Loas => "LOAS",
+            InputFormat::Ts => "MPEG-TS",
+            InputFormat::Ogg => "Ogg",
+        }
+    }
+
+    /// Frame parser for formats walked by `walk`, ADTS has its own scan
+    /// and transport streams are demuxed first
+    pub fn parser(self) -> Option<ParseFrame> {
+        match self {
+            InputFormat::Adts | InputFormat::Ts => None,
+            InputFormat::Mp3 => Some(Box::new(mp3::parse_frame)),
+            InputFormat::Ac3 => Some(Box::new(dolby::parse_frame)),
+            InputFormat::Dts => Some(Box::new(dca::parse_frame)),
+            InputFormat::Flac => Some(Box::new(xiph::parse_frame)),
+            InputFormat::Loas => Some(Box::new(loas::parser())),
+            InputFormat::Ogg => Some(Box::new(pages::parser())),
+        }
+    }
+}
+
+pub fn parse_input_format(value: &str) -> Result<InputFormat, String> {
+    match value {
+        "adts" => Ok(InputFormat::Adts),
+        "mp3" => Ok(InputFormat::Mp3),
+        "ac3" | "eac3" => Ok(InputFormat::Ac3),
+        "dts" => Ok(InputFormat::Dts),
+        "flac" => Ok(InputFormat::Flac),
+        "loas" | "latm" => Ok(InputFormat::Loas),
+        "ts" | "mpegts" => Ok(InputFormat::Ts),
+        "ogg" => Ok(InputFormat::Ogg),
+        _ => Err(format!(
+            "unknown input format '{}', use adts, mp3, ac3, dts, flac, loas, ts or ogg",
+            value
+        )),
+    }
+}
+
+/// A frame found by a format parser
+pub struct Frame {
+    /// Length in bytes, including the header
+    pub length: usize,
+    /// Duration in seconds
+    pub duration: f64,
+    /// Human readable lines describing the frame
+    pub lines: Vec<String>,
+    /// Problems found in the frame, by kind of warning
+    pub warnings: Vec<(&'static str, String)>,
+    /// Totals of the stream the frame carries in place of audio
+    pub advertised: Option<Advertised>,
+}
+
+/// Totals of a stream advertised at its start, like in the Xing header of
+/// MPEG audio
+#[derive(Debug, Clone, Copy)]
+pub struct Advertised {
+    /// Where the totals come from, like "Xing"
+    pub source: &'static str,
+    /// Frames after the one advertising
+    pub frames: Option<u64>,
+    /// Bytes of the frames, including the one advertising
+    pub bytes: Option<u64>,
+}
+
+/// Compares the totals advertised by a frame with the frames that follow
+pub struct TotalsCheck {
+    offset: u64,
+    advertised: Advertised,
+    /// Duration of the frame advertising, which all frames have
+    frame_duration: f64,
+    /// Frames after the one advertising
+    frames: u64,
+    /// Bytes of these frames
+    bytes: u64,
+    /// Length of the frame advertising
+    first_length: u64,
+    duration: f64,
+}
+
+impl TotalsCheck {
+    pub fn new(offset: u64, frame: &Frame, advertised: Advertised) -> TotalsCheck {
+        TotalsCheck {
+            offset,
+            advertised,
+            frame_duration: frame.duration,
+            frames: 0,
+            bytes: 0,
+            first_length: frame.length as u64,
+            duration: 0.0,
+        }
+    }
+
+    /// Count a frame that follows the one advertising
+    pub fn push(&mut self, frame: &Frame) {
+        self.frames += 1;
+        self.bytes += frame.length as u64;
+        self.duration += frame.duration;
+    }
+
+    /// Offset of the frame advertising
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn source(&self) -> &'static str {
+        self.advertised.source
+    }
+
+    /// Line comparing the advertised totals with the measured ones
+    pub fn describe(&self) -> String {
+        let mut advertised = Vec::new();
+        let mut measured = Vec::new();
+        if let Some(frames) = self.advertised.frames {
+            advertised.push(format!("{} frames", frames));
+            measured.push(format!("{} frames", self.frames));
+        }
+        if let Some(bytes) = self.advertised.bytes {
+            advertised.push(format!("{} bytes", bytes));
+            measured.push(format!("{} bytes", self.first_length + self.bytes));
+        }
+        if let Some(bitrate) = self.advertised_bitrate() {
+            advertised.push(format!("{:.1} kbit/s", bitrate / 1000.0));
+            measured.push(format!("{:.1} kbit/s", self.measured_bitrate() / 1000.0));
+        }
+        format!(
+            "{} header advertises {}, measured {}",
+            self.advertised.source,
+            advertised.join(", "),
+            measured.join(", ")
+        )
+    }
+
+    /// The totals that are off the advertised ones by more than
+    /// `TOTALS_TOLERANCE`, described
+    pub fn mismatches(&self) -> Vec<String> {
+        let mut found = Vec::new();
+        let off = |advertised: f64, measured: f64| {
+            (measured - advertised).abs() > advertised * TOTALS_TOLERANCE
+        };
+        if let Some(frames) = self.advertised.frames {
+            if off(frames as f64, self.frames as f64) {
+                found.push(format!(
+                    "{} frames advertised, {} found",
+                    frames, self.frames
+                ));
+            }
+        }
+        if let Some(bytes) = self.advertised.bytes {
+            let measured = self.first_length + self.bytes;
+            if off(bytes as f64, measured as f64) {
+                found.push(format!("{} bytes advertised, {} found", bytes, measured));
+            }
+        }
+        if let Some(bitrate) = self.advertised_bitrate() {
+            let measured = self.measured_bitrate();
+            if off(bitrate, measured) {
+                found.push(format!(
+                    "{:.1} kbit/s advertised, {:.1} kbit/s measured",
+                    bitrate / 1000.0,
+                    measured / 1000.0
+                ));
+            }
+        }
+        found
+    }
+
+    /// Average bitrate of the frames after the one advertising, by the
+    /// advertised totals
+    fn advertised_bitrate(&self) -> Option<f64> {
+        let frames = self.advertised.frames.filter(|&frames| frames > 0)?;
+        let bytes = self.advertised.bytes?.checked_sub(self.first_length)?;
+        Some(bytes as f64 * 8.0 / (frames as f64 * self.frame_duration))
+    }
+
+    fn measured_bitrate(&self) -> f64 {
+        if self.duration > 0.0 {
+            self.bytes as f64 * 8.0 / self.duration
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Everything known at the end of a walk
+pub struct Walk {
+    pub frames: usize,
+    pub duration: f64,
+    pub junk: Vec<Junk>,
+    /// Offset of a frame the input ends inside of
+    pub truncated: Option<u64>,
+}
+
+/// Walk the frames of `input`, which starts at `offset`, calling `on_frame`
+/// with the offset, start time and the frame for each
+///
+/// Data between frames is skipped as junk. After junk, and for the first
+/// frame, a frame only counts if the next one parses too, so a sync pattern
+/// inside the junk is not taken for a frame.
+pub fn walk<R: Read>(
+    input: R,
+    offset: u64,
+    mut parse: ParseFrame,
+    mut on_frame: impl FnMut(u64, f64, &Frame),
+) -> io::Result<Walk> {
+    let mut window = Window::new(input, offset);
+    let mut walk = Walk {
+        frames: 0,
+        duration: 0.0,
+        junk: Vec::new(),
+        truncated: None,
+    };
+    // Start offset and first bytes of the junk being skipped
+    let mut junk: Option<(u64, Vec<u8>)> = None;
+
+    loop {
+        let data = window.fill()?;
+        if data.is_empty() {
+            break;
+        }
+        let at_end = data.len() < WINDOW_LEN;
+        let frame = parse(data).filter(|frame| {
+            let synced = walk.frames > 0 && junk.is_none();
+            synced
+                || frame.length >= data.len()
+                || parse(&data[frame.length..]).is_some()
+                || (at_end && data.len() - frame.length < 4)
+        });
+
+        match frame {
+            Some(frame) if frame.length > data.len() => {
+                walk.truncated = Some(window.offset);
+                break;
+            }
+            Some(frame) => {
+                if let Some((start, sample)) = junk.take() {
+                    walk.junk
+                        .push(Junk::new(start, window.offset - start, &sample));
+                }
+                on_frame(window.offset, walk.duration, &frame);
+                walk.frames += 1;
+                walk.duration += frame.duration;
+                window.consume(frame.length);
+            }
+            None => {
+                let byte = data[0];
+                let offset = window.offset;
+                let (_, sample) = junk.get_or_insert_with(|| (offset, Vec::new()));
+                if sample.len() < junk::SAMPLE_LEN {
+                    sample.push(byte);
+                }
+                window.consume(1);
+            }
+        }
+    }
+
+    if let Some((start, sample)) = junk {
+        walk.junk
+            .push(Junk::new(start, window.offset - start, &sample));
+    }
+    Ok(walk)
+}
+
+/// Buffer over the input that always holds `WINDOW_LEN` bytes from the
+/// current position, unless the input ends before
+struct Window<R: Read> {
+    input: R,
+    buffer: Vec<u8>,
+    /// Position of the current offset in `buffer`
+    start: usize,
+    /// Offset of `buffer[start]` in the input
+    offset: u64,
+    eof: bool,
+}
+
+impl<R: Read> Window<R> {
+    fn new(input: R, offset: u64) -> Window<R> {
+        Window {
+            input,
+            buffer: Vec::new(),
+            start: 0,
+            offset,
+            eof: false,
+        }
+    }
+
+    fn fill(&mut self) -> io::Result<&[u8]> {
+        if self.buffer.len() - self.start < WINDOW_LEN && !self.eof {
+            self.buffer.drain(..self.start);
+            self.start = 0;
+            let mut chunk = [0; 64 * 1024];
+            while self.buffer.len() < 2 * WINDOW_LEN {
+                let read = match self.input.read(&mut chunk) {
+                    Ok(read) => read,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err),
+                };
+                if read == 0 {
+                    self.eof = true;
+                    break;
+                }
+                self.buffer.extend_from_slice(&chunk[..read]);
+            }
+        }
+        let end = self.buffer.len().min(self.start + WINDOW_LEN);
+        Ok(&self.buffer[self.start..end])
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.start += len;
+        self.offset += len as u64;
+    }
+}
@@ -0,0 +1,118 @@
+/// A warning that was printed and is being collapsed while it repeats
+struct Repeat {
+    kind: &'static str,
+    message: String,
+    count: u64,
+    last_offset: u64,
+    /// Frame ID of the frame at `last_offset`, with --frame-ids
+    last_frame_id: Option<String>,
+}
+
+struct KindCount {
+    kind: &'static str,
+    seen: u64,
+    printed: u64,
+}
+
+/// Prints warnings to stderr, collapsing consecutive repeats and limiting
+/// how often each kind of warning is printed
+pub struct Warnings {
+    /// Maximum number of printed warnings per kind
+    limit: Option<u64>,
+    last: Option<Repeat>,
+    kinds: Vec<KindCount>,
+}
+
+impl Warnings {
+    pub fn new(limit: Option<u64>) -> Warnings {
+        Warnings {
+            limit,
+            last: None,
+            kinds: Vec::new(),
+        }
+    }
+
+    /// Report a warning of the given kind for the data at `offset`
+    pub fn warn(&mut self, kind: &'static str, offset: u64, message: String) {
+        self.warn_frame(kind, offset, None, message);
+    }
+
+    /// Report a warning of the given kind for the frame at `offset`, naming
+    /// it by its frame ID if there is one
+    pub fn warn_frame(
+        &mut self,
+        kind: &'static str,
+        offset: u64,
+        frame_id: Option<&str>,
+        message: String,
+    ) {
+        if let Some(last) = self.last.as_mut() {
+            if last.kind == kind && last.message == message {
+                last.count += 1;
+                last.last_offset = offset;
+                last.last_frame_id = frame_id.map(str::to_string);
+                return;
+            }
+        }
+        self.flush_repeat();
+
+        let limit = self.limit;
+        let entry = match self.kinds.iter().position(|k| k.kind == kind) {
+            Some(idx) => &mut self.kinds[idx],
+            None => {
+                self.kinds.push(KindCount {
+                    kind,
+                    seen: 0,
+                    printed: 0,
+                });
+                self.kinds.last_mut().unwrap()
+            }
+        };
+        entry.seen += 1;
+        if limit.is_some_and(|limit| entry.printed >= limit) {
+            return;
+        }
+        entry.printed += 1;
+
+        match frame_id {
+            Some(id) => eprintln!("warning: at offset {} (frame {}): {}", offset, id, message),
+            None => eprintln!("warning: at offset {}: {}", offset, message),
+        }
+        self.last = Some(Repeat {
+            kind,
+            message,
+            count: 0,
+            last_offset: offset,
+            last_frame_id: frame_id.map(str::to_string),
+        });
+    }
+
+    /// Flush pending repeats and summarize suppressed warnings
+    pub fn finish(&mut self) {
+        self.flush_repeat();
+
+        for count in self.kinds.iter() {
+            if count.seen > count.printed {
+                eprintln!(
+                    "warning: {} further '{}' warnings suppressed",
+                    count.seen - count.printed,
+                    count.kind
+                );
+            }
+        }
+    }
+
+    fn flush_repeat(&mut self) {
+        if let Some(last) = self.last.take() {
+            if last.count > 0 {
+                let frame = last
+                    .last_frame_id
+                    .map_or_else(String::new, |id| format!(" (frame {})", id));
+                eprintln!(
+                    "warning: previous warning repeated {} times until offset {}{}",
+                    last.count, last.last_offset, frame
+                );
+            }
+        }
+    }
+}
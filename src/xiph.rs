@@ -0,0 +1,137 @@
+use streamreader::crc::crc16;
+use streamreader::flac::{self, FLAC_MARKER, METADATA_HEADER_LEN};
+
+use crate::walk::Frame;
+
+/// Length of the CRC-16 at the end of every frame
+const FOOTER_LEN: usize = 2;
+
+/// Parse the FLAC frame, or the stream marker with the metadata blocks, at
+/// the start of `data`
+///
+/// Frames do not state their length, a frame ends where a frame header
+/// follows that its CRC-16 matches up to. If no such header follows, the
+/// frame ends at the first following header, or at the end of the data.
+///
+/// Frames taking the sample rate from STREAMINFO have no duration.
+pub fn parse_frame(data: &[u8]) -> Option<Frame> {
+    if data.starts_with(&FLAC_MARKER) {
+        return parse_metadata(data);
+    }
+    let header = flac::parse_frame_header(data)?;
+
+    let mut crc = 0;
+    let mut checked = 0;
+    let mut first_header = None;
+    let mut end = None;
+    for pos in header.header_length + FOOTER_LEN..data.len().saturating_sub(1) {
+        if data[pos] != 0xFF || data[pos + 1] & 0xFE != 0xF8 {
+            continue;
+        }
+        if flac::parse_frame_header(&data[pos..]).is_none() {
+            continue;
+        }
+        crc = crc16(crc, &data[checked..pos]);
+        checked = pos;
+        first_header.get_or_insert(pos);
+        if crc == 0 {
+            end = Some(pos);
+            break;
+        }
+    }
+    let (length, crc_valid) = match (end, first_header) {
+        (Some(end), _) => (end, true),
+        (None, Some(first)) => (first, false),
+        (None, None) => (data.len(), crc16(crc, &data[checked..]) == 0),
+    };
+
+    let mut lines = vec![format!("Len is {}", length)];
+    if header.variable_block_size {
+        lines.push(format!(
+            "Blocking strategy is variable, first sample {}",
+            header.number
+        ));
+    } else {
+        lines.push(format!(
+            "Blocking strategy is fixed, frame {}",
+            header.number
+        ));
+    }
+    lines.push(format!("Block size is {} samples", header.block_size));
+    match header.sample_rate {
+        Some(rate) => lines.push(format!("Sampling frequency is {} Hz", rate)),
+        None => lines.push("Sampling frequency is from STREAMINFO".to_string()),
+    }
+    lines.push(format!(
+        "Channel assignment is {}",
+        header.channel_assignment_name()
+    ));
+    match header.sample_size {
+        Some(bits) => lines.push(format!("Sample size is {} bits", bits)),
+        None => lines.push("Sample size is from STREAMINFO".to_string()),
+    }
+    lines.push(format!(
+        "CRC-16 is {}",
+        if crc_valid { "valid" } else { "mismatch" }
+    ));
+
+    Some(Frame {
+        length,
+        duration: header.duration().unwrap_or(0.0),
+        lines,
+        warnings: Vec::new(),
+        advertised: None,
+    })
+}
+
+fn parse_metadata(data: &[u8]) -> Option<Frame> {
+    let mut lines = Vec::new();
+    let mut pos = FLAC_MARKER.len();
+    loop {
+        let block = flac::parse_metadata_header(data.get(pos..)?)?;
+        let body = pos + METADATA_HEADER_LEN;
+        lines.push(format!(
+            "Metadata block is {}, {} bytes",
+            block.type_name(),
+            block.length
+        ));
+        if block.block_type == 0 {
+            let info = data
+                .get(body..body + block.length)
+                .and_then(flac::parse_stream_info);
+            if let Some(info) = info {
+                lines.push(format!(
+                    "Block size is {} to {} samples",
+                    info.min_block_size, info.max_block_size
+                ));
+                lines.push(format!(
+                    "Frame size is {} to {} bytes",
+                    info.min_frame_size, info.max_frame_size
+                ));
+                lines.push(format!("Sampling frequency is {} Hz", info.sample_rate));
+                lines.push(format!("Channels: {}", info.channels));
+                lines.push(format!("Sample size is {} bits", info.bits_per_sample));
+                lines.push(format!("Total samples: {}", info.total_samples));
+                let md5: String = info
+                    .md5
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+                lines.push(format!("MD5 is {}", md5));
+            }
+        }
+        pos = body + block.length;
+        if block.last || pos >= data.len() {
+            break;
+        }
+    }
+    lines.insert(0, format!("Len is {}", pos));
+
+    Some(Frame {
+        length: pos,
+        duration: 0.0,
+        lines,
+        warnings: Vec::new(),
+        advertised: None,
+    })
+}